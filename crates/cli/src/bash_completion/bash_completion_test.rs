@@ -0,0 +1,96 @@
+use super::*;
+
+#[test]
+fn test_replacement() {
+    assert_eq!(replacement("chec", "checkout"), "checkout");
+    assert_eq!(replacement("--color=al", "always"), "--color=always");
+    assert_eq!(replacement("host:/tm", "/tmp/"), "host:/tmp/");
+    assert_eq!(replacement("x", "other"), "other");
+}
+
+fn words(line: &str) -> Vec<String> {
+    crate::completion::command_words(line)
+}
+
+#[test]
+fn test_complete_with_framework() {
+    let CommandKind::External(bash) =
+        CommandKind::resolve("bash", Path::new("/"), &std::env::vars().collect())
+    else {
+        // nothing to test against, `new` finds no setup either
+        return;
+    };
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().to_path_buf();
+    let framework = dir.join("bash_completion");
+    std::fs::write(
+        &framework,
+        "_fake() {\n\
+           case $2 in\n\
+           --color=*) COMPREPLY=(always never) ;;\n\
+           *) COMPREPLY=($(compgen -W 'checkout cherry-pick --color=' -- \"$2\")) ;;\n\
+           esac\n\
+         }\n\
+         complete -F _fake fake\n",
+    )
+    .unwrap();
+    let completer = BashCompleter::with_setup(Some((bash, framework)));
+    let env = HashMap::new();
+
+    let checkout = completer.complete(&words("fake ch"), &dir, &env);
+    let all = completer.complete(&words("ls | fake "), &dir, &env);
+    let missing = completer.complete(&words("other x"), &dir, &env);
+    let after = completer.complete(&words("fake checkout "), &dir, &env);
+    let colors = completer.complete(&words("fake cherry-pick --color="), &dir, &env);
+    temp.close().unwrap();
+
+    assert_eq!(
+        checkout,
+        Some(vec!["checkout".to_string(), "cherry-pick".to_string()])
+    );
+    assert_eq!(all.map(|all| all.len()), Some(3));
+    assert_eq!(missing, None);
+    assert!(completer.unsupported.borrow().contains("other"));
+    assert_eq!(after.map(|after| after.len()), Some(3));
+    assert_eq!(
+        colors,
+        Some(vec![
+            "--color=always".to_string(),
+            "--color=never".to_string()
+        ])
+    );
+
+    // with bash's directory gone, these can only come from the cache, narrowed to the word
+    assert_eq!(
+        completer.complete(&words("fake che"), &dir, &env),
+        Some(vec!["checkout".to_string(), "cherry-pick".to_string()])
+    );
+    assert_eq!(
+        completer.complete(&words("fake checkout --c"), &dir, &env),
+        Some(vec!["--color=".to_string()])
+    );
+    // past a `/`, `=` or `:`, the word completes to something else, which only bash knows
+    assert_eq!(completer.complete(&words("fake src/"), &dir, &env), None);
+    assert_eq!(
+        completer.complete(&words("fake checkout --color="), &dir, &env),
+        None
+    );
+    // the cache is per place and directory
+    assert_eq!(
+        completer.complete(&words("fake checkout x "), &dir, &env),
+        None
+    );
+    assert_eq!(
+        completer.complete(&words("fake c"), Path::new("/"), &env),
+        None
+    );
+}
+
+#[test]
+fn test_complete_without_bash() {
+    let completer = BashCompleter::with_setup(None);
+    assert_eq!(
+        completer.complete(&words("git ch"), Path::new("/"), &HashMap::new()),
+        None
+    );
+}
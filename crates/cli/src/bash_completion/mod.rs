@@ -0,0 +1,221 @@
+//! Completions of external commands from the bash-completion framework.
+//!
+//! Completers for `git`, `docker` or `kubectl` already exist for bash, so the arguments of a
+//! command are completed by running `bash` with the framework loaded and calling the
+//! completion function registered for the command, like bash does on `Tab`. Without `bash` or
+//! the framework, or for commands that have no completion function, nothing is found and the
+//! REPL completes paths instead.
+
+#[cfg(test)]
+mod bash_completion_test;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::which::CommandKind;
+
+/// Where the bash-completion framework is usually installed.
+const FRAMEWORK_PATHS: &[&str] = &[
+    "/usr/share/bash-completion/bash_completion",
+    "/usr/local/share/bash-completion/bash_completion",
+    "/opt/homebrew/share/bash-completion/bash_completion",
+    "/etc/bash_completion",
+];
+
+/// How long a completion function may run before it is given up on.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The exit status of [`SCRIPT`] when the command has no completion function.
+const NO_COMPLETION: i32 = 2;
+
+/// Loads the framework, then the completion of a command, and prints the completions of the
+/// last word. Arguments: the framework script, then the command line as words.
+const SCRIPT: &str = r#"
+source "$1" >/dev/null 2>&1 || exit 3
+shift
+cmd=$1
+COMP_WORDS=("$@")
+COMP_CWORD=$(($# - 1))
+COMP_LINE="$*"
+COMP_POINT=${#COMP_LINE}
+spec=$(complete -p "$cmd" 2>/dev/null)
+for loader in __load_completion _completion_loader; do
+    if [[ -z $spec ]] && declare -F "$loader" >/dev/null; then
+        "$loader" "$cmd" >/dev/null 2>&1
+        spec=$(complete -p "$cmd" 2>/dev/null)
+    fi
+done
+[[ $spec == *" -F "* ]] || exit 2
+func=${spec##* -F }
+func=${func%% *}
+"$func" "$cmd" "${COMP_WORDS[COMP_CWORD]}" "${COMP_WORDS[COMP_CWORD - 1]}" >/dev/null 2>&1
+(( ${#COMPREPLY[@]} )) && printf '%s\n' "${COMPREPLY[@]}"
+exit 0
+"#;
+
+/// How many places completions are kept for before they are all dropped.
+const CACHE_SIZE: usize = 64;
+
+/// Characters after which a word completes to something else, like the entries of a directory
+/// or the values of `--color=`, so the completions of the text before them don't hold those.
+const WORD_BREAKS: &[char] = &['/', '=', ':'];
+
+/// Where a word is completed: the words before it, starting with the command, and the working
+/// directory.
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    cwd: PathBuf,
+    preceding: Vec<String>,
+}
+
+/// Completions computed for a word, which also hold those of the words it is a prefix of, up
+/// to a [`WORD_BREAKS`] character.
+struct Cached {
+    word: String,
+    completions: Vec<String>,
+}
+
+pub(crate) struct BashCompleter {
+    /// `bash` and the framework script, when both are installed.
+    setup: Option<(PathBuf, PathBuf)>,
+    /// Commands bash has no completion function for.
+    unsupported: RefCell<HashSet<String>>,
+    /// Completions already computed, as the line editor asks again for the same line, e.g. to
+    /// list the candidates, and typing more of a word only narrows them down.
+    cache: RefCell<HashMap<CacheKey, Cached>>,
+}
+
+impl BashCompleter {
+    /// Finds `bash` on the `PATH` of `env` and the framework where it is usually installed.
+    pub(crate) fn new(cwd: &Path, env: &HashMap<String, String>) -> Self {
+        let bash = match CommandKind::resolve("bash", cwd, env) {
+            CommandKind::External(bash) => Some(bash),
+            CommandKind::Builtin | CommandKind::NotFound => None,
+        };
+        let framework = FRAMEWORK_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file());
+        Self::with_setup(bash.zip(framework))
+    }
+
+    fn with_setup(setup: Option<(PathBuf, PathBuf)>) -> Self {
+        if setup.is_none() {
+            log::info!("bash or bash-completion is missing, commands complete paths only");
+        }
+        Self {
+            setup,
+            unsupported: RefCell::default(),
+            cache: RefCell::default(),
+        }
+    }
+
+    /// The completions of the last word of `words`, a command and its arguments up to the
+    /// cursor, each as it would replace that word. `None` when bash has nothing to offer.
+    pub(crate) fn complete(
+        &self,
+        words: &[String],
+        cwd: &Path,
+        env: &HashMap<String, String>,
+    ) -> Option<Vec<String>> {
+        let (bash, framework) = self.setup.as_ref()?;
+        let [command, .., word] = words else {
+            return None;
+        };
+        if self.unsupported.borrow().contains(command) {
+            return None;
+        }
+        let key = CacheKey {
+            cwd: cwd.to_path_buf(),
+            preceding: words[..words.len() - 1].to_vec(),
+        };
+        let cached = self.cache.borrow().get(&key).and_then(|cached| {
+            let added = word.strip_prefix(cached.word.as_str())?;
+            (!added.contains(WORD_BREAKS)).then(|| {
+                cached
+                    .completions
+                    .iter()
+                    .filter(|completion| completion.starts_with(word.as_str()))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+        });
+        if cached.is_some() {
+            return cached;
+        }
+
+        let mut child = Command::new(bash)
+            .args(["--noprofile", "--norc", "-c", SCRIPT, "oxipy"])
+            .arg(framework)
+            .args(words)
+            .current_dir(cwd)
+            .env_clear()
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        // read while bash runs, as it would block on a full pipe
+        let mut stdout = child.stdout.take()?;
+        let reader = std::thread::spawn(move || {
+            let mut output = String::new();
+            stdout.read_to_string(&mut output).map(|_| output)
+        });
+        let started = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if started.elapsed() < TIMEOUT => {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                _ => {
+                    log::warn!("bash took too long to complete `{command}`");
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return None;
+                }
+            }
+        };
+        if status.code() == Some(NO_COMPLETION) {
+            self.unsupported.borrow_mut().insert(command.clone());
+            return None;
+        }
+        let output = reader.join().ok()?.ok()?;
+        if !status.success() {
+            return None;
+        }
+        let completions: Vec<String> = output
+            .lines()
+            .map(|completion| replacement(word, completion.trim_end()))
+            .collect();
+        let mut cache = self.cache.borrow_mut();
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(
+            key,
+            Cached {
+                word: word.clone(),
+                completions: completions.clone(),
+            },
+        );
+        Some(completions)
+    }
+}
+
+/// The text replacing `word` for a completion. Completion functions split words on `=` and
+/// `:` like bash does, so a completion of `--color=al` may be `always`.
+fn replacement(word: &str, completion: &str) -> String {
+    if completion.starts_with(word) {
+        return completion.to_string();
+    }
+    match word.rfind(['=', ':']) {
+        Some(index) => format!("{}{completion}", &word[..=index]),
+        None => completion.to_string(),
+    }
+}
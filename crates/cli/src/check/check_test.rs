@@ -0,0 +1,72 @@
+use super::noqa::Directive;
+use super::*;
+
+fn codes(source: &str) -> Vec<&'static str> {
+    let context = CheckContext {
+        cwd: PathBuf::from("/"),
+        env: HashMap::from([("DEFINED".to_string(), "1".to_string())]),
+    };
+    check_source(source, &context)
+        .iter()
+        .map(|diagnostic| diagnostic.rule.code())
+        .collect()
+}
+
+#[test]
+fn test_noqa_directive() {
+    assert_eq!(Directive::try_from_comment("# noqa"), Some(Directive::All));
+    assert_eq!(
+        Directive::try_from_comment("# why # NOQA: OX001, OX002"),
+        Some(Directive::Codes(vec!["OX001".into(), "OX002".into()]))
+    );
+    assert_eq!(Directive::try_from_comment("# noqanot"), None);
+    assert_eq!(Directive::try_from_comment("# plain comment"), None);
+}
+
+#[test]
+fn test_command_not_found() {
+    assert_eq!(codes("$[nosuchcmd]"), ["OX001"]);
+    assert!(codes("$[echo hello]").is_empty());
+}
+
+#[test]
+fn test_unquoted_glob() {
+    assert_eq!(codes("$[echo *.py]"), ["OX002"]);
+    assert!(codes("$[echo '*.py']").is_empty());
+}
+
+#[test]
+fn test_redirect_clobbers_input() {
+    assert_eq!(codes("$[cat data.txt > data.txt]"), ["OX003"]);
+    assert!(codes("$[cat data.txt > out.txt]").is_empty());
+}
+
+#[test]
+fn test_undefined_env_var() {
+    assert_eq!(codes("print($UNDEFINED)"), ["OX004"]);
+    assert!(codes("print($DEFINED)").is_empty());
+    assert!(codes("export UNDEFINED=1\nprint($UNDEFINED)").is_empty());
+}
+
+#[test]
+fn test_captured_output_as_bool() {
+    assert_eq!(codes("if $(echo hi):\n    pass\n"), ["OX005"]);
+    assert_eq!(codes("x = not $(echo hi)"), ["OX005"]);
+    assert!(codes("x = $(echo hi)").is_empty());
+}
+
+#[test]
+fn test_noqa_suppression() {
+    assert!(codes("$[echo *.py]  # noqa").is_empty());
+    assert!(codes("$[echo *.py]  # noqa: OX002").is_empty());
+    assert_eq!(codes("$[echo *.py]  # noqa: OX001"), ["OX002"]);
+}
+
+#[test]
+fn test_rule_selection() {
+    let select = vec!["OX00".to_string()];
+    let ignore = vec!["OX002".to_string()];
+    assert!(Rule::CommandNotFound.is_enabled(&select, &ignore));
+    assert!(!Rule::UnquotedGlob.is_enabled(&select, &ignore));
+    assert!(!Rule::UnquotedGlob.is_enabled(&["OX001".to_string()], &[]));
+}
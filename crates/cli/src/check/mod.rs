@@ -0,0 +1,205 @@
+//! `oxipy check`: static checks specific to shell-flavoured Python.
+
+#[cfg(test)]
+mod check_test;
+
+mod noqa;
+mod rules;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ruff_python_ast::PySourceType;
+use ruff_source_file::{LineIndex, SourceCode};
+use ruff_text_size::{Ranged, TextRange};
+use serde::Serialize;
+
+pub(crate) use rules::Rule;
+
+/// File extensions picked up when a directory is passed to `oxipy check` or `--rc`.
+pub(crate) const SOURCE_EXTENSIONS: &[&str] = &["oxy", "xsh"];
+
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    /// Files or directories to check
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Output format of the diagnostics
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output_format: OutputFormat,
+
+    /// Only report the given rule codes, e.g. --select OX001,OX002
+    #[arg(long, value_delimiter = ',')]
+    pub select: Vec<String>,
+
+    /// Do not report the given rule codes
+    #[arg(long, value_delimiter = ',')]
+    pub ignore: Vec<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// The environment the checks are evaluated against.
+pub(crate) struct CheckContext {
+    pub(crate) cwd: PathBuf,
+    pub(crate) env: HashMap<String, String>,
+}
+
+impl CheckContext {
+    pub(crate) fn from_process() -> Result<Self> {
+        Ok(Self {
+            cwd: std::env::current_dir()?,
+            env: std::env::vars().collect(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    pub(crate) rule: Rule,
+    pub(crate) message: String,
+    pub(crate) range: TextRange,
+}
+
+impl Ranged for Diagnostic {
+    fn range(&self) -> TextRange {
+        self.range
+    }
+}
+
+/// Runs all checks on `source` and returns the diagnostics that are not suppressed by `# noqa`.
+pub(crate) fn check_source(source: &str, context: &CheckContext) -> Vec<Diagnostic> {
+    let parsed = ruff_python_parser::parse_unchecked_source(source, PySourceType::Python);
+
+    let mut diagnostics: Vec<Diagnostic> = parsed
+        .errors()
+        .iter()
+        .map(|error| Diagnostic {
+            rule: Rule::SyntaxError,
+            message: error.error.to_string(),
+            range: error.location,
+        })
+        .collect();
+
+    if parsed.is_valid() {
+        diagnostics.extend(rules::Checker::check(parsed.suite(), source, context));
+    }
+
+    let line_index = LineIndex::from_source_text(source);
+    let source_code = SourceCode::new(source, &line_index);
+    let noqa = noqa::NoqaMapping::from_tokens(parsed.tokens(), &source_code);
+    diagnostics.retain(|diagnostic| {
+        !noqa.suppresses(source_code.line_index(diagnostic.start()), diagnostic.rule)
+    });
+    diagnostics.sort_by_key(|diagnostic| diagnostic.start());
+    diagnostics
+}
+
+#[derive(Serialize)]
+struct Location {
+    row: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    code: &'static str,
+    name: &'static str,
+    message: &'a str,
+    filename: String,
+    location: Location,
+    end_location: Location,
+}
+
+/// Entry point of `oxipy check`. Returns the process exit code.
+pub(crate) fn run(args: &CheckArgs, out: &mut dyn Write) -> Result<i32> {
+    let context = CheckContext::from_process()?;
+    let mut files = Vec::new();
+    for path in &args.files {
+        collect_files(path, &mut files)?;
+    }
+
+    let mut total = 0;
+    let mut json = Vec::new();
+    for file in &files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read {}", file.display()))?;
+        let diagnostics: Vec<Diagnostic> = check_source(&source, &context)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.rule.is_enabled(&args.select, &args.ignore))
+            .collect();
+        total += diagnostics.len();
+
+        let line_index = LineIndex::from_source_text(&source);
+        let source_code = SourceCode::new(&source, &line_index);
+        for diagnostic in &diagnostics {
+            let start = source_code.source_location(diagnostic.start());
+            match args.output_format {
+                OutputFormat::Text => writeln!(
+                    out,
+                    "{}:{}:{}: {} {}",
+                    file.display(),
+                    start.row,
+                    start.column,
+                    diagnostic.rule.code(),
+                    diagnostic.message
+                )?,
+                OutputFormat::Json => {
+                    let end = source_code.source_location(diagnostic.end());
+                    json.push(serde_json::to_value(JsonDiagnostic {
+                        code: diagnostic.rule.code(),
+                        name: diagnostic.rule.name(),
+                        message: &diagnostic.message,
+                        filename: file.display().to_string(),
+                        location: Location {
+                            row: start.row.get(),
+                            column: start.column.get(),
+                        },
+                        end_location: Location {
+                            row: end.row.get(),
+                            column: end.column.get(),
+                        },
+                    })?);
+                }
+            }
+        }
+    }
+
+    match args.output_format {
+        OutputFormat::Text if total > 0 => writeln!(out, "Found {total} error(s).")?,
+        OutputFormat::Text => {}
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&json)?)?,
+    }
+    Ok(i32::from(total > 0))
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext))
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
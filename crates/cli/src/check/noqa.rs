@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use ruff_python_parser::{TokenKind, Tokens};
+use ruff_source_file::{OneIndexed, SourceCode};
+use ruff_text_size::Ranged;
+
+use super::Rule;
+
+/// A `# noqa` comment found on a line.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Directive {
+    /// `# noqa`: suppresses every diagnostic on the line.
+    All,
+    /// `# noqa: OX001, OX002`: suppresses the listed codes.
+    Codes(Vec<String>),
+}
+
+impl Directive {
+    /// Parses the directive out of a comment like `# some text  # noqa: OX001`.
+    pub(super) fn try_from_comment(comment: &str) -> Option<Self> {
+        comment.split('#').skip(1).find_map(|segment| {
+            let segment = segment.trim_start();
+            let prefix = segment.get(..4)?;
+            if !prefix.eq_ignore_ascii_case("noqa") {
+                return None;
+            }
+            let rest = &segment[4..];
+            match rest.strip_prefix(':') {
+                Some(codes) => Some(Directive::Codes(
+                    codes
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .map(str::trim)
+                        .filter(|code| !code.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                )),
+                None if rest.trim().is_empty() => Some(Directive::All),
+                None => None,
+            }
+        })
+    }
+
+    fn suppresses(&self, rule: Rule) -> bool {
+        match self {
+            Directive::All => true,
+            Directive::Codes(codes) => codes.iter().any(|code| code == rule.code()),
+        }
+    }
+}
+
+/// `# noqa` directives of a file, keyed by line.
+#[derive(Debug, Default)]
+pub(super) struct NoqaMapping {
+    directives: HashMap<OneIndexed, Directive>,
+}
+
+impl NoqaMapping {
+    pub(super) fn from_tokens(tokens: &Tokens, source: &SourceCode) -> Self {
+        let directives = tokens
+            .iter()
+            .filter(|token| token.kind() == TokenKind::Comment)
+            .filter_map(|token| {
+                let directive = Directive::try_from_comment(source.slice(token.range()))?;
+                Some((source.line_index(token.start()), directive))
+            })
+            .collect();
+        Self { directives }
+    }
+
+    pub(super) fn suppresses(&self, line: OneIndexed, rule: Rule) -> bool {
+        // syntax errors can't be silenced
+        rule != Rule::SyntaxError
+            && self
+                .directives
+                .get(&line)
+                .is_some_and(|directive| directive.suppresses(rule))
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashSet;
+
+use ruff_python_ast::subproc::{
+    CaptureKind, EnvVarRef, GLOB_CHARS, Pipeline, RedirectKind, unquoted_word,
+};
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{self as ast, Expr, ExprContext, Stmt};
+use ruff_text_size::{Ranged, TextRange};
+
+use super::{CheckContext, Diagnostic};
+use crate::which::CommandKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Rule {
+    SyntaxError,
+    CommandNotFound,
+    UnquotedGlob,
+    RedirectClobbersInput,
+    UndefinedEnvVar,
+    CapturedOutputAsBool,
+}
+
+impl Rule {
+    pub(crate) const fn code(self) -> &'static str {
+        match self {
+            Rule::SyntaxError => "OX000",
+            Rule::CommandNotFound => "OX001",
+            Rule::UnquotedGlob => "OX002",
+            Rule::RedirectClobbersInput => "OX003",
+            Rule::UndefinedEnvVar => "OX004",
+            Rule::CapturedOutputAsBool => "OX005",
+        }
+    }
+
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Rule::SyntaxError => "syntax-error",
+            Rule::CommandNotFound => "command-not-found",
+            Rule::UnquotedGlob => "unquoted-glob",
+            Rule::RedirectClobbersInput => "redirect-clobbers-input",
+            Rule::UndefinedEnvVar => "undefined-env-var",
+            Rule::CapturedOutputAsBool => "captured-output-as-bool",
+        }
+    }
+
+    /// Whether the rule passes `--select`/`--ignore`. Codes match by prefix, so `OX` selects all.
+    pub(crate) fn is_enabled(self, select: &[String], ignore: &[String]) -> bool {
+        let code = self.code();
+        let matches = |prefix: &String| code.starts_with(prefix.trim());
+        (select.is_empty() || select.iter().any(matches)) && !ignore.iter().any(matches)
+    }
+}
+
+pub(crate) struct Checker<'a> {
+    source: &'a str,
+    context: &'a CheckContext,
+    diagnostics: Vec<Diagnostic>,
+    env_reads: Vec<(&'a str, TextRange)>,
+    env_writes: HashSet<&'a str>,
+}
+
+impl<'a> Checker<'a> {
+    pub(crate) fn check(
+        suite: &'a [Stmt],
+        source: &'a str,
+        context: &'a CheckContext,
+    ) -> Vec<Diagnostic> {
+        let mut checker = Checker {
+            source,
+            context,
+            diagnostics: Vec::new(),
+            env_reads: Vec::new(),
+            env_writes: HashSet::new(),
+        };
+        checker.visit_body(suite);
+        checker.check_env_reads();
+        checker.diagnostics
+    }
+
+    fn report(&mut self, rule: Rule, message: String, range: TextRange) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            message,
+            range,
+        });
+    }
+
+    fn check_pipeline(&mut self, pipeline: &Pipeline<'a>) {
+        let mut reads = Vec::new();
+        for stage in &pipeline.stages {
+            if let Some(program) = stage.program() {
+                self.check_program(program, stage.program_expr().unwrap().range());
+                if program == "export" {
+                    self.collect_exports(stage.words());
+                }
+            }
+            for word in stage.words().iter().skip(1) {
+                if let Some(text) = unquoted_word(word, self.source) {
+                    if text.contains(GLOB_CHARS) {
+                        self.report(
+                            Rule::UnquotedGlob,
+                            format!(
+                                "Unquoted argument `{text}` contains glob characters and will be expanded; quote it to pass it literally"
+                            ),
+                            word.range(),
+                        );
+                    }
+                    if !text.starts_with('-') {
+                        reads.push(normalize_path(text));
+                    }
+                }
+            }
+            for redirect in stage.redirects() {
+                if redirect.kind == RedirectKind::Read {
+                    if let Some(target) = literal_str(redirect.target) {
+                        reads.push(normalize_path(target));
+                    }
+                }
+            }
+        }
+
+        for stage in &pipeline.stages {
+            for redirect in stage.redirects() {
+                if redirect.kind == RedirectKind::Read {
+                    continue;
+                }
+                let Some(target) = literal_str(redirect.target) else {
+                    continue;
+                };
+                if reads.contains(&normalize_path(target)) {
+                    self.report(
+                        Rule::RedirectClobbersInput,
+                        format!("`{target}` is written by a redirect while it is read by the same pipeline"),
+                        redirect.target.range(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_program(&mut self, program: &str, range: TextRange) {
+        let kind = CommandKind::resolve(program, &self.context.cwd, &self.context.env);
+        if kind == CommandKind::NotFound {
+            self.report(
+                Rule::CommandNotFound,
+                format!("Command `{program}` is not a builtin and was not found on `PATH`"),
+                range,
+            );
+        }
+    }
+
+    /// Records the names set by `export NAME=VALUE`.
+    fn collect_exports(&mut self, words: &'a [Expr]) {
+        for word in words.iter().skip(1) {
+            if let Some(text) = literal_str(word) {
+                let name = text.split_once('=').map_or(text, |(name, _)| name);
+                self.env_writes.insert(name);
+            }
+        }
+    }
+
+    fn check_env_reads(&mut self) {
+        let reads = std::mem::take(&mut self.env_reads);
+        for (name, range) in reads {
+            if !self.env_writes.contains(name) && !self.context.env.contains_key(name) {
+                self.report(
+                    Rule::UndefinedEnvVar,
+                    format!("`${name}` is used but never exported"),
+                    range,
+                );
+            }
+        }
+    }
+
+    /// Reports captured outputs (`$(...)`) used where only their truthiness matters.
+    ///
+    /// Operands of `not` are handled by [`Visitor::visit_expr`] so they are reported once.
+    fn check_bool_context(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::BoolOp(ast::ExprBoolOp { values, .. }) => {
+                for value in values {
+                    self.check_bool_context(value);
+                }
+            }
+            _ => {
+                if Pipeline::from_expr(expr).is_some_and(|p| p.capture == CaptureKind::Out) {
+                    self.report(
+                        Rule::CapturedOutputAsBool,
+                        "Captured output `$(...)` is used as a boolean; use `!(...)` to test the exit status".to_string(),
+                        expr.range(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Checker<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::If(ast::StmtIf { test, .. })
+            | Stmt::While(ast::StmtWhile { test, .. })
+            | Stmt::Assert(ast::StmtAssert { test, .. }) => self.check_bool_context(test),
+            _ => {}
+        }
+        visitor::walk_stmt(self, stmt);
+    }
+
+    fn visit_elif_else_clause(&mut self, clause: &'a ast::ElifElseClause) {
+        if let Some(test) = &clause.test {
+            self.check_bool_context(test);
+        }
+        visitor::walk_elif_else_clause(self, clause);
+    }
+
+    fn visit_comprehension(&mut self, comprehension: &'a ast::Comprehension) {
+        for condition in &comprehension.ifs {
+            self.check_bool_context(condition);
+        }
+        visitor::walk_comprehension(self, comprehension);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::If(ast::ExprIf { test, .. }) => self.check_bool_context(test),
+            Expr::UnaryOp(ast::ExprUnaryOp {
+                op: ast::UnaryOp::Not,
+                operand,
+                ..
+            }) => self.check_bool_context(operand),
+            _ => {}
+        }
+        if let Some(pipeline) = Pipeline::from_expr(expr) {
+            self.check_pipeline(&pipeline);
+        }
+        if let Some(env_var) = EnvVarRef::from_expr(expr) {
+            if let Some(name) = env_var.name {
+                match env_var.ctx {
+                    ExprContext::Load => self.env_reads.push((name, env_var.range())),
+                    _ => {
+                        self.env_writes.insert(name);
+                    }
+                }
+            }
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
+fn literal_str(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::StringLiteral(literal) => Some(literal.value.to_str()),
+        _ => None,
+    }
+}
+
+fn normalize_path(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
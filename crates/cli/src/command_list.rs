@@ -0,0 +1,147 @@
+//! `cmdgroup` lists built from evaluated words, shared by `ox.cmd(...)` and the session.
+//!
+//! The words are passed to `cmdgroup` as quoted strings, so they are never expanded again, and
+//! commands are only separated where the parser put `ox.AND`, `ox.OR` or `ox.THEN`, never on a
+//! word that happens to read `&&`.
+
+use anyhow::{Result, anyhow, bail};
+use cmdgroup::parser::{
+    BooleanList, BooleanListOperator, Command, CommandInner, IoFile, PipeSequence,
+    PipeSequenceOperator, Pipeline, PipelineInner, Redirect, RedirectFd, RedirectOp, Sequence,
+    SequentialList, SequentialListItem, SimpleCommand, Word,
+};
+
+/// What joins a command to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    And,
+    Or,
+    Then,
+    Pipe,
+}
+
+/// Builds a list from commands in order: `;` separates, then `&&`/`||`, then `|`.
+#[derive(Default)]
+pub struct ListBuilder {
+    commands: Vec<(Command, Option<Operator>)>,
+}
+
+impl ListBuilder {
+    /// Adds the command of `words` followed by `op`, or ending the list when it is `None`.
+    ///
+    /// An empty command is skipped before a trailing `;` and an error anywhere else.
+    pub fn push(
+        &mut self,
+        words: &[String],
+        redirects: &[Redirect],
+        op: Option<Operator>,
+    ) -> Result<()> {
+        if words.is_empty() {
+            return match op {
+                Some(Operator::Then) | None if redirects.is_empty() => Ok(()),
+                _ => Err(anyhow!("empty command in subprocess")),
+            };
+        }
+        // `cmdgroup` takes one redirect per command, so the others wrap it in subshells; those
+        // between streams go innermost, which makes `> out.txt e>o` send both to the file.
+        let mut redirects = redirects.to_vec();
+        redirects.sort_by_key(|redirect| matches!(redirect.io_file, IoFile::Word(_)));
+        let mut redirects = redirects.into_iter();
+        let mut command = Command {
+            inner: CommandInner::Simple(SimpleCommand {
+                env_vars: Vec::new(),
+                args: words.iter().map(|word| Word::new_string(word)).collect(),
+            }),
+            redirect: redirects.next(),
+        };
+        for redirect in redirects {
+            command = Command {
+                inner: CommandInner::Subshell(Box::new(SequentialList {
+                    items: vec![SequentialListItem {
+                        is_async: false,
+                        sequence: command.into(),
+                    }],
+                })),
+                redirect: Some(redirect),
+            };
+        }
+        self.commands.push((command, op));
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<SequentialList> {
+        let mut items = Vec::new();
+        let mut sequence: Option<(Sequence, BooleanListOperator)> = None;
+        let mut pipeline = Vec::new();
+        for (command, op) in self.commands {
+            pipeline.push(command);
+            let boolean = match op {
+                Some(Operator::Pipe) => continue,
+                Some(Operator::And) => Some(BooleanListOperator::And),
+                Some(Operator::Or) => Some(BooleanListOperator::Or),
+                Some(Operator::Then) | None => None,
+            };
+            let next = pipe_sequence(std::mem::take(&mut pipeline));
+            let current = match sequence.take() {
+                Some((current, op)) => {
+                    Sequence::BooleanList(Box::new(BooleanList { current, op, next }))
+                }
+                None => next,
+            };
+            match boolean {
+                Some(op) => sequence = Some((current, op)),
+                None => items.push(SequentialListItem {
+                    is_async: false,
+                    sequence: current,
+                }),
+            }
+        }
+        if sequence.is_some() || items.is_empty() {
+            bail!("incomplete command in subprocess");
+        }
+        Ok(SequentialList { items })
+    }
+}
+
+fn pipe_sequence(commands: Vec<Command>) -> Sequence {
+    let mut commands = commands.into_iter().rev();
+    let last = commands.next().expect("a pipeline has a command");
+    let inner = commands.fold(PipelineInner::Command(last), |next, current| {
+        PipeSequence {
+            current,
+            op: PipeSequenceOperator::Stdout,
+            next,
+        }
+        .into()
+    });
+    Pipeline {
+        negated: false,
+        inner,
+    }
+    .into()
+}
+
+/// Converts one `{source: target}` item of the `writes`, `appends` or `reads` keywords.
+pub fn redirect(source: &str, target: &str, op: RedirectOp) -> Result<Redirect> {
+    let maybe_fd = match source {
+        "" => None,
+        "o" | "out" | "1" => Some(RedirectFd::Fd(1)),
+        "e" | "err" | "2" => Some(RedirectFd::Fd(2)),
+        "a" | "all" | "&" => Some(RedirectFd::StdoutStderr),
+        fd => Some(RedirectFd::Fd(
+            fd.parse()
+                .map_err(|_| anyhow!("unknown redirect source {fd:?}"))?,
+        )),
+    };
+    // `e>o` joins the streams, while a plain `> o` writes to a file named `o`.
+    let io_file = match target {
+        "o" | "out" if maybe_fd.is_some() => IoFile::Fd(1),
+        "e" | "err" if maybe_fd.is_some() => IoFile::Fd(2),
+        _ => IoFile::Word(Word::new_string(target)),
+    };
+    Ok(Redirect {
+        maybe_fd,
+        op,
+        io_file,
+    })
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+#[test]
+fn test_context_from_source() {
+    assert_eq!(
+        CompletionContext::from_source("ec"),
+        CompletionContext::Name("ec")
+    );
+    assert_eq!(
+        CompletionContext::from_source("x = os.pa"),
+        CompletionContext::Python("os.pa")
+    );
+    assert_eq!(
+        CompletionContext::from_source("print(len(na"),
+        CompletionContext::Python("na")
+    );
+    assert_eq!(
+        CompletionContext::from_source("  os."),
+        CompletionContext::Python("os.")
+    );
+    assert_eq!(
+        CompletionContext::from_source("x = $(ec"),
+        CompletionContext::Command("ec")
+    );
+    assert_eq!(
+        CompletionContext::from_source("ls | gr"),
+        CompletionContext::Command("gr")
+    );
+    assert_eq!(
+        CompletionContext::from_source("$[ls src/ma"),
+        CompletionContext::Argument("src/ma")
+    );
+    assert_eq!(
+        CompletionContext::from_source("ls sr"),
+        CompletionContext::Argument("sr")
+    );
+    assert_eq!(
+        CompletionContext::from_source("cat ~/.bash"),
+        CompletionContext::Argument("~/.bash")
+    );
+    assert_eq!(
+        CompletionContext::from_source("print($HO"),
+        CompletionContext::EnvVar("HO")
+    );
+}
+
+#[test]
+fn test_word() {
+    assert_eq!(CompletionContext::from_source("echo $HO").word(), "HO");
+    assert_eq!(CompletionContext::from_source("os.pa").word(), "os.pa");
+}
+
+#[test]
+fn test_command_words() {
+    assert_eq!(command_words("git chec"), ["git", "chec"]);
+    assert_eq!(command_words("ls | git commit "), ["git", "commit", ""]);
+    assert_eq!(command_words("x = $(docker  ps -"), ["docker", "ps", "-"]);
+    assert_eq!(command_words(""), [""]);
+}
@@ -0,0 +1,298 @@
+//! Completion candidates shared by the language server and the REPL.
+
+#[cfg(test)]
+mod completion_test;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use ruff_python_ast::subproc::Pipeline;
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, PySourceType};
+use ruff_text_size::{Ranged, TextSize};
+
+use crate::which::BUILTINS;
+
+/// Text before which a word is in command position.
+const COMMAND_STARTERS: &[&str] = &["|", ";", "&&", "||", "$(", "$[", "!(", "![", "@$("];
+
+/// Stands for the word being completed when the line is parsed, so that `ls ` parses like
+/// `ls x` would.
+const WORD_PLACEHOLDER: &str = "_";
+
+/// Characters that end the word being completed.
+const WORD_BREAKS: &[char] = &[' ', '\t', '(', '[', '{', '|', ';', '&', '=', ',', '"', '\''];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CandidateKind {
+    Builtin,
+    Executable,
+    Directory,
+    File,
+    EnvVar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Candidate {
+    /// The text that replaces the word being completed.
+    pub(crate) text: String,
+    pub(crate) kind: CandidateKind,
+    /// Extra information, e.g. the resolved path or the variable value.
+    pub(crate) detail: Option<String>,
+}
+
+/// What the word under the cursor is expected to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CompletionContext<'a> {
+    /// A program name, e.g. after `$(` or `|`.
+    Command(&'a str),
+    /// A command argument, completed as a path.
+    Argument(&'a str),
+    /// An environment variable after `$`, without the `$`.
+    EnvVar(&'a str),
+    /// A word starting a statement, which may be a command or a Python name.
+    Name(&'a str),
+    /// A Python name or attribute, e.g. `os.pa`.
+    Python(&'a str),
+}
+
+impl<'a> CompletionContext<'a> {
+    /// Guesses the context from the text of the current line up to the cursor.
+    pub(crate) fn from_line(line: &'a str) -> Self {
+        let word_start = line.rfind(WORD_BREAKS).map_or(0, |index| index + 1);
+        let word = &line[word_start..];
+        if let Some(name) = word.strip_prefix('$') {
+            return CompletionContext::EnvVar(name);
+        }
+        let before = line[..word_start].trim_end();
+        if before.is_empty()
+            || COMMAND_STARTERS
+                .iter()
+                .any(|starter| before.ends_with(starter))
+        {
+            CompletionContext::Command(word)
+        } else {
+            CompletionContext::Argument(word)
+        }
+    }
+
+    /// Like [`from_line`](Self::from_line), but uses the parse tree to tell commands from
+    /// Python code.
+    ///
+    /// Outside of subprocess expressions, the word is a Python name, or a [`Name`](Self::Name)
+    /// that may also be a command when it starts the statement. A word after `|` or `$(` is
+    /// still a command, and words that can't be Python, like `-v` or `src/ma`, are still
+    /// arguments.
+    pub(crate) fn from_source(line: &'a str) -> Self {
+        let context = Self::from_line(line);
+        let shell_word = match context {
+            CompletionContext::EnvVar(_) => return context,
+            // after `|` or `$(`, rather than at the start of the line
+            CompletionContext::Command(word)
+                if !line[..line.len() - word.len()].trim().is_empty() =>
+            {
+                return context;
+            }
+            CompletionContext::Command(word)
+            | CompletionContext::Argument(word)
+            | CompletionContext::Name(word)
+            | CompletionContext::Python(word) => word,
+        };
+        if in_pipeline(line) {
+            return context;
+        }
+        let word_start = line
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+            .map_or(0, |index| {
+                index + line[index..].chars().next().map_or(1, char::len_utf8)
+            });
+        let word = &line[word_start..];
+        if word.len() < shell_word.len()
+            && (shell_word.starts_with(['-', '~', '.', '/']) || shell_word.contains('/'))
+        {
+            return context;
+        }
+        if line[..word_start].trim().is_empty() && !word.contains('.') {
+            CompletionContext::Name(word)
+        } else {
+            CompletionContext::Python(word)
+        }
+    }
+
+    /// The text the candidates replace.
+    pub(crate) fn word(&self) -> &'a str {
+        match *self {
+            CompletionContext::Command(word)
+            | CompletionContext::Argument(word)
+            | CompletionContext::EnvVar(word)
+            | CompletionContext::Name(word)
+            | CompletionContext::Python(word) => word,
+        }
+    }
+
+    /// The candidates found without an interpreter: Python names come from the namespace of a
+    /// running session, so there are none for [`Python`](Self::Python).
+    pub(crate) fn candidates(&self, cwd: &Path, env: &HashMap<String, String>) -> Vec<Candidate> {
+        match *self {
+            CompletionContext::Command(prefix) | CompletionContext::Name(prefix)
+                if !prefix.contains('/') =>
+            {
+                commands(prefix, env)
+            }
+            CompletionContext::Name(_) | CompletionContext::Python(_) => Vec::new(),
+            CompletionContext::Command(prefix) | CompletionContext::Argument(prefix) => {
+                paths(prefix, cwd, env)
+            }
+            CompletionContext::EnvVar(prefix) => env_vars(prefix, env),
+        }
+    }
+}
+
+/// The words of the command that the end of `line` is in, split on whitespace. The last one is
+/// the word being completed, empty after a space.
+pub(crate) fn command_words(line: &str) -> Vec<String> {
+    let start = COMMAND_STARTERS
+        .iter()
+        .filter_map(|starter| line.rfind(starter).map(|index| index + starter.len()))
+        .max()
+        .unwrap_or(0);
+    let command = &line[start..];
+    let mut words: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+    if words.is_empty() || command.ends_with(char::is_whitespace) {
+        words.push(String::new());
+    }
+    words
+}
+
+/// Whether the end of `line` is inside a subprocess expression, the word there included.
+fn in_pipeline(line: &str) -> bool {
+    let source = format!("{line}{WORD_PLACEHOLDER}");
+    let parsed = ruff_python_parser::parse_unchecked_source(&source, PySourceType::Python);
+    let mut finder = PipelineFinder {
+        offset: TextSize::of(line),
+        found: false,
+    };
+    finder.visit_body(parsed.suite());
+    finder.found
+}
+
+/// Looks for a pipeline around `offset`.
+struct PipelineFinder {
+    offset: TextSize,
+    found: bool,
+}
+
+impl<'a> Visitor<'a> for PipelineFinder {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if self.found || !expr.range().contains_inclusive(self.offset) {
+            return;
+        }
+        if Pipeline::from_expr(expr).is_some() {
+            self.found = true;
+            return;
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
+/// Builtins and executables on `PATH` starting with `prefix`.
+pub(crate) fn commands(prefix: &str, env: &HashMap<String, String>) -> Vec<Candidate> {
+    let mut found = BTreeMap::new();
+    for name in BUILTINS.iter().filter(|name| name.starts_with(prefix)) {
+        found.insert(name.clone(), (CandidateKind::Builtin, None));
+    }
+    let path_var = env.get("PATH").map(String::as_str).unwrap_or_default();
+    for dir in std::env::split_paths(path_var) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !name.starts_with(prefix) || found.contains_key(&name) {
+                continue;
+            }
+            let path = entry.path();
+            if is_executable(&path) {
+                let detail = Some(path.display().to_string());
+                found.insert(name, (CandidateKind::Executable, detail));
+            }
+        }
+    }
+    found
+        .into_iter()
+        .map(|(text, (kind, detail))| Candidate { text, kind, detail })
+        .collect()
+}
+
+/// Files and directories matching `prefix`, resolved against `cwd`.
+///
+/// Hidden entries are only offered when the prefix starts with a `.`.
+pub(crate) fn paths(prefix: &str, cwd: &Path, env: &HashMap<String, String>) -> Vec<Candidate> {
+    let (dir_part, file_part) = prefix
+        .rfind('/')
+        .map_or(("", prefix), |index| prefix.split_at(index + 1));
+    let dir = match dir_part.strip_prefix('~') {
+        Some(rest) => match env.get("HOME") {
+            Some(home) => Path::new(home).join(rest.trim_start_matches('/')),
+            None => return Vec::new(),
+        },
+        None if dir_part.is_empty() => cwd.to_path_buf(),
+        None => cwd.join(dir_part),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<Candidate> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(file_part)
+                || (name.starts_with('.') && !file_part.starts_with('.'))
+            {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(Candidate {
+                text: format!("{dir_part}{name}{}", if is_dir { "/" } else { "" }),
+                kind: if is_dir {
+                    CandidateKind::Directory
+                } else {
+                    CandidateKind::File
+                },
+                detail: None,
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.text.cmp(&b.text));
+    candidates
+}
+
+/// Environment variables whose name starts with `prefix`.
+pub(crate) fn env_vars(prefix: &str, env: &HashMap<String, String>) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = env
+        .iter()
+        .filter(|(name, _)| name.starts_with(prefix))
+        .map(|(name, value)| Candidate {
+            text: name.clone(),
+            kind: CandidateKind::EnvVar,
+            detail: Some(value.clone()),
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.text.cmp(&b.text));
+    candidates
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
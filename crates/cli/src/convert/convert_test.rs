@@ -0,0 +1,140 @@
+use super::lines::{heredoc_delimiter, logical_lines};
+use super::*;
+
+fn convert(script: &str) -> String {
+    convert_script(script).source
+}
+
+fn reasons(script: &str) -> Vec<(usize, String)> {
+    convert_script(script)
+        .unsupported
+        .into_iter()
+        .map(|unsupported| (unsupported.line, unsupported.reason))
+        .collect()
+}
+
+#[test]
+fn test_logical_lines() {
+    let lines = logical_lines("a \\\n  b  # note\nx=$(\n  echo a\n  echo b\n)\n");
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].number, 1);
+    assert_eq!(lines[0].code, "a   b  ");
+    assert_eq!(lines[0].comment.as_deref(), Some("# note"));
+    assert_eq!(lines[1].number, 3);
+    assert_eq!(lines[1].code, "x=$(echo a; echo b)");
+}
+
+#[test]
+fn test_heredoc_delimiter() {
+    assert_eq!(heredoc_delimiter("cat <<EOF"), Some("EOF".into()));
+    assert_eq!(heredoc_delimiter("cat <<- 'END' > out"), Some("END".into()));
+    assert_eq!(heredoc_delimiter("cat <<< word"), None);
+    assert_eq!(heredoc_delimiter("echo hi"), None);
+}
+
+#[test]
+fn test_assignments() {
+    assert_eq!(convert("NAME=world\n"), "$NAME = 'world'\n");
+    assert_eq!(
+        convert("OUT=$(git rev-parse HEAD)\n"),
+        "$OUT = $(git rev-parse HEAD).rstrip('\\n')\n"
+    );
+    assert_eq!(
+        convert("GREETING=\"hi $NAME\"\n"),
+        "$GREETING = 'hi ' + $NAME\n"
+    );
+}
+
+#[test]
+fn test_commands() {
+    assert_eq!(convert("ls -la *.py\n"), "$[ls -la *.py]\n");
+    assert_eq!(
+        convert("echo \"hello $NAME\"\n"),
+        "$[echo @('hello ' + $NAME)]\n"
+    );
+    assert_eq!(convert("echo \"$1\"\n"), "$[echo @($ARGS[1])]\n");
+    assert_eq!(
+        convert("echo \"failed: $?\"\n"),
+        "$[echo @('failed: ' + str($?))]\n"
+    );
+    assert_eq!(convert("sleep 10 &\n"), "$[sleep 10 &]\n");
+}
+
+#[test]
+fn test_pipes_and_redirects() {
+    assert_eq!(
+        convert("ls | grep foo >> log.txt\n"),
+        "$[ls | grep foo >> log.txt]\n"
+    );
+    assert_eq!(convert("cp a b 2> err.log\n"), "$[cp a b 2> err.log]\n");
+    assert_eq!(
+        convert("grep x f |& tee log\n"),
+        "$[grep x f e>o | tee log]\n"
+    );
+    assert_eq!(convert("cmd >&2\n"), "$[cmd o>e]\n");
+}
+
+#[test]
+fn test_boolean_lists() {
+    assert_eq!(
+        convert("make && echo ok || echo no\n"),
+        "$[make && echo ok || echo no]\n"
+    );
+    assert_eq!(convert("cd /tmp; ls\n"), "$[cd /tmp]\n$[ls]\n");
+}
+
+#[test]
+fn test_env_prefix_and_subshell() {
+    assert_eq!(
+        convert("FOO=1 BAR=\"x y\" env\n"),
+        "$[env FOO=1 'BAR=x y' env]\n"
+    );
+    assert_eq!(
+        convert("(cd /tmp; ls)\n"),
+        "$[oxipy -c '$[cd /tmp]\\n$[ls]']\n"
+    );
+}
+
+#[test]
+fn test_comments_and_shebang() {
+    assert_eq!(
+        convert("#!/bin/sh\n# setup\n\necho hi  # greet\n"),
+        "#!/usr/bin/env oxipy\n# setup\n\n$[echo hi]  # greet\n"
+    );
+    assert_eq!(convert("long_cmd --a \\\n  --b\n"), "$[long_cmd --a --b]\n");
+}
+
+#[test]
+fn test_unsupported_blocks() {
+    let script = "if [ -f x ]; then\n  echo yes\nfi\necho end\n";
+    assert_eq!(
+        convert(script),
+        "# oxipy convert: `if` block: if [ -f x ]; then\n#   echo yes\n# fi\n$[echo end]\n"
+    );
+    assert_eq!(reasons(script), [(1, "`if` block".to_string())]);
+
+    let script = "cat <<EOF\nbody $x\nEOF\n";
+    assert_eq!(
+        convert(script),
+        "# oxipy convert: here-document: cat <<EOF\n# body $x\n# EOF\n"
+    );
+    assert_eq!(reasons(script), [(1, "here-document".to_string())]);
+}
+
+#[test]
+fn test_run_reports_unsupported() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script.sh");
+    std::fs::write(&script, "echo ok\necho [ab].txt\n").unwrap();
+    let args = ConvertArgs {
+        script: script.clone(),
+        output: None,
+    };
+    let (mut out, mut report) = (Vec::new(), Vec::new());
+    let code = run(&args, &mut out, &mut report).unwrap();
+
+    assert_eq!(code, 1);
+    assert!(String::from_utf8(out).unwrap().starts_with("$[echo ok]\n"));
+    let report = String::from_utf8(report).unwrap();
+    assert!(report.starts_with(&format!("{}:2: not translated: ", script.display())));
+}
@@ -0,0 +1,167 @@
+//! Splitting an sh script into logical lines, since `cmdgroup::parser` treats newlines as
+//! plain whitespace.
+
+/// A logical line: its code joined across continuations and open quotes or parentheses, and
+/// the trailing comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Line {
+    /// 1-based number of the first physical line.
+    pub(super) number: usize,
+    pub(super) code: String,
+    pub(super) comment: Option<String>,
+    /// The physical lines it was read from.
+    pub(super) raw: String,
+}
+
+#[derive(Default)]
+struct State {
+    single_quoted: bool,
+    double_quoted: bool,
+    depth: usize,
+    /// A newline inside parentheses, turned into `;` unless the list ends or continues anyway.
+    pending_separator: bool,
+}
+
+impl State {
+    fn is_open(&self) -> bool {
+        self.single_quoted || self.double_quoted || self.depth > 0
+    }
+}
+
+pub(super) fn logical_lines(script: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut physical = script.lines().enumerate();
+    while let Some((index, first)) = physical.next() {
+        let mut line = Line {
+            number: index + 1,
+            code: String::new(),
+            comment: None,
+            raw: first.to_string(),
+        };
+        let mut state = State::default();
+        let mut text = first;
+        loop {
+            let continued = scan(text, &mut line, &mut state);
+            if !(continued || state.is_open()) {
+                break;
+            }
+            let Some((_, next)) = physical.next() else {
+                break;
+            };
+            if state.single_quoted || state.double_quoted {
+                line.code.push('\n');
+            } else if state.depth > 0 && !continued {
+                state.pending_separator = true;
+            }
+            line.raw.push('\n');
+            line.raw.push_str(next);
+            text = next;
+        }
+
+        if let Some(delimiter) = heredoc_delimiter(&line.code) {
+            for (_, body) in physical.by_ref() {
+                line.raw.push('\n');
+                line.raw.push_str(body);
+                if body.trim() == delimiter {
+                    break;
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Appends the code of one physical line. Returns whether it ends with a `\` continuation.
+fn scan(text: &str, line: &mut Line, state: &mut State) -> bool {
+    let mut chars = text.char_indices();
+    while let Some((index, c)) = chars.next() {
+        if state.pending_separator {
+            if c.is_whitespace() {
+                continue;
+            }
+            state.pending_separator = false;
+            let code = line.code.trim_end();
+            if c != ')' && !code.ends_with(['(', ';', '|', '&']) {
+                line.code.push_str("; ");
+            }
+        }
+        if state.single_quoted {
+            state.single_quoted = c != '\'';
+            line.code.push(c);
+            continue;
+        }
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => {
+                    line.code.push(c);
+                    line.code.push(escaped);
+                }
+                None => return true,
+            },
+            '\'' if !state.double_quoted => {
+                state.single_quoted = true;
+                line.code.push(c);
+            }
+            '"' => {
+                state.double_quoted = !state.double_quoted;
+                line.code.push(c);
+            }
+            '#' if !state.double_quoted
+                && line
+                    .code
+                    .chars()
+                    .last()
+                    .is_none_or(|prev| prev.is_whitespace() || ";&|(".contains(prev)) =>
+            {
+                if state.depth == 0 {
+                    line.comment = Some(text[index..].to_string());
+                }
+                return false;
+            }
+            '(' if !state.double_quoted || line.code.ends_with('$') => {
+                state.depth += 1;
+                line.code.push(c);
+            }
+            ')' if state.depth > 0 => {
+                state.depth -= 1;
+                line.code.push(c);
+            }
+            c => line.code.push(c),
+        }
+    }
+    false
+}
+
+/// The delimiter of a `<<` here-document started on the line, if any.
+pub(super) fn heredoc_delimiter(code: &str) -> Option<String> {
+    let (_, rest) = code.split_once("<<")?;
+    if rest.starts_with('<') {
+        return None;
+    }
+    let rest = rest.strip_prefix('-').unwrap_or(rest).trim_start();
+    let word: String = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !";&|)".contains(*c))
+        .filter(|c| !"'\"\\".contains(*c))
+        .collect();
+    (!word.is_empty()).then_some(word)
+}
+
+/// The compound commands opened on a line, like `if` or a function's `{`, and the number of
+/// them closed again.
+pub(super) fn compound_words(code: &str) -> (Vec<&str>, usize) {
+    let mut opened = Vec::new();
+    let mut closed = 0;
+    let words = code
+        .split(|c: char| c.is_whitespace() || ";&|".contains(c))
+        .filter(|word| !word.is_empty());
+    for word in words {
+        match word {
+            "if" | "for" | "while" | "until" | "case" | "{" | "select" => opened.push(word),
+            "fi" | "done" | "esac" | "}" => closed += 1,
+            _ => {}
+        }
+    }
+    (opened, closed)
+}
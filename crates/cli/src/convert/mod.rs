@@ -0,0 +1,455 @@
+//! `oxipy convert`: translates sh scripts into oxipy source.
+//!
+//! Each logical line of the script is parsed with `cmdgroup::parser` and rendered as `$[...]`
+//! commands and `$NAME = ...` assignments. Lines that can't be translated are kept as comments
+//! and reported with their line number.
+
+#[cfg(test)]
+mod convert_test;
+
+mod lines;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use cmdgroup::parser::{
+    BooleanList, Command, CommandInner, EnvVar, IoFile, PipeSequence, PipeSequenceOperator,
+    Pipeline, PipelineInner, Redirect, RedirectFd, RedirectOp, RedirectOpOutput, Sequence,
+    SequentialList, Word, WordPart,
+};
+
+use lines::Line;
+
+const SHEBANG: &str = "#!/usr/bin/env oxipy";
+
+/// Characters of an unquoted word that may be written as-is inside `$[...]`.
+const PLAIN_WORD_CHARS: &str = "-_./:=+,%*?~^";
+
+#[derive(clap::Args, Debug)]
+pub struct ConvertArgs {
+    /// The sh script to convert
+    pub script: PathBuf,
+
+    /// Write the converted source to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// A construct that was left untranslated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Unsupported {
+    /// 1-based line of the script.
+    pub(crate) line: usize,
+    pub(crate) reason: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct Conversion {
+    pub(crate) source: String,
+    pub(crate) unsupported: Vec<Unsupported>,
+}
+
+/// Translates the sh `script` into oxipy source.
+pub(crate) fn convert_script(script: &str) -> Conversion {
+    let mut source = String::new();
+    let mut unsupported = Vec::new();
+    let mut lines = lines::logical_lines(script).into_iter().peekable();
+    if lines
+        .peek()
+        .is_some_and(|line| line.number == 1 && line.code.is_empty())
+        && script.starts_with("#!")
+    {
+        lines.next();
+        source.push_str(SHEBANG);
+        source.push('\n');
+    }
+
+    // Nesting of compound commands, which are kept as comments as a whole.
+    let mut block_depth = 0;
+    for line in lines {
+        let Line {
+            number,
+            code,
+            comment,
+            raw,
+        } = line;
+        let (opened, closed) = lines::compound_words(&code);
+        let statements = if block_depth > 0 || !opened.is_empty() {
+            let reason = (block_depth == 0).then(|| format!("`{}` block", opened[0]));
+            block_depth = (block_depth + opened.len()).saturating_sub(closed);
+            commented(&raw, reason.as_deref(), &mut unsupported, number)
+        } else if code.trim().is_empty() {
+            comment.into_iter().collect()
+        } else {
+            let mut translator = Translator::default();
+            match translator.line(&code) {
+                Ok(mut statements) if translator.unsupported.is_empty() => {
+                    if let (Some(comment), Some(last)) = (comment, statements.last_mut()) {
+                        last.push_str("  ");
+                        last.push_str(&comment);
+                    }
+                    statements
+                }
+                Ok(_) => {
+                    let reasons = translator.unsupported.join("; ");
+                    commented(&raw, Some(&reasons), &mut unsupported, number)
+                }
+                Err(reason) => commented(&raw, Some(&reason), &mut unsupported, number),
+            }
+        };
+        if statements.is_empty() {
+            source.push('\n');
+        }
+        for statement in statements {
+            source.push_str(&statement);
+            source.push('\n');
+        }
+    }
+    Conversion {
+        source,
+        unsupported,
+    }
+}
+
+/// Keeps the `raw` lines as comments, recording `reason` when it starts a new report entry.
+fn commented(
+    raw: &str,
+    reason: Option<&str>,
+    unsupported: &mut Vec<Unsupported>,
+    line: usize,
+) -> Vec<String> {
+    if let Some(reason) = reason {
+        unsupported.push(Unsupported {
+            line,
+            reason: reason.to_string(),
+        });
+    }
+    raw.lines()
+        .enumerate()
+        .map(|(index, raw)| match reason {
+            Some(reason) if index == 0 => format!("# oxipy convert: {reason}: {raw}"),
+            _ => format!("# {raw}"),
+        })
+        .collect()
+}
+
+/// Entry point of `oxipy convert`. Returns the process exit code: 1 when parts of the script
+/// could not be translated.
+pub(crate) fn run(args: &ConvertArgs, out: &mut dyn Write, report: &mut dyn Write) -> Result<i32> {
+    let script = std::fs::read_to_string(&args.script)
+        .with_context(|| format!("failed to read {}", args.script.display()))?;
+    let conversion = convert_script(&script);
+    match &args.output {
+        Some(output) => std::fs::write(output, &conversion.source)
+            .with_context(|| format!("failed to write {}", output.display()))?,
+        None => out.write_all(conversion.source.as_bytes())?,
+    }
+
+    for unsupported in &conversion.unsupported {
+        writeln!(
+            report,
+            "{}:{}: not translated: {}",
+            args.script.display(),
+            unsupported.line,
+            unsupported.reason
+        )?;
+    }
+    if !conversion.unsupported.is_empty() {
+        writeln!(
+            report,
+            "{} construct(s) could not be translated and were left as comments.",
+            conversion.unsupported.len()
+        )?;
+    }
+    Ok(i32::from(!conversion.unsupported.is_empty()))
+}
+
+/// Renders the cmdgroup AST of one line, collecting the constructs it can't express.
+#[derive(Default)]
+struct Translator {
+    unsupported: Vec<String>,
+}
+
+impl Translator {
+    /// Returns the statements for a line, or the parse error of the line.
+    fn line(&mut self, code: &str) -> Result<Vec<String>, String> {
+        if lines::heredoc_delimiter(code).is_some() {
+            return Err("here-document".to_string());
+        }
+        let list = cmdgroup::parser::parse(code.trim()).map_err(|err| {
+            let message = err.to_string();
+            let message = message.lines().next().unwrap_or_default();
+            message.trim_end_matches('.').to_string()
+        })?;
+        Ok(self.statements(&list))
+    }
+
+    fn statements(&mut self, list: &SequentialList) -> Vec<String> {
+        list.items
+            .iter()
+            .map(|item| match &item.sequence {
+                Sequence::ShellVar(var) => {
+                    format!("${} = {}", var.name, self.python_word(&var.value))
+                }
+                sequence => {
+                    let background = if item.is_async { " &" } else { "" };
+                    format!("$[{}{background}]", self.sequence(sequence))
+                }
+            })
+            .collect()
+    }
+
+    /// Renders a sequence as the body of `$[...]`.
+    fn sequence(&mut self, sequence: &Sequence) -> String {
+        match sequence {
+            Sequence::ShellVar(var) => {
+                self.unsupported
+                    .push(format!("assignment `{}=` inside a command list", var.name));
+                String::new()
+            }
+            Sequence::Pipeline(pipeline) => self.pipeline(pipeline),
+            Sequence::BooleanList(list) => {
+                let BooleanList { current, op, next } = list.as_ref();
+                format!(
+                    "{} {} {}",
+                    self.sequence(current),
+                    op.as_str(),
+                    self.sequence(next)
+                )
+            }
+        }
+    }
+
+    fn pipeline(&mut self, pipeline: &Pipeline) -> String {
+        if pipeline.negated {
+            self.unsupported.push("negated pipeline `!`".to_string());
+        }
+        self.pipeline_inner(&pipeline.inner)
+    }
+
+    fn pipeline_inner(&mut self, inner: &PipelineInner) -> String {
+        match inner {
+            PipelineInner::Command(command) => self.command(command),
+            PipelineInner::PipeSequence(sequence) => {
+                let PipeSequence { current, op, next } = sequence.as_ref();
+                let stderr = match op {
+                    PipeSequenceOperator::Stdout => "",
+                    PipeSequenceOperator::StdoutStderr => " e>o",
+                };
+                format!(
+                    "{}{stderr} | {}",
+                    self.command(current),
+                    self.pipeline_inner(next)
+                )
+            }
+        }
+    }
+
+    fn command(&mut self, command: &Command) -> String {
+        let mut words = Vec::new();
+        match &command.inner {
+            CommandInner::Simple(simple) => {
+                if !simple.env_vars.is_empty() {
+                    words.push("env".to_string());
+                    for EnvVar { name, value } in &simple.env_vars {
+                        words.push(self.proc_word_with_prefix(&format!("{name}="), value));
+                    }
+                }
+                for arg in &simple.args {
+                    words.push(self.proc_word(arg));
+                }
+            }
+            CommandInner::Subshell(list) => {
+                // A child oxipy keeps `cd` and variables from leaking, like a subshell.
+                let body = self.statements(list).join("\n");
+                words.push("oxipy".to_string());
+                words.push("-c".to_string());
+                words.push(python_str(&body));
+            }
+        }
+        if let Some(redirect) = &command.redirect {
+            words.push(self.redirect(redirect));
+        }
+        words.join(" ")
+    }
+
+    fn redirect(&mut self, redirect: &Redirect) -> String {
+        let op = match redirect.op {
+            RedirectOp::Input(_) => "<",
+            RedirectOp::Output(RedirectOpOutput::Overwrite) => ">",
+            RedirectOp::Output(RedirectOpOutput::Append) => ">>",
+        };
+        let source = match &redirect.maybe_fd {
+            None => String::new(),
+            Some(RedirectFd::Fd(fd)) => fd.to_string(),
+            Some(RedirectFd::StdoutStderr) => "a".to_string(),
+        };
+        match &redirect.io_file {
+            IoFile::Word(word) => format!("{source}{op} {}", self.proc_word(word)),
+            IoFile::Fd(fd) => {
+                let stream = |fd: &str| match fd {
+                    "" | "1" => Some("o"),
+                    "2" => Some("e"),
+                    _ => None,
+                };
+                match (stream(&source), stream(&fd.to_string())) {
+                    (Some(from), Some(to)) if op == ">" => format!("{from}>{to}"),
+                    _ => {
+                        self.unsupported
+                            .push(format!("redirect `{source}{op}&{fd}`"));
+                        String::new()
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders a word as an argument inside `$[...]`.
+    fn proc_word(&mut self, word: &Word) -> String {
+        self.proc_word_with_prefix("", word)
+    }
+
+    fn proc_word_with_prefix(&mut self, prefix: &str, word: &Word) -> String {
+        let parts = word.parts();
+        if let Some(text) = literal_text(parts) {
+            let text = format!("{prefix}{text}");
+            if matches!(parts.as_slice(), [WordPart::Text(_)]) && is_plain_word(&text) {
+                return text;
+            }
+            if parts
+                .iter()
+                .any(|part| matches!(part, WordPart::Text(text) if text.contains(['[', ']'])))
+            {
+                self.unsupported.push(format!(
+                    "bracket glob `{text}`, it would be passed literally"
+                ));
+            }
+            return python_str(&text);
+        }
+
+        let mut exprs = Vec::new();
+        let mut text = prefix.to_string();
+        self.collect_parts(parts, &mut exprs, &mut text);
+        if !text.is_empty() {
+            exprs.push(python_str(&text));
+        }
+        match exprs.as_slice() {
+            [single] if is_proc_expr(single) => single.clone(),
+            _ => format!("@({})", exprs.join(" + ")),
+        }
+    }
+
+    /// Renders a word as a Python expression, e.g. the value of an assignment.
+    fn python_word(&mut self, word: &Word) -> String {
+        match literal_text(word.parts()) {
+            Some(text) => python_str(&text),
+            None => {
+                let exprs = self.python_parts(word.parts());
+                match exprs.as_slice() {
+                    [] => python_str(""),
+                    [single] if single.starts_with("$(") => format!("{single}.rstrip('\\n')"),
+                    _ => exprs.join(" + "),
+                }
+            }
+        }
+    }
+
+    /// Renders the parts of a word as Python expressions to concatenate.
+    fn python_parts(&mut self, parts: &[WordPart]) -> Vec<String> {
+        let mut exprs = Vec::new();
+        let mut text = String::new();
+        self.collect_parts(parts, &mut exprs, &mut text);
+        if !text.is_empty() {
+            exprs.push(python_str(&text));
+        }
+        exprs
+    }
+
+    fn collect_parts(&mut self, parts: &[WordPart], exprs: &mut Vec<String>, text: &mut String) {
+        for part in parts {
+            let expr = match part {
+                WordPart::Text(part) => {
+                    text.push_str(part);
+                    continue;
+                }
+                WordPart::Quoted(parts) => {
+                    self.collect_parts(parts, exprs, text);
+                    continue;
+                }
+                WordPart::Variable(name) if name == "?" => "str($?)".to_string(),
+                WordPart::Variable(name) if name.chars().all(|c| c.is_ascii_digit()) => {
+                    format!("$ARGS[{name}]")
+                }
+                WordPart::Variable(name) => format!("${name}"),
+                WordPart::Command(list) => format!("$({})", self.substitution(list)),
+            };
+            if !text.is_empty() {
+                exprs.push(python_str(text));
+                text.clear();
+            }
+            exprs.push(expr);
+        }
+    }
+
+    /// Renders the body of a command substitution.
+    fn substitution(&mut self, list: &SequentialList) -> String {
+        list.items
+            .iter()
+            .map(|item| {
+                if item.is_async {
+                    self.unsupported
+                        .push("background job inside a command substitution".to_string());
+                }
+                self.sequence(&item.sequence)
+            })
+            .collect::<Vec<_>>()
+            .join(" ; ")
+    }
+}
+
+/// The text of a word without variables or command substitutions.
+fn literal_text(parts: &[WordPart]) -> Option<String> {
+    let mut text = String::new();
+    for part in parts {
+        match part {
+            WordPart::Text(part) => text.push_str(part),
+            WordPart::Quoted(parts) => text.push_str(&literal_text(parts)?),
+            WordPart::Variable(_) | WordPart::Command(_) => return None,
+        }
+    }
+    Some(text)
+}
+
+/// Whether a Python expression can be written as-is as a command argument, e.g. `$HOME`.
+fn is_proc_expr(expr: &str) -> bool {
+    expr.starts_with("$(")
+        || expr.strip_prefix('$').is_some_and(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+fn is_plain_word(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || PLAIN_WORD_CHARS.contains(c))
+}
+
+/// Quotes `text` as a single-quoted Python string literal.
+fn python_str(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('\'');
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
@@ -0,0 +1,240 @@
+//! Semantic classification of source ranges for syntax highlighting.
+//!
+//! The lexer alone can't tell a command name from an argument, so the lexer tokens give the
+//! base roles and the parse tree refines the ranges that belong to subprocess expressions.
+
+use ruff_python_ast::subproc::{EnvVarRef, GLOB_CHARS, Pipeline, runtime_call_name, unquoted_word};
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, PySourceType};
+use ruff_python_parser::TokenKind;
+use ruff_text_size::{Ranged, TextRange, TextSize};
+
+/// The role of a highlighted range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticRole {
+    /// Program name of a pipeline stage.
+    Command,
+    /// A plain command argument.
+    Argument,
+    /// A command argument starting with `-`.
+    Flag,
+    /// Unquoted command arguments containing glob characters and `g"..."` literals.
+    Glob,
+    /// Redirect operators and their targets, e.g. `2>` and `err.log`.
+    Redirect,
+    /// `$NAME` and `${...}` accesses.
+    EnvVar,
+    /// `p"..."` path literals.
+    Path,
+    /// `re"..."` literals and `@name"..."` searches.
+    Regex,
+    /// Subprocess delimiters like `$(`, `![` and `@$(`.
+    Subproc,
+    /// Python and shell operators, including `|`, `&&` and `;` inside commands.
+    Operator,
+    PythonKeyword,
+    String,
+    Number,
+    Comment,
+}
+
+impl SemanticRole {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            SemanticRole::Command => "command",
+            SemanticRole::Argument => "argument",
+            SemanticRole::Flag => "flag",
+            SemanticRole::Glob => "glob",
+            SemanticRole::Redirect => "redirect",
+            SemanticRole::EnvVar => "env-var",
+            SemanticRole::Path => "path",
+            SemanticRole::Regex => "regex",
+            SemanticRole::Subproc => "subproc",
+            SemanticRole::Operator => "operator",
+            SemanticRole::PythonKeyword => "python-keyword",
+            SemanticRole::String => "string",
+            SemanticRole::Number => "number",
+            SemanticRole::Comment => "comment",
+        }
+    }
+
+    /// The role of a lexer token when nothing more specific is known.
+    fn from_token(kind: TokenKind) -> Option<Self> {
+        Some(match kind {
+            _ if kind.is_keyword() => SemanticRole::PythonKeyword,
+            TokenKind::String
+            | TokenKind::FStringStart
+            | TokenKind::FStringMiddle
+            | TokenKind::FStringEnd => SemanticRole::String,
+            TokenKind::Int | TokenKind::Float | TokenKind::Complex => SemanticRole::Number,
+            TokenKind::Comment => SemanticRole::Comment,
+            TokenKind::Dollar
+            | TokenKind::DollarLParen
+            | TokenKind::DollarLSqb
+            | TokenKind::DollarLBrace
+            | TokenKind::BangLParen
+            | TokenKind::BangLSqb
+            | TokenKind::AtDollarLParen => SemanticRole::Subproc,
+            _ if kind.is_operator() || kind.is_proc_op() => SemanticRole::Operator,
+            _ => return None,
+        })
+    }
+}
+
+/// A classified source range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: TextRange,
+    pub role: SemanticRole,
+}
+
+impl Ranged for SemanticToken {
+    fn range(&self) -> TextRange {
+        self.range
+    }
+}
+
+/// Classifies `source` into non-overlapping ranges, sorted by start offset.
+///
+/// Works on incomplete input too: the parser recovers from errors and the lexer tokens cover
+/// whatever the tree doesn't.
+pub fn semantic_tokens(source: &str) -> Vec<SemanticToken> {
+    let parsed = ruff_python_parser::parse_unchecked_source(source, PySourceType::Python);
+
+    let mut classifier = Classifier {
+        source,
+        tokens: Vec::new(),
+    };
+    classifier.visit_body(parsed.suite());
+    let mut refined = classifier.tokens;
+    refined.sort_by_key(|token| (token.start(), std::cmp::Reverse(token.end())));
+
+    // drop refinements nested in an earlier one, e.g. a redirect target that is also a word
+    let mut merged: Vec<SemanticToken> = Vec::with_capacity(refined.len());
+    for token in refined {
+        if merged.last().is_none_or(|last| last.end() <= token.start()) {
+            merged.push(token);
+        }
+    }
+
+    let mut result = Vec::new();
+    let mut refined = merged.into_iter().peekable();
+    for token in parsed.tokens().iter() {
+        while let Some(next) = refined.next_if(|next| next.start() <= token.start()) {
+            result.push(next);
+        }
+        let covered = result
+            .last()
+            .is_some_and(|last: &SemanticToken| last.end() > token.start());
+        if covered || token.range().is_empty() {
+            continue;
+        }
+        if let Some(role) = SemanticRole::from_token(token.kind()) {
+            result.push(SemanticToken {
+                range: token.range(),
+                role,
+            });
+        }
+    }
+    result.extend(refined);
+    result
+}
+
+/// Collects the roles the parse tree knows better than the lexer.
+struct Classifier<'src> {
+    source: &'src str,
+    tokens: Vec<SemanticToken>,
+}
+
+impl Classifier<'_> {
+    fn push(&mut self, range: TextRange, role: SemanticRole) {
+        if !range.is_empty() {
+            self.tokens.push(SemanticToken { range, role });
+        }
+    }
+
+    fn classify_pipeline(&mut self, pipeline: &Pipeline) {
+        for stage in &pipeline.stages {
+            if let Some(program) = stage.program_expr() {
+                if matches!(program, Expr::StringLiteral(_)) {
+                    self.push(program.range(), SemanticRole::Command);
+                }
+            }
+            for word in stage.words().iter().skip(1) {
+                let Some(text) = unquoted_word(word, self.source) else {
+                    continue;
+                };
+                let role = if matches!(text, "!" | "&") {
+                    SemanticRole::Operator
+                } else if text.starts_with('-') {
+                    SemanticRole::Flag
+                } else if text.contains(GLOB_CHARS) {
+                    SemanticRole::Glob
+                } else {
+                    SemanticRole::Argument
+                };
+                self.push(word.range(), role);
+            }
+            for redirect in stage.redirects() {
+                let target = redirect.target.range();
+                // a bare `>` is its own source, otherwise the operator follows the source
+                let operator_start = match redirect.source {
+                    Some(source) => {
+                        self.push(source.range(), SemanticRole::Redirect);
+                        source.end()
+                    }
+                    None => target.start(),
+                };
+                if operator_start < target.start() {
+                    self.push_trimmed(TextRange::new(operator_start, target.start()));
+                }
+                self.push(target, SemanticRole::Redirect);
+            }
+        }
+    }
+
+    /// Pushes a redirect operator found in `range`, ignoring surrounding whitespace.
+    fn push_trimmed(&mut self, range: TextRange) {
+        let text = &self.source[range];
+        let trimmed = text.trim_start();
+        let start = range.start() + TextSize::of(&text[..text.len() - trimmed.len()]);
+        let range = TextRange::at(start, TextSize::of(trimmed.trim_end()));
+        self.push(range, SemanticRole::Redirect);
+    }
+}
+
+impl<'a> Visitor<'a> for Classifier<'_> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Some(pipeline) = Pipeline::from_expr(expr) {
+            self.classify_pipeline(&pipeline);
+        } else if let Some(env_var) = EnvVarRef::from_expr(expr) {
+            if env_var.name.is_some() {
+                // the range of the lowered subscript starts after the `$`
+                let Expr::Subscript(subscript) = expr else {
+                    unreachable!()
+                };
+                let range = TextRange::new(subscript.value.start(), env_var.end());
+                self.push(range, SemanticRole::EnvVar);
+                return;
+            }
+        } else if let Expr::StringLiteral(literal) = expr {
+            if literal.value.is_glob() {
+                self.push(expr.range(), SemanticRole::Glob);
+            }
+        } else if runtime_call_name(expr) == Some("path") {
+            self.push(expr.range(), SemanticRole::Path);
+            return;
+        } else if let Expr::Call(call) = expr {
+            // `ox.Pattern(s).regex()` and `ox.Pattern(s).invoke(name)`
+            if let Expr::Attribute(method) = call.func.as_ref() {
+                if runtime_call_name(&method.value) == Some("Pattern")
+                    && matches!(method.attr.as_str(), "regex" | "invoke")
+                {
+                    self.push(expr.range(), SemanticRole::Regex);
+                    return;
+                }
+            }
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
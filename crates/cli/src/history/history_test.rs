@@ -0,0 +1,222 @@
+use super::*;
+
+use std::rc::Rc;
+
+use clap::Parser;
+use cmdgroup::{KillSignal, ShellPipeReader, ShellPipeWriter, ShellState};
+
+use crate::which::CommandKind;
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+#[test]
+fn test_sessions_share_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+    let mut first = HistoryStore::open(Some(path.clone()));
+    let mut second = HistoryStore::open(Some(path.clone()));
+    first.append("ls", Path::new("/a"), 1.0, 0, 0.5).unwrap();
+    second
+        .append("false", Path::new("/b"), 2.0, 1, 0.1)
+        .unwrap();
+    first.append("pwd", Path::new("/a"), 3.0, 0, 0.2).unwrap();
+    // a line cut short by a crash
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"{\"command\": \"trunc")
+        .unwrap();
+
+    let reopened = HistoryStore::open(Some(path.clone()));
+
+    let commands: Vec<_> = reopened
+        .entries()
+        .iter()
+        .map(|entry| entry.command.as_str())
+        .collect();
+    assert_eq!(commands, ["ls", "false", "pwd"]);
+    let failed = &reopened.entries()[1];
+    assert_eq!(failed.cwd.as_deref(), Some(Path::new("/b")));
+    assert_eq!(failed.status, Some(1));
+    assert_eq!(failed.session, second.session);
+    assert_ne!(first.session, second.session);
+    // each session only knows its own entries until it reads the file again
+    assert_eq!(first.entries().len(), 2);
+}
+
+#[test]
+fn test_import_legacy() {
+    let dir = tempfile::tempdir().unwrap();
+    let legacy = dir.path().join(".oxsh_history");
+    std::fs::write(&legacy, "#V2\nls -la\n\nfor x in y:\\n    print(x\\\\n)\n").unwrap();
+    let path = dir.path().join("oxsh").join("history.jsonl");
+
+    let mut history = HistoryStore::open(Some(path.clone()));
+    history.import_legacy(&legacy).unwrap();
+    history.append("pwd", Path::new("/a"), 1.0, 0, 0.1).unwrap();
+
+    let reopened = HistoryStore::open(Some(path.clone()));
+    let commands: Vec<_> = reopened
+        .entries()
+        .iter()
+        .map(|entry| entry.command.as_str())
+        .collect();
+    assert_eq!(commands, ["ls -la", "for x in y:\n    print(x\\n)", "pwd"]);
+    assert_eq!(history.entries().len(), 3);
+    assert_eq!(reopened.entries()[0].cwd, None);
+    assert_eq!(reopened.entries()[0].status, None);
+    // only the first time
+    let mut again = HistoryStore::open(Some(path.clone()));
+    again.import_legacy(&legacy).unwrap();
+    assert_eq!(again.entries().len(), 3);
+    // plain lines without the header
+    std::fs::write(&legacy, "echo a\\nb\n").unwrap();
+    let mut plain = HistoryStore::open(Some(dir.path().join("plain.jsonl")));
+    plain.import_legacy(&legacy).unwrap();
+    assert_eq!(plain.entries()[0].command, "echo a\\nb");
+}
+
+#[test]
+fn test_fuzzy_score() {
+    assert_eq!(fuzzy_score("", "ls"), Some(0));
+    assert_eq!(fuzzy_score("gco", "git checkout"), Some(4 - 4 + 4 - 5));
+    assert_eq!(fuzzy_score("GIT", "git status"), Some(4 + 8 + 8));
+    assert_eq!(fuzzy_score("xyz", "git status"), None);
+    assert!(fuzzy_score("gst", "git status") > fuzzy_score("gst", "grep -rn st"));
+}
+
+#[test]
+fn test_search_prefers_cwd() {
+    let mut history = HistoryStore::open(None);
+    let here = Path::new("/project");
+    history.append("cargo test", here, 1.0, 0, 1.0).unwrap();
+    history
+        .append("cargo build", Path::new("/other"), 2.0, 0, 1.0)
+        .unwrap();
+    history
+        .append("cat Cargo.toml", Path::new("/other"), 3.0, 0, 1.0)
+        .unwrap();
+    history
+        .append("cargo test", Path::new("/other"), 4.0, 0, 1.0)
+        .unwrap();
+    assert_eq!(
+        history.search("cargo", here),
+        ["cargo test", "cargo build", "cat Cargo.toml"]
+    );
+    assert_eq!(
+        history.search("ca", Path::new("/other")),
+        ["cargo test", "cat Cargo.toml", "cargo build"]
+    );
+}
+
+fn listed(args: &[&str], history: &HistoryStore) -> String {
+    let args = HistoryArgs::try_parse_from(args).unwrap();
+    let mut out = Vec::new();
+    run(&args, history, Path::new("/project"), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_filters() {
+    let mut history = HistoryStore::open(None);
+    let old = now() - 3.0 * 60.0 * 60.0;
+    history
+        .append("make", Path::new("/project"), old, 2, 1.0)
+        .unwrap();
+    history.append("ls", Path::new("/"), now(), 0, 0.1).unwrap();
+    history
+        .append("false", Path::new("/"), now(), 1, 0.1)
+        .unwrap();
+
+    assert_eq!(
+        listed(&[], &history),
+        "    1  make\n    2  ls\n    3  false\n"
+    );
+    assert_eq!(
+        listed(&["--failed"], &history),
+        "    1  make\n    3  false\n"
+    );
+    assert_eq!(
+        listed(&["--since", "1h"], &history),
+        "    2  ls\n    3  false\n"
+    );
+    assert_eq!(listed(&["--cwd"], &history), "    1  make\n");
+    assert_eq!(
+        listed(&["--cwd", "/", "--failed"], &history),
+        "    3  false\n"
+    );
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+    assert_eq!(parse_duration("1w"), Ok(Duration::from_secs(604_800)));
+    for invalid in ["", "2", "h", "2y", "-1d"] {
+        assert!(parse_duration(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn test_builtin_in_pipes_and_lists() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = HistoryStore::open(Some(dir.path().join("history.jsonl")));
+    for (command, cwd) in [
+        ("cargo build", "/a"),
+        ("ls", "/my dir"),
+        ("cargo test", "/my dir"),
+    ] {
+        store
+            .append(command, Path::new(cwd), now(), 0, 0.1)
+            .unwrap();
+    }
+    let history = Arc::new(Mutex::new(store));
+    let run_line = |line: &str| {
+        let command: Rc<dyn ShellCommand> = Rc::new(HistoryCommand(history.clone()));
+        let commands = HashMap::from([("history".to_string(), command)]);
+        let mut state = ShellState::new(
+            std::env::vars().collect(),
+            Path::new("/"),
+            commands,
+            KillSignal::default(),
+        );
+        let list = cmdgroup::parser::parse(line).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&runtime, async {
+            let (reader, writer) = cmdgroup::pipe();
+            let output = reader.pipe_to_string_handle();
+            let stdin = ShellPipeReader::stdin();
+            let stderr = ShellPipeWriter::null();
+            let result =
+                cmdgroup::execute_with_state(list, &mut state, stdin, writer, stderr).await;
+            (result.exit_code(), output.await.unwrap())
+        })
+    };
+
+    assert_eq!(
+        run_line("history | grep cargo"),
+        (0, "    1  cargo build\n    3  cargo test\n".to_string())
+    );
+    assert_eq!(
+        run_line("true; history --cwd 'my dir' | grep -v ls"),
+        (0, "    3  cargo test\n".to_string())
+    );
+    assert_eq!(
+        run_line("history --nope || echo failed"),
+        (0, "failed\n".to_string())
+    );
+    assert_eq!(
+        CommandKind::resolve("history", Path::new("/"), &HashMap::new()),
+        CommandKind::Builtin
+    );
+}
@@ -0,0 +1,346 @@
+//! The REPL's history, with what each entry ran in and how it went.
+//!
+//! Entries are appended as JSON lines to `history.jsonl` in the data directory, e.g.
+//! `~/.local/share/oxipy`. Each entry is written with a single `write` on a file opened for
+//! appending, so concurrent sessions interleave whole lines; lines that don't parse, like one
+//! cut short by a crash, are skipped when the file is read. The first time, the commands of the
+//! plain `~/.oxsh_history` kept before are imported.
+
+#[cfg(test)]
+mod history_test;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use clap::Parser;
+use cmdgroup::{ExecuteResult, FutureExecuteResult, ShellCommand, ShellCommandContext};
+use serde::{Deserialize, Serialize};
+
+/// One entry of the history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    pub(crate) command: String,
+    /// Seconds since the epoch when the entry started.
+    pub(crate) timestamp: f64,
+    /// The working directory the entry started in, unknown for imported entries.
+    pub(crate) cwd: Option<PathBuf>,
+    /// Unknown for imported entries.
+    pub(crate) status: Option<i32>,
+    /// Seconds the entry ran for.
+    pub(crate) duration: f64,
+    /// The session that ran the entry, empty for imported entries.
+    pub(crate) session: String,
+}
+
+/// The history file and the entries read from it, plus the ones of this session.
+#[derive(Debug)]
+pub(crate) struct HistoryStore {
+    /// `None` keeps the history in memory only, e.g. without a data directory.
+    path: Option<PathBuf>,
+    session: String,
+    entries: Vec<Entry>,
+}
+
+impl HistoryStore {
+    /// The history file in the user's data directory.
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("oxipy").join("history.jsonl"))
+    }
+
+    /// The plain history file of the line editor, which was used before `history.jsonl`.
+    pub(crate) fn legacy_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".oxsh_history"))
+    }
+
+    /// Reads the entries of the file at `path`, which may not exist yet, for a new session.
+    pub(crate) fn open(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_deref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            path,
+            session: format!("{:x}-{:x}", std::process::id(), started.as_nanos()),
+            entries,
+        }
+    }
+
+    pub(crate) fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Creates the history file with the commands of `legacy`, a history file saved by the
+    /// line editor, unless the file exists already. Their directory and status are unknown.
+    pub(crate) fn import_legacy(&mut self, legacy: &Path) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if path.exists() {
+            return Ok(());
+        }
+        let commands = match std::fs::read_to_string(legacy) {
+            Ok(text) => legacy_commands(&text),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let imported: Vec<Entry> = commands
+            .into_iter()
+            .map(|command| Entry {
+                command,
+                timestamp: 0.0,
+                cwd: None,
+                status: None,
+                duration: 0.0,
+                session: String::new(),
+            })
+            .collect();
+        let mut lines = String::new();
+        for entry in &imported {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // another session may have just created it
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        file.write_all(lines.as_bytes())?;
+        self.entries.splice(0..0, imported);
+        Ok(())
+    }
+
+    /// Adds an entry of this session and appends it to the file.
+    pub(crate) fn append(
+        &mut self,
+        command: &str,
+        cwd: &Path,
+        timestamp: f64,
+        status: i32,
+        duration: f64,
+    ) -> Result<()> {
+        let entry = Entry {
+            command: command.to_string(),
+            timestamp,
+            cwd: Some(cwd.to_path_buf()),
+            status: Some(status),
+            duration,
+            session: self.session.clone(),
+        };
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let mut line = serde_json::to_string(&entry)?;
+            line.push('\n');
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            // a single write, so that lines of concurrent sessions don't mix
+            file.write_all(line.as_bytes())?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// The distinct commands matching `query` fuzzily, best first: those run in `cwd`, then
+    /// the closest matches, then the most recent ones.
+    pub(crate) fn search(&self, query: &str, cwd: &Path) -> Vec<&str> {
+        // whether each command ran in `cwd`, and the last time it ran
+        let mut commands: HashMap<&str, (bool, usize)> = HashMap::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (in_cwd, last) = commands.entry(entry.command.as_str()).or_default();
+            *in_cwd |= entry.cwd.as_deref() == Some(cwd);
+            *last = index;
+        }
+        let mut matches: Vec<(bool, i64, usize, &str)> = commands
+            .into_iter()
+            .filter_map(|(command, (in_cwd, last))| {
+                Some((in_cwd, fuzzy_score(query, command)?, last, command))
+            })
+            .collect();
+        matches.sort_by_key(|&(in_cwd, score, last, _)| Reverse((in_cwd, score, last)));
+        matches.into_iter().map(|(.., command)| command).collect()
+    }
+}
+
+/// The commands of a history file saved by the line editor. After a `#V2` header, newlines
+/// and backslashes in commands are escaped with backslashes.
+fn legacy_commands(text: &str) -> Vec<String> {
+    let mut lines = text.lines().peekable();
+    let escaped = lines.next_if_eq(&"#V2").is_some();
+    lines
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if !escaped {
+                return line.to_string();
+            }
+            let mut command = String::with_capacity(line.len());
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => match chars.next() {
+                        Some('n') => command.push('\n'),
+                        Some(escaped) => command.push(escaped),
+                        None => command.push(c),
+                    },
+                    _ => command.push(c),
+                }
+            }
+            command
+        })
+        .collect()
+}
+
+/// How well `text` matches `query`, whose characters must all appear in it in order, ignoring
+/// case. Consecutive characters and characters starting a word score higher, gaps lower.
+pub(crate) fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut chars = text.char_indices();
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let (index, _) = chars.find(|(_, c)| c.to_lowercase().eq(std::iter::once(wanted)))?;
+        let at_word_start = text[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|before| !before.is_alphanumeric());
+        score += match previous {
+            Some(previous) if text[previous..index].chars().count() == 1 => 8,
+            Some(previous) => -i64::try_from(text[previous..index].chars().count()).unwrap_or(0),
+            None => 0,
+        };
+        if at_word_start {
+            score += 4;
+        }
+        previous = Some(index);
+    }
+    Some(score)
+}
+
+/// Lists the entries of the history, oldest first.
+#[derive(clap::Parser, Debug)]
+#[command(name = "history", no_binary_name = true)]
+pub(crate) struct HistoryArgs {
+    /// Only entries run in DIR, the current directory by default
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = ".")]
+    cwd: Option<PathBuf>,
+
+    /// Only entries that failed
+    #[arg(long)]
+    failed: bool,
+
+    /// Only entries started in the last DURATION, like `30m`, `2h`, `1d` or `1w`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    since: Option<Duration>,
+}
+
+/// Parses a number followed by a unit of `s`, `m`, `h`, `d` or `w`.
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let error = || "expected a number and a unit of s, m, h, d or w, like `2h`".to_string();
+    let unit_start = text.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+    let count: u64 = text[..unit_start].parse().map_err(|_| error())?;
+    let seconds = match &text[unit_start..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(error()),
+    };
+    Ok(Duration::from_secs(count.saturating_mul(seconds)))
+}
+
+/// Writes the entries of `history` selected by `args` to `out`, numbered by their place in the
+/// whole history. Relative directories are resolved against `cwd`.
+pub(crate) fn run(
+    args: &HistoryArgs,
+    history: &HistoryStore,
+    cwd: &Path,
+    out: &mut impl Write,
+) -> Result<()> {
+    let dir = args.cwd.as_ref().map(|dir| {
+        let dir = cwd.join(dir);
+        dir.canonicalize().unwrap_or(dir)
+    });
+    let since = args.since.map(|since| {
+        SystemTime::now()
+            .checked_sub(since)
+            .and_then(|since| since.duration_since(UNIX_EPOCH).ok())
+            .map_or(0.0, |since| since.as_secs_f64())
+    });
+    for (index, entry) in history.entries().iter().enumerate() {
+        if dir
+            .as_ref()
+            .is_some_and(|dir| entry.cwd.as_ref() != Some(dir))
+            || (args.failed && entry.status.is_none_or(|status| status == 0))
+            || since.is_some_and(|since| entry.timestamp < since)
+        {
+            continue;
+        }
+        writeln!(out, "{:5}  {}", index + 1, entry.command)?;
+    }
+    Ok(())
+}
+
+/// The `history` builtin, which `cmdgroup` runs like its own commands, so it takes part in
+/// pipes and lists like `history | grep cargo`.
+pub(crate) struct HistoryCommand(pub(crate) Arc<Mutex<HistoryStore>>);
+
+impl ShellCommand for HistoryCommand {
+    fn execute(&self, context: ShellCommandContext) -> FutureExecuteResult {
+        let ShellCommandContext {
+            args,
+            state,
+            mut stdout,
+            mut stderr,
+            ..
+        } = context;
+        let mut output = Vec::new();
+        let mut errors = String::new();
+        let code = match HistoryArgs::try_parse_from(args) {
+            Ok(args) => {
+                let history = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+                match run(&args, &history, state.cwd(), &mut output) {
+                    Ok(()) => 0,
+                    Err(err) => {
+                        errors = format!("history: {err}\n");
+                        1
+                    }
+                }
+            }
+            // `--help` is printed to stdout
+            Err(err) if err.use_stderr() => {
+                errors = err.to_string();
+                err.exit_code()
+            }
+            Err(err) => {
+                output = err.to_string().into_bytes();
+                0
+            }
+        };
+        Box::pin(async move {
+            // the next stage of a pipe may be reading on this thread
+            let written = tokio::task::spawn_blocking(move || {
+                stdout.write_all(&output)?;
+                stderr.write_all(errors.as_bytes())
+            });
+            let _ = written.await;
+            ExecuteResult::from_exit_code(code)
+        })
+    }
+}
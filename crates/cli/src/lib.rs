@@ -1,11 +1,30 @@
 #[cfg(test)]
 mod lib_test;
 
+mod bash_completion;
+mod check;
+pub mod command_list;
+mod completion;
+mod convert;
+pub mod highlight;
+mod history;
+mod lsp;
+mod prompt;
+mod rc;
+mod session;
 mod shell;
+mod theme;
+pub mod which;
 
-use clap::{Parser, arg};
+pub use check::{CheckArgs, OutputFormat};
+pub use convert::ConvertArgs;
+pub use session::{CommandRun, Interpreter, Outcome};
+use clap::{Parser, Subcommand, arg};
 use clap_verbosity_flag::{Verbosity, WarnLevel};
+use std::collections::HashMap;
 use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use anyhow::Result;
 
 #[allow(dead_code)]
@@ -25,54 +44,155 @@ pub struct Cli {
     #[arg(short = 'c')]
     pub command: Option<String>,
 
-    // /// The RC files to load, these may be either xonsh files or directories containing xonsh files
-    // #[arg(long)]
-    // pub rc: Option<Vec<PathBuf>>,
+    /// The RC files to load, these may be either oxipy files or directories containing oxipy files
+    #[arg(long)]
+    pub rc: Option<Vec<PathBuf>>,
 
-    // /// Do not load any RC files. Argument --rc will be ignored if --no-rc is set
-    // #[arg(long)]
-    // pub no_rc: bool,
+    /// Do not load any RC files. Argument --rc will be ignored if --no-rc is set
+    #[arg(long)]
+    pub no_rc: bool,
 
-    /// Do not inherit program specific environment variables from parent process
+    /// Do not inherit program specific environment variables from parent process, only PATH,
+    /// HOME and TERM
     #[arg(long)]
     pub no_env: bool,
 
     /// Define an environment variable, in the form of -DNAME=VAL. May be used many times
-    #[arg(short = 'D')]
+    #[arg(short = 'D', value_parser = parse_define)]
     pub defines: Option<Vec<String>>,
 
+    /// Do not read or write `.oxyc` caches of compiled sources
+    #[arg(long)]
+    pub no_cache: bool,
+
     #[command(flatten)]
     verbose: Verbosity<WarnLevel>,
 
-    // /// If present, execute the script in script-file and exit
-    // #[arg()]
-    // script_file: Option<PathBuf>,
+    #[command(subcommand)]
+    pub subcommand: Option<Commands>,
 
-    // /// Additional arguments to the script specified by script-file
-    // #[arg()]
-    // args: Vec<String>,
+    /// If present, execute the script in script-file and exit. `-` reads the script from stdin
+    #[arg()]
+    pub script_file: Option<PathBuf>,
+
+    /// Additional arguments to the script specified by script-file
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub args: Vec<String>,
+}
+
+/// The variables inherited from the parent process with `--no-env`.
+const MINIMAL_ENV: &[&str] = &["PATH", "HOME", "TERM"];
+
+/// Checks that a `-D` define has the form `NAME=VAL`, where `NAME` is a valid variable name.
+fn parse_define(define: &str) -> Result<String, String> {
+    let Some((name, _)) = define.split_once('=') else {
+        return Err("expected NAME=VAL".to_string());
+    };
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!(
+            "invalid variable name `{name}`, expected letters, digits and `_`, not starting \
+             with a digit"
+        ));
+    }
+    Ok(define.to_string())
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Check shell-flavoured Python files for common mistakes
+    Check(CheckArgs),
+    /// Translate an sh script into oxipy source
+    Convert(ConvertArgs),
+    /// Run the language server over stdio
+    Lsp,
 }
 
 impl Cli {
-    pub fn main<I, T>(args: I) -> Result<()>
+    /// Runs the CLI and returns the exit code of the process.
+    pub fn main<I, T>(args: I, interpreter: Box<dyn Interpreter>) -> Result<i32>
     where
         I: IntoIterator<Item = T> + std::fmt::Debug,
         T: Into<OsString> + Clone,
     {
-        let cli = Cli::parse_from(args);
-        env_logger::Builder::new()
-        .filter_level(cli.verbose.log_level_filter())
-        .init();
+        Self::parse_args(args).run(interpreter)
+    }
+
+    pub fn parse_args<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        Cli::parse_from(args)
+    }
+
+    /// The variables the session starts with: those of the parent process, or only
+    /// [`MINIMAL_ENV`] of them with `--no-env`, then the `-D` defines.
+    fn env_vars(
+        &self,
+        inherited: impl IntoIterator<Item = (String, String)>,
+    ) -> HashMap<String, String> {
+        let mut env_vars: HashMap<String, String> = inherited
+            .into_iter()
+            .filter(|(name, _)| !self.no_env || MINIMAL_ENV.contains(&name.as_str()))
+            .collect();
+        for define in self.defines.iter().flatten() {
+            // the form was checked by `parse_define`
+            if let Some((name, value)) = define.split_once('=') {
+                env_vars.insert(name.to_string(), value.to_string());
+            }
+        }
+        env_vars
+    }
 
-        if let Some(command) = cli.command {
+    /// Runs the parsed CLI and returns the exit code of the process. Python code is run by
+    /// `interpreter`.
+    pub fn run(self, interpreter: Box<dyn Interpreter>) -> Result<i32> {
+        // `try_init`, as the CLI may run more than once in a process, e.g. from Python
+        let _ = env_logger::Builder::new()
+        .filter_level(self.verbose.log_level_filter())
+        .try_init();
+
+        match &self.subcommand {
+            Some(Commands::Check(args)) => return check::run(args, &mut std::io::stdout().lock()),
+            Some(Commands::Convert(args)) => {
+                return convert::run(
+                    args,
+                    &mut std::io::stdout().lock(),
+                    &mut std::io::stderr().lock(),
+                );
+            }
+            Some(Commands::Lsp) => return lsp::run(),
+            None => {}
+        }
+
+        let mut session = session::Session::new(
+            self.env_vars(std::env::vars()),
+            &std::env::current_dir()?,
+            interpreter,
+        )?;
+        if let Some(command) = self.command {
             log::info!("Running command: {}", command);
-            return Ok(());
+            return Ok(session.run(&command, "<string>").code());
+        }
+        if let Some(script) = &self.script_file {
+            log::info!("Running script: {}", script.display());
+            return Ok(session.run_script(script, &self.args).code());
+        }
+        if !std::io::stdin().is_terminal() {
+            log::info!("Running script from stdin");
+            return Ok(session.run_script(Path::new("-"), &[]).code());
         }
 
         log::info!("Starting interactive shell");
-        let mut shell = shell::Shell::new()?;
-        // let mut shell = rustyline::DefaultEditor::new()?;
-        shell.run()?;
-        Ok(())
+        if !self.no_rc {
+            rc::load(&mut session, self.rc.as_deref(), &mut std::io::stderr().lock())?;
+        }
+        let mut shell = shell::Shell::new(session)?;
+        shell.run()
     }
 }
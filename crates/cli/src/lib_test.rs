@@ -12,11 +12,13 @@ fn test_cli_parser() {
         no_rc: false,
         no_env: false,
         defines: None,
+        no_cache: false,
         verbose: Verbosity {
             verbose: 0,
             quiet: 0,
             phantom: PhantomData<clap_verbosity_flag::WarnLevel>,
         },
+        subcommand: None,
         script_file: None,
         args: [],
     }
@@ -39,11 +41,13 @@ fn test_cli_with_rc_files() {
         no_rc: false,
         no_env: false,
         defines: None,
+        no_cache: false,
         verbose: Verbosity {
             verbose: 0,
             quiet: 0,
             phantom: PhantomData<clap_verbosity_flag::WarnLevel>,
         },
+        subcommand: None,
         script_file: None,
         args: [],
     }
@@ -66,13 +70,56 @@ fn test_cli_with_defines() {
                 "OTHER=VAL2",
             ],
         ),
+        no_cache: false,
         verbose: Verbosity {
             verbose: 0,
             quiet: 0,
             phantom: PhantomData<clap_verbosity_flag::WarnLevel>,
         },
+        subcommand: None,
         script_file: None,
         args: [],
     }
     "#);
 }
+
+#[test]
+fn test_env_vars() {
+    let inherited = || {
+        [
+            ("PATH", "/bin"),
+            ("HOME", "/home/me"),
+            ("SECRET", "1"),
+            ("NAME", "old"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+    };
+    let cli = Cli::parse_from(["-DNAME=VAL", "-DEQ=a=b", "-DEMPTY="]);
+    let env_vars = cli.env_vars(inherited());
+    assert_eq!(env_vars["NAME"], "VAL");
+    assert_eq!(env_vars["EQ"], "a=b");
+    assert_eq!(env_vars["EMPTY"], "");
+    assert_eq!(env_vars["SECRET"], "1");
+
+    let cli = Cli::parse_from(["--no-env", "-D_NAME=VAL"]);
+    let mut names: Vec<_> = cli.env_vars(inherited()).into_keys().collect();
+    names.sort();
+    assert_eq!(names, ["HOME", "PATH", "_NAME"]);
+}
+
+#[test]
+fn test_invalid_defines() {
+    for define in ["-DNAME", "-D=VAL", "-D1NAME=VAL", "-DNA-ME=VAL"] {
+        let err = Cli::try_parse_from([define]).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            clap::error::ErrorKind::ValueValidation,
+            "{define}"
+        );
+    }
+    let err = Cli::try_parse_from(["-DNAME"]).unwrap_err().to_string();
+    assert!(
+        err.contains("invalid value 'NAME' for '-D <DEFINES>': expected NAME=VAL"),
+        "{err}"
+    );
+}
@@ -0,0 +1,93 @@
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+use ruff_source_file::{LineIndex, OneIndexed, SourceCode};
+use ruff_text_size::{TextRange, TextSize};
+
+/// An open text document with conversions between byte offsets and LSP positions.
+///
+/// LSP positions count characters in UTF-16 code units.
+pub(super) struct Document {
+    text: String,
+    line_index: LineIndex,
+    version: i32,
+}
+
+impl Document {
+    pub(super) fn new(text: String, version: i32) -> Self {
+        let line_index = LineIndex::from_source_text(&text);
+        Self {
+            text,
+            line_index,
+            version,
+        }
+    }
+
+    pub(super) fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub(super) fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub(super) fn source_code(&self) -> SourceCode<'_, '_> {
+        SourceCode::new(&self.text, &self.line_index)
+    }
+
+    /// Applies a `didChange` event, either a full replacement or a range edit.
+    pub(super) fn apply_change(&mut self, change: TextDocumentContentChangeEvent, version: i32) {
+        match change.range {
+            Some(range) => {
+                let range = self.text_range(range);
+                self.text.replace_range(
+                    usize::from(range.start())..usize::from(range.end()),
+                    &change.text,
+                );
+            }
+            None => self.text = change.text,
+        }
+        self.line_index = LineIndex::from_source_text(&self.text);
+        self.version = version;
+    }
+
+    /// The byte offset of `position`, clamped to the end of its line.
+    pub(super) fn offset(&self, position: Position) -> TextSize {
+        let source = self.source_code();
+        let line = position.line as usize;
+        if line >= source.line_count() {
+            return TextSize::of(self.text.as_str());
+        }
+        let line = OneIndexed::from_zero_indexed(line);
+        let line_start = source.line_start(line);
+        let mut offset = line_start;
+        let mut remaining = position.character as usize;
+        for c in source.line_text(line).chars() {
+            if remaining == 0 || matches!(c, '\n' | '\r') {
+                break;
+            }
+            remaining = remaining.saturating_sub(c.len_utf16());
+            offset += TextSize::of(c);
+        }
+        offset
+    }
+
+    pub(super) fn position(&self, offset: TextSize) -> Position {
+        let source = self.source_code();
+        let line = source.line_index(offset);
+        let line_start = source.line_start(line);
+        let character = self.text[usize::from(line_start)..usize::from(offset)]
+            .encode_utf16()
+            .count();
+        Position::new(
+            u32::try_from(line.to_zero_indexed()).unwrap_or(u32::MAX),
+            u32::try_from(character).unwrap_or(u32::MAX),
+        )
+    }
+
+    pub(super) fn range(&self, range: TextRange) -> Range {
+        Range::new(self.position(range.start()), self.position(range.end()))
+    }
+
+    pub(super) fn text_range(&self, range: Range) -> TextRange {
+        TextRange::new(self.offset(range.start), self.offset(range.end))
+    }
+}
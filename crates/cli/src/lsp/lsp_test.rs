@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use lsp_server::RequestId;
+use lsp_types::notification::{Exit, Initialized};
+use lsp_types::request::{Initialize, Shutdown};
+use lsp_types::{
+    CompletionParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, InitializeParams, InitializedParams, Position,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, VersionedTextDocumentIdentifier,
+};
+use ruff_text_size::TextSize;
+
+use super::document::Document;
+use super::*;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A scripted client talking to the server over an in-memory connection.
+struct TestClient {
+    connection: Connection,
+    server: Option<JoinHandle<Result<()>>>,
+    notifications: VecDeque<Notification>,
+    next_id: i32,
+}
+
+impl TestClient {
+    fn start() -> Self {
+        let (connection, server) = Connection::memory();
+        let server = std::thread::spawn(move || serve(&server));
+        let mut client = Self {
+            connection,
+            server: Some(server),
+            notifications: VecDeque::new(),
+            next_id: 0,
+        };
+        client.request::<Initialize>(InitializeParams::default());
+        client.notify::<Initialized>(InitializedParams {});
+        client
+    }
+
+    fn request<R: LspRequest>(&mut self, params: R::Params) -> R::Result {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let request = Request::new(id.clone(), R::METHOD.to_string(), params);
+        self.connection.sender.send(request.into()).unwrap();
+        loop {
+            match self.connection.receiver.recv_timeout(TIMEOUT).unwrap() {
+                Message::Response(response) if response.id == id => {
+                    assert!(response.error.is_none(), "{:?}", response.error);
+                    return serde_json::from_value(response.result.unwrap()).unwrap();
+                }
+                Message::Notification(notification) => self.notifications.push_back(notification),
+                message => panic!("unexpected message {message:?}"),
+            }
+        }
+    }
+
+    fn notify<N: LspNotification>(&self, params: N::Params) {
+        let notification = Notification::new(N::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into()).unwrap();
+    }
+
+    fn diagnostics(&mut self) -> PublishDiagnosticsParams {
+        let notification = match self.notifications.pop_front() {
+            Some(notification) => notification,
+            None => match self.connection.receiver.recv_timeout(TIMEOUT).unwrap() {
+                Message::Notification(notification) => notification,
+                message => panic!("unexpected message {message:?}"),
+            },
+        };
+        notification.extract(PublishDiagnostics::METHOD).unwrap()
+    }
+
+    fn open(&mut self, text: &str) -> Url {
+        let uri = Url::parse("file:///tmp/test.oxy").unwrap();
+        self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri.clone(), "oxipy".into(), 1, text.into()),
+        });
+        uri
+    }
+
+    fn position(uri: &Url, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            Position::new(line, character),
+        )
+    }
+}
+
+impl Drop for TestClient {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        self.request::<Shutdown>(());
+        self.notify::<Exit>(());
+        if let Some(server) = self.server.take() {
+            server.join().unwrap().unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_document_positions() {
+    let document = Document::new("a = 1\né😀 = 2\n".into(), 0);
+    let offset = TextSize::from(u32::try_from("a = 1\né😀".len()).unwrap());
+    assert_eq!(document.position(offset), Position::new(1, 3));
+    assert_eq!(document.offset(Position::new(1, 3)), offset);
+    assert_eq!(document.offset(Position::new(0, 99)), TextSize::from(5));
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut client = TestClient::start();
+    let uri = client.open("$[echo *.py]\n");
+    let published = client.diagnostics();
+    assert_eq!(published.uri, uri);
+    let codes: Vec<_> = published
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.code.clone())
+        .collect();
+    assert_eq!(codes, [Some(NumberOrString::String("OX002".into()))]);
+    assert_eq!(
+        published.diagnostics[0].range,
+        lsp_types::Range::new(Position::new(0, 7), Position::new(0, 11))
+    );
+
+    client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: Some(lsp_types::Range::new(
+                Position::new(0, 7),
+                Position::new(0, 11),
+            )),
+            range_length: None,
+            text: "'*.py'".into(),
+        }],
+    });
+    let published = client.diagnostics();
+    assert_eq!(published.version, Some(2));
+    assert!(published.diagnostics.is_empty());
+
+    client.notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+        text_document: TextDocumentIdentifier::new(uri),
+    });
+    assert!(client.diagnostics().diagnostics.is_empty());
+}
+
+#[test]
+fn test_hover() {
+    let mut client = TestClient::start();
+    let uri = client.open("$[echo hi]\n");
+    client.diagnostics();
+    let hover = client
+        .request::<HoverRequest>(HoverParams {
+            text_document_position_params: TestClient::position(&uri, 0, 3),
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        })
+        .unwrap();
+    let HoverContents::Markup(contents) = hover.contents else {
+        panic!("expected markup");
+    };
+    assert_eq!(contents.value, "`echo`: builtin command");
+
+    let hover = client.request::<HoverRequest>(HoverParams {
+        text_document_position_params: TestClient::position(&uri, 0, 8),
+        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+    });
+    assert!(hover.is_none());
+}
+
+#[test]
+fn test_completion() {
+    let mut client = TestClient::start();
+    let uri = client.open("$[ech\n");
+    client.diagnostics();
+    let Some(CompletionResponse::Array(items)) = client.request::<Completion>(CompletionParams {
+        text_document_position: TestClient::position(&uri, 0, 5),
+        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        partial_result_params: lsp_types::PartialResultParams::default(),
+        context: None,
+    }) else {
+        panic!("expected completion items");
+    };
+    assert!(items.iter().any(|item| item.label == "echo"));
+}
+
+#[test]
+fn test_completion_context() {
+    assert_eq!(
+        CompletionContext::from_line("x = $(ec"),
+        CompletionContext::Command("ec")
+    );
+    assert_eq!(
+        CompletionContext::from_line("ls | gr"),
+        CompletionContext::Command("gr")
+    );
+    assert_eq!(
+        CompletionContext::from_line("$[ls src/ma"),
+        CompletionContext::Argument("src/ma")
+    );
+    assert_eq!(
+        CompletionContext::from_line("print($HO"),
+        CompletionContext::EnvVar("HO")
+    );
+}
+
+#[test]
+fn test_document_symbols() {
+    let mut client = TestClient::start();
+    let uri = client.open("class A:\n    def f(self):\n        pass\n\nx = 1\n");
+    client.diagnostics();
+    let Some(DocumentSymbolResponse::Nested(symbols)) =
+        client.request::<DocumentSymbolRequest>(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier::new(uri),
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        })
+    else {
+        panic!("expected nested symbols");
+    };
+    let names: Vec<_> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["A", "x"]);
+    let children = symbols[0].children.as_ref().unwrap();
+    assert_eq!(children[0].name, "f");
+    assert_eq!(children[0].kind, lsp_types::SymbolKind::METHOD);
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let mut client = TestClient::start();
+    let uri = client.open("# hi\nx = 1\n$[ls -l]\n");
+    client.diagnostics();
+    let Some(SemanticTokensResult::Tokens(tokens)) =
+        client.request::<SemanticTokensFullRequest>(SemanticTokensParams {
+            text_document: TextDocumentIdentifier::new(uri),
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        })
+    else {
+        panic!("expected semantic tokens");
+    };
+    let data: Vec<_> = tokens
+        .data
+        .iter()
+        .map(|token| {
+            (
+                token.delta_line,
+                token.delta_start,
+                token.length,
+                token.token_type,
+            )
+        })
+        .collect();
+    // comment, `=`, `1`, then `$[`, `ls`, `-l` and `]`
+    assert_eq!(
+        data,
+        [
+            (0, 0, 4, 9),
+            (1, 2, 1, 4),
+            (0, 2, 1, 8),
+            (1, 0, 2, 6),
+            (0, 2, 2, 0),
+            (0, 3, 2, 1),
+            (0, 2, 1, 4)
+        ]
+    );
+}
@@ -0,0 +1,318 @@
+//! `oxipy lsp`: a language server for shell-flavoured Python over stdio.
+
+#[cfg(test)]
+mod lsp_test;
+
+mod document;
+mod semantic_tokens;
+mod symbols;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, HoverRequest, Request as LspRequest,
+    SemanticTokensFullRequest,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DiagnosticSeverity, DocumentSymbolParams, DocumentSymbolResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, MarkupContent, MarkupKind, NumberOrString, OneOf,
+    PublishDiagnosticsParams, SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions,
+    SemanticTokensParams, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use ruff_python_ast::subproc::Pipeline;
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, PySourceType};
+use ruff_text_size::{Ranged, TextRange, TextSize};
+
+use crate::check::{self, CheckContext, Rule};
+use crate::completion::{CandidateKind, CompletionContext};
+use crate::which::CommandKind;
+use document::Document;
+
+/// Value of the `source` field of published diagnostics.
+const DIAGNOSTIC_SOURCE: &str = "oxipy";
+
+/// Entry point of `oxipy lsp`.
+pub(crate) fn run() -> Result<i32> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+    io_threads.join()?;
+    Ok(0)
+}
+
+fn server_capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["$".into(), "/".into()]),
+            ..CompletionOptions::default()
+        }),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            },
+        )),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Runs the initialize handshake and the main loop until the client asks for shutdown.
+pub(crate) fn serve(connection: &Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(server_capabilities())?)?;
+    let mut server = Server {
+        documents: HashMap::new(),
+        context: CheckContext::from_process()?,
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(notification) = server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    documents: HashMap<Url, Document>,
+    context: CheckContext,
+}
+
+impl Server {
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => dispatch::<HoverRequest>(request, |params| self.hover(params)),
+            Completion::METHOD => dispatch::<Completion>(request, |params| self.complete(params)),
+            DocumentSymbolRequest::METHOD => {
+                dispatch::<DocumentSymbolRequest>(request, |params| self.document_symbols(&params))
+            }
+            SemanticTokensFullRequest::METHOD => {
+                dispatch::<SemanticTokensFullRequest>(request, |params| {
+                    self.semantic_tokens(&params)
+                })
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unhandled method `{method}`"),
+            ),
+        }
+    }
+
+    /// Updates the open documents and returns the diagnostics to publish, if any.
+    fn handle_notification(&mut self, notification: Notification) -> Option<Notification> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidOpenTextDocument as LspNotification>::Params>(
+                        DidOpenTextDocument::METHOD,
+                    )
+                    .ok()?;
+                let document = params.text_document;
+                self.documents.insert(
+                    document.uri.clone(),
+                    Document::new(document.text, document.version),
+                );
+                document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidChangeTextDocument as LspNotification>::Params>(
+                        DidChangeTextDocument::METHOD,
+                    )
+                    .ok()?;
+                let uri = params.text_document.uri;
+                let document = self.documents.get_mut(&uri)?;
+                for change in params.content_changes {
+                    document.apply_change(change, params.text_document.version);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidCloseTextDocument as LspNotification>::Params>(
+                        DidCloseTextDocument::METHOD,
+                    )
+                    .ok()?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                return Some(publish_diagnostics(uri, Vec::new(), None));
+            }
+            _ => return None,
+        };
+
+        let document = self.documents.get(&uri)?;
+        let diagnostics = check::check_source(document.text(), &self.context)
+            .into_iter()
+            .map(|diagnostic| lsp_types::Diagnostic {
+                range: document.range(diagnostic.range),
+                severity: Some(if diagnostic.rule == Rule::SyntaxError {
+                    DiagnosticSeverity::ERROR
+                } else {
+                    DiagnosticSeverity::WARNING
+                }),
+                code: Some(NumberOrString::String(diagnostic.rule.code().to_string())),
+                source: Some(DIAGNOSTIC_SOURCE.to_string()),
+                message: diagnostic.message,
+                ..lsp_types::Diagnostic::default()
+            })
+            .collect();
+        let version = document.version();
+        Some(publish_diagnostics(uri, diagnostics, Some(version)))
+    }
+
+    /// The directory commands and relative paths of `uri` are resolved against.
+    fn base_dir(&self, uri: &Url) -> PathBuf {
+        uri.to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(PathBuf::from))
+            .unwrap_or_else(|| self.context.cwd.clone())
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let parsed =
+            ruff_python_parser::parse_unchecked_source(document.text(), PySourceType::Python);
+
+        let mut finder = CommandFinder {
+            offset: document.offset(position.position),
+            found: None,
+        };
+        finder.visit_body(parsed.suite());
+        let (program, range) = finder.found?;
+
+        let base_dir = self.base_dir(&position.text_document.uri);
+        let kind = CommandKind::resolve(&program, &base_dir, &self.context.env);
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: kind.describe(&program),
+            }),
+            range: Some(document.range(range)),
+        })
+    }
+
+    fn complete(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let offset = document.offset(position.position);
+        let line_start = document.offset(lsp_types::Position::new(position.position.line, 0));
+        let line = &document.text()[usize::from(line_start)..usize::from(offset)];
+
+        let base_dir = self.base_dir(&position.text_document.uri);
+        let items = CompletionContext::from_line(line)
+            .candidates(&base_dir, &self.context.env)
+            .into_iter()
+            .map(|candidate| CompletionItem {
+                label: candidate.text,
+                kind: Some(match candidate.kind {
+                    CandidateKind::Builtin | CandidateKind::Executable => {
+                        CompletionItemKind::FUNCTION
+                    }
+                    CandidateKind::Directory => CompletionItemKind::FOLDER,
+                    CandidateKind::File => CompletionItemKind::FILE,
+                    CandidateKind::EnvVar => CompletionItemKind::VARIABLE,
+                }),
+                detail: candidate.detail,
+                ..CompletionItem::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn document_symbols(&self, params: &DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let parsed =
+            ruff_python_parser::parse_unchecked_source(document.text(), PySourceType::Python);
+        Some(DocumentSymbolResponse::Nested(symbols::document_symbols(
+            document,
+            parsed.suite(),
+        )))
+    }
+
+    fn semantic_tokens(&self, params: &SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::semantic_tokens(document),
+        }))
+    }
+}
+
+/// Deserializes the params of `request`, runs `handler` and wraps its result in a response.
+fn dispatch<R: LspRequest>(
+    request: Request,
+    handler: impl FnOnce(R::Params) -> R::Result,
+) -> Response {
+    let id = request.id.clone();
+    match request.extract::<R::Params>(R::METHOD) {
+        Ok((id, params)) => Response::new_ok(id, handler(params)),
+        Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+    }
+}
+
+fn publish_diagnostics(
+    uri: Url,
+    diagnostics: Vec<lsp_types::Diagnostic>,
+    version: Option<i32>,
+) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        },
+    )
+}
+
+/// Finds the literal program name of a pipeline stage under `offset`.
+struct CommandFinder {
+    offset: TextSize,
+    found: Option<(String, TextRange)>,
+}
+
+impl<'a> Visitor<'a> for CommandFinder {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Some(pipeline) = Pipeline::from_expr(expr) {
+            for stage in &pipeline.stages {
+                if let (Some(program), Some(program_expr)) = (stage.program(), stage.program_expr())
+                {
+                    if program_expr.range().contains_inclusive(self.offset) {
+                        self.found = Some((program.to_string(), program_expr.range()));
+                    }
+                }
+            }
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,75 @@
+use lsp_types::{SemanticToken, SemanticTokenType, SemanticTokensLegend};
+use ruff_text_size::{Ranged, TextSize};
+
+use super::document::Document;
+use crate::highlight::{self, SemanticRole};
+
+/// Token types reported to the client, indexed by [`token_type`].
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::REGEXP,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::STRING,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+];
+
+pub(super) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: Vec::new(),
+    }
+}
+
+/// Index into [`TOKEN_TYPES`] for a highlighting role.
+fn token_type(role: SemanticRole) -> u32 {
+    match role {
+        SemanticRole::Command => 0,
+        SemanticRole::Argument | SemanticRole::Flag => 1,
+        SemanticRole::EnvVar => 2,
+        SemanticRole::Glob | SemanticRole::Regex => 3,
+        SemanticRole::Operator | SemanticRole::Redirect => 4,
+        SemanticRole::String | SemanticRole::Path => 5,
+        SemanticRole::Subproc => 6,
+        SemanticRole::PythonKeyword => 7,
+        SemanticRole::Number => 8,
+        SemanticRole::Comment => 9,
+    }
+}
+
+/// Encodes the highlighting of `document` in the relative LSP format.
+///
+/// Tokens spanning several lines are split, since not every client supports multiline tokens.
+pub(super) fn semantic_tokens(document: &Document) -> Vec<SemanticToken> {
+    let mut data = Vec::new();
+    let mut last = lsp_types::Position::default();
+    for token in highlight::semantic_tokens(document.text()) {
+        let token_type = token_type(token.role);
+        let mut offset = token.start();
+        for segment in document.text()[token.range()].split_inclusive('\n') {
+            let text = segment.trim_end_matches(['\n', '\r']);
+            let position = document.position(offset);
+            offset += TextSize::of(segment);
+            if text.is_empty() {
+                continue;
+            }
+            data.push(SemanticToken {
+                delta_line: position.line - last.line,
+                delta_start: if position.line == last.line {
+                    position.character - last.character
+                } else {
+                    position.character
+                },
+                length: u32::try_from(text.encode_utf16().count()).unwrap_or(u32::MAX),
+                token_type,
+                token_modifiers_bitset: 0,
+            });
+            last = position;
+        }
+    }
+    data
+}
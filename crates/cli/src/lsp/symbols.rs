@@ -0,0 +1,114 @@
+use lsp_types::{DocumentSymbol, SymbolKind};
+use ruff_python_ast::statement_visitor::{self, StatementVisitor};
+use ruff_python_ast::{Expr, Stmt};
+use ruff_text_size::{Ranged, TextRange};
+
+use super::document::Document;
+
+/// Collects functions, classes and assigned names as a tree of symbols.
+struct SymbolCollector<'a> {
+    document: &'a Document,
+    /// Symbols of the scopes being visited, innermost last.
+    scopes: Vec<Vec<DocumentSymbol>>,
+    in_class: bool,
+}
+
+impl SymbolCollector<'_> {
+    #[allow(deprecated)]
+    fn symbol(
+        &self,
+        name: &str,
+        kind: SymbolKind,
+        range: TextRange,
+        name_range: TextRange,
+    ) -> DocumentSymbol {
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: self.document.range(range),
+            selection_range: self.document.range(name_range),
+            children: None,
+        }
+    }
+
+    fn push(&mut self, symbol: DocumentSymbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(symbol);
+        }
+    }
+
+    /// Pushes `symbol` with the symbols found in `body` as its children.
+    fn push_scope(&mut self, mut symbol: DocumentSymbol, body: &[Stmt], in_class: bool) {
+        let outer = std::mem::replace(&mut self.in_class, in_class);
+        self.scopes.push(Vec::new());
+        statement_visitor::walk_body(self, body);
+        let children = self.scopes.pop().unwrap_or_default();
+        self.in_class = outer;
+        symbol.children = Some(children);
+        self.push(symbol);
+    }
+
+    fn push_target(&mut self, target: &Expr) {
+        match target {
+            Expr::Name(name) => {
+                let kind = if self.in_class {
+                    SymbolKind::FIELD
+                } else {
+                    SymbolKind::VARIABLE
+                };
+                let symbol = self.symbol(&name.id, kind, name.range, name.range);
+                self.push(symbol);
+            }
+            Expr::Tuple(tuple) => tuple.iter().for_each(|elt| self.push_target(elt)),
+            Expr::List(list) => list.iter().for_each(|elt| self.push_target(elt)),
+            _ => {}
+        }
+    }
+}
+
+impl<'a> StatementVisitor<'a> for SymbolCollector<'_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::FunctionDef(function) => {
+                let kind = if self.in_class {
+                    SymbolKind::METHOD
+                } else {
+                    SymbolKind::FUNCTION
+                };
+                let symbol =
+                    self.symbol(&function.name, kind, function.range, function.name.range());
+                self.push_scope(symbol, &function.body, false);
+            }
+            Stmt::ClassDef(class) => {
+                let symbol = self.symbol(
+                    &class.name,
+                    SymbolKind::CLASS,
+                    class.range,
+                    class.name.range(),
+                );
+                self.push_scope(symbol, &class.body, true);
+            }
+            Stmt::Assign(assign) => {
+                for target in &assign.targets {
+                    self.push_target(target);
+                }
+            }
+            Stmt::AnnAssign(assign) => self.push_target(&assign.target),
+            _ => statement_visitor::walk_stmt(self, stmt),
+        }
+    }
+}
+
+/// The symbols of `suite`, nested by scope.
+pub(super) fn document_symbols(document: &Document, suite: &[Stmt]) -> Vec<DocumentSymbol> {
+    let mut collector = SymbolCollector {
+        document,
+        scopes: vec![Vec::new()],
+        in_class: false,
+    };
+    collector.visit_body(suite);
+    collector.scopes.pop().unwrap_or_default()
+}
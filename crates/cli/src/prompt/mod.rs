@@ -0,0 +1,474 @@
+//! The prompts of the REPL, rendered from templates before each entry.
+//!
+//! `$OXIPY_PROMPT` is the prompt, `$OXIPY_RIGHT_PROMPT` is shown at the right edge of the
+//! terminal while the line leaves room for it, and `$OXIPY_CONTINUATION_PROMPT` starts the
+//! later lines of an entry, like those of a block after `if x:`. In a template, `{name}` is
+//! replaced by a field:
+//!
+//! | Field        | Value                                                        |
+//! |--------------|--------------------------------------------------------------|
+//! | `cwd`        | The working directory, with `~` for the home directory       |
+//! | `short_cwd`  | The same with each parent shortened to a letter, `~/s/oxipy` |
+//! | `user`       | `$USER`                                                      |
+//! | `hostname`   | The host name up to its first `.`                            |
+//! | `git_branch` | The branch of the git repository, or its short commit        |
+//! | `git_dirty`  | `*` when tracked files of the repository changed             |
+//! | `last_exit`  | The exit status of the last entry, empty when it was 0       |
+//! | `duration`   | How long the last entry ran, empty under a second            |
+//! | `venv`       | The name of the active virtual or conda environment          |
+//! | `time`       | The local time, as `HH:MM:SS`                                |
+//!
+//! `git_dirty` runs `git status`, which is given 200ms and counted as clean when it takes longer,
+//! like in a large repository.
+//!
+//! `{name:FORMAT}` renders `FORMAT` with `{}` as the value, or nothing when the value is empty,
+//! so `{git_branch: ({})}` only shows up in a repository. Colors are fields too, like `{GREEN}`,
+//! `{BOLD_RED}` or `{RESET}`, and `{{` and `}}` are literal braces.
+//!
+//! Fields set in `ox.prompt_fields` come first, so an RC file can add fields, or replace the
+//! ones above, with Python callables:
+//!
+//! ```python
+//! ox.prompt_fields["jobs"] = lambda: len($(jobs).splitlines())
+//! ```
+
+#[cfg(test)]
+mod prompt_test;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::Chars;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+
+pub(crate) const PROMPT_VAR: &str = "OXIPY_PROMPT";
+pub(crate) const RIGHT_PROMPT_VAR: &str = "OXIPY_RIGHT_PROMPT";
+pub(crate) const CONTINUATION_PROMPT_VAR: &str = "OXIPY_CONTINUATION_PROMPT";
+
+const DEFAULT_PROMPT: &str = "{BOLD_BLUE}{short_cwd}{RESET}\
+    {git_branch: {PURPLE}{}{git_dirty}{RESET}}{last_exit: {RED}[{}]{RESET}}> ";
+const DEFAULT_RIGHT_PROMPT: &str = "";
+const DEFAULT_CONTINUATION_PROMPT: &str = "... ";
+
+/// How long `git status` may run for `{git_dirty}`, which is rendered before every entry.
+const GIT_STATUS_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The SGR parameters of the colors, which `BOLD_` makes bold.
+const COLORS: &[(&str, &str)] = &[
+    ("BLACK", "30"),
+    ("RED", "31"),
+    ("GREEN", "32"),
+    ("YELLOW", "33"),
+    ("BLUE", "34"),
+    ("PURPLE", "35"),
+    ("CYAN", "36"),
+    ("WHITE", "37"),
+];
+
+/// The SGR parameters of a color field like `RED`, `BOLD_RED` or `RESET`.
+fn color(name: &str) -> Option<String> {
+    match name {
+        "RESET" => return Some("0".to_string()),
+        "BOLD" => return Some("1".to_string()),
+        _ => {}
+    }
+    let (bold, name) = match name.strip_prefix("BOLD_") {
+        Some(name) => (true, name),
+        None => (false, name),
+    };
+    let (_, code) = COLORS.iter().find(|(color, _)| *color == name)?;
+    Some(if bold {
+        format!("1;{code}")
+    } else {
+        (*code).to_string()
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    /// `{name}` or `{name:FORMAT}`; the name is empty for the `{}` of a format.
+    Field {
+        name: String,
+        format: Option<Vec<Part>>,
+    },
+}
+
+/// A parsed prompt template, see the module docs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Template(Vec<Part>);
+
+/// A rendered prompt: the text the line editor measures, and the same text with its colors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Rendered {
+    pub(crate) plain: String,
+    pub(crate) colored: String,
+}
+
+impl Rendered {
+    fn push_str(&mut self, text: &str) {
+        self.plain.push_str(text);
+        self.colored.push_str(text);
+    }
+}
+
+impl Template {
+    pub(crate) fn parse(text: &str) -> Result<Self> {
+        Ok(Self(parse_parts(&mut text.chars().peekable(), false)?))
+    }
+
+    /// Renders the template. `field` gives the value of a field, or `None` for an unknown one,
+    /// which is kept as it was written.
+    pub(crate) fn render(&self, mut field: impl FnMut(&str) -> Option<String>) -> Rendered {
+        let mut rendered = Rendered::default();
+        render_parts(&self.0, None, &mut field, &mut rendered);
+        if rendered.colored != rendered.plain {
+            rendered.colored.push_str("\x1b[0m");
+        }
+        rendered
+    }
+
+    /// Adds the names of the fields of the template, other than colors, to `names`.
+    fn field_names<'a>(&'a self, names: &mut BTreeSet<&'a str>) {
+        fn add<'a>(parts: &'a [Part], names: &mut BTreeSet<&'a str>) {
+            for part in parts {
+                if let Part::Field { name, format } = part {
+                    if !name.is_empty() && color(name).is_none() {
+                        names.insert(name);
+                    }
+                    if let Some(format) = format {
+                        add(format, names);
+                    }
+                }
+            }
+        }
+        add(&self.0, names);
+    }
+}
+
+/// Parses up to the end of `chars`, or when `nested` up to the `}` ending a format.
+fn parse_parts(chars: &mut Peekable<Chars<'_>>, nested: bool) -> Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+            '}' if nested => {
+                if !text.is_empty() {
+                    parts.push(Part::Text(text));
+                }
+                return Ok(parts);
+            }
+            '}' => bail!("unmatched `}}`, write `}}}}` for a brace"),
+            '{' => {
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                let mut name = String::new();
+                let format = loop {
+                    match chars.next() {
+                        Some('}') => break None,
+                        Some(':') => break Some(parse_parts(chars, true)?),
+                        Some(c) => name.push(c),
+                        None => bail!("unclosed `{{`, write `{{{{` for a brace"),
+                    }
+                };
+                parts.push(Part::Field {
+                    name: name.trim().to_string(),
+                    format,
+                });
+            }
+            _ => text.push(c),
+        }
+    }
+    if nested {
+        bail!("unclosed `{{`, write `{{{{` for a brace");
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Ok(parts)
+}
+
+/// Renders `parts`, with `value` as the `{}` of a format.
+fn render_parts(
+    parts: &[Part],
+    value: Option<&str>,
+    field: &mut impl FnMut(&str) -> Option<String>,
+    rendered: &mut Rendered,
+) {
+    for part in parts {
+        let (name, format) = match part {
+            Part::Text(text) => {
+                rendered.push_str(text);
+                continue;
+            }
+            Part::Field { name, format } => (name, format),
+        };
+        if name.is_empty() {
+            rendered.push_str(value.unwrap_or("{}"));
+        } else if let Some(sgr) = color(name) {
+            let _ = write!(rendered.colored, "\x1b[{sgr}m");
+        } else {
+            match (field(name), format) {
+                (Some(value), None) => rendered.push_str(&value),
+                (Some(value), Some(format)) if !value.is_empty() => {
+                    render_parts(format, Some(&value), field, rendered);
+                }
+                (Some(_), Some(_)) => {}
+                (None, _) => rendered.push_str(&format!("{{{name}}}")),
+            }
+        }
+    }
+}
+
+/// The templates of the prompts of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Templates {
+    prompt: Template,
+    right: Template,
+    continuation: Template,
+}
+
+/// The prompts for the next entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Prompts {
+    pub(crate) prompt: Rendered,
+    /// Empty when there is no right prompt.
+    pub(crate) right: Rendered,
+    pub(crate) continuation: Rendered,
+}
+
+impl Templates {
+    /// The templates set in `env`, or the default ones for those that aren't set or are
+    /// invalid.
+    pub(crate) fn from_env(env: &HashMap<String, String>) -> Self {
+        let template = |var: &str, default: &str| {
+            let default = || Template::parse(default).expect("default templates are valid");
+            let Some(text) = env.get(var) else {
+                return default();
+            };
+            Template::parse(text).unwrap_or_else(|err| {
+                log::warn!("Ignoring ${var}: {err}");
+                default()
+            })
+        };
+        Self {
+            prompt: template(PROMPT_VAR, DEFAULT_PROMPT),
+            right: template(RIGHT_PROMPT_VAR, DEFAULT_RIGHT_PROMPT),
+            continuation: template(CONTINUATION_PROMPT_VAR, DEFAULT_CONTINUATION_PROMPT),
+        }
+    }
+
+    /// The names of the fields the templates use, other than colors.
+    pub(crate) fn field_names(&self) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        for template in [&self.prompt, &self.right, &self.continuation] {
+            template.field_names(&mut names);
+        }
+        names.into_iter().map(str::to_string).collect()
+    }
+
+    /// Renders the prompts with the `custom` fields first, then those of [`field`], each
+    /// computed once.
+    pub(crate) fn render(
+        &self,
+        context: &Context<'_>,
+        custom: &HashMap<String, String>,
+    ) -> Prompts {
+        let mut computed: HashMap<String, Option<String>> = HashMap::new();
+        let mut value = |name: &str| {
+            if let Some(value) = custom.get(name) {
+                return Some(value.clone());
+            }
+            computed
+                .entry(name.to_string())
+                .or_insert_with(|| field(name, context))
+                .clone()
+        };
+        Prompts {
+            prompt: self.prompt.render(&mut value),
+            right: self.right.render(&mut value),
+            continuation: self.continuation.render(&mut value),
+        }
+    }
+}
+
+/// What the fields of the REPL are computed from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Context<'a> {
+    pub(crate) cwd: &'a Path,
+    pub(crate) env: &'a HashMap<String, String>,
+    /// The exit status and run time of the last entry.
+    pub(crate) last_exit: i32,
+    pub(crate) duration: Duration,
+}
+
+/// The value of the field `name` of the REPL, or `None` when there is no such field.
+pub(crate) fn field(name: &str, context: &Context<'_>) -> Option<String> {
+    let env = context.env;
+    let value = match name {
+        "cwd" => tilde_cwd(context.cwd, env),
+        "short_cwd" => short_cwd(&tilde_cwd(context.cwd, env)),
+        "user" => env
+            .get("USER")
+            .or_else(|| env.get("LOGNAME"))
+            .cloned()
+            .unwrap_or_default(),
+        "hostname" => hostname(env),
+        "git_branch" => git_dir(context.cwd)
+            .and_then(|dir| git_branch(&dir))
+            .unwrap_or_default(),
+        "git_dirty" => {
+            let dirty =
+                git_dir(context.cwd).is_some() && git_dirty(context.cwd, env, GIT_STATUS_TIMEOUT);
+            if dirty { "*" } else { "" }.to_string()
+        }
+        "last_exit" => match context.last_exit {
+            0 => String::new(),
+            status => status.to_string(),
+        },
+        "duration" => format_duration(context.duration),
+        "venv" => env
+            .get("VIRTUAL_ENV")
+            .and_then(|venv| Path::new(venv).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .or_else(|| env.get("CONDA_DEFAULT_ENV").cloned())
+            .unwrap_or_default(),
+        "time" => chrono::Local::now().format("%H:%M:%S").to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// `cwd` with `~` for the home directory.
+fn tilde_cwd(cwd: &Path, env: &HashMap<String, String>) -> String {
+    let home = env.get("HOME").filter(|home| !home.is_empty());
+    match home.and_then(|home| cwd.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => cwd.display().to_string(),
+    }
+}
+
+/// `cwd` with each parent directory shortened to its first letter, or two for hidden ones.
+fn short_cwd(cwd: &str) -> String {
+    let Some((parents, last)) = cwd.rsplit_once('/') else {
+        return cwd.to_string();
+    };
+    let parents: Vec<String> = parents
+        .split('/')
+        .map(|parent| {
+            let letters = if parent.starts_with('.') { 2 } else { 1 };
+            parent.chars().take(letters).collect()
+        })
+        .collect();
+    format!("{}/{last}", parents.join("/"))
+}
+
+fn hostname(env: &HashMap<String, String>) -> String {
+    let hostname = env
+        .get("HOSTNAME")
+        .cloned()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| {
+            let output = Command::new("hostname")
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            String::from_utf8(output.stdout).ok()
+        })
+        .unwrap_or_default();
+    let hostname = hostname.trim();
+    hostname.split('.').next().unwrap_or(hostname).to_string()
+}
+
+/// The git directory of the repository holding `cwd`.
+fn git_dir(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors().find_map(|dir| {
+        let git = dir.join(".git");
+        if git.is_dir() {
+            return Some(git);
+        }
+        // worktrees and submodules point to their git directory
+        let link = std::fs::read_to_string(&git).ok()?;
+        Some(dir.join(link.strip_prefix("gitdir:")?.trim()))
+    })
+}
+
+/// The branch checked out in `git_dir`, or the short commit when there is none.
+fn git_branch(git_dir: &Path) -> Option<String> {
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            )
+        }
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// Whether tracked files of the repository holding `cwd` changed, or `false` when `git status`
+/// doesn't finish within `timeout`.
+fn git_dirty(cwd: &Path, env: &HashMap<String, String>, timeout: Duration) -> bool {
+    let child = Command::new("git")
+        .args([
+            "--no-optional-locks",
+            "status",
+            "--porcelain",
+            "--untracked-files=no",
+        ])
+        .current_dir(cwd)
+        .env_clear()
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    // a long listing would fill the pipe before git exits
+    let reader = std::thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::sink()));
+    let started = Instant::now();
+    let success = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status.success(),
+            Ok(None) if started.elapsed() < timeout => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    };
+    success
+        && reader
+            .join()
+            .is_ok_and(|copied| copied.is_ok_and(|bytes| bytes > 0))
+}
+
+/// Like `2.5s`, `1m5s` or `2h3m`, or empty under a second.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0 => String::new(),
+        1..60 => format!("{:.1}s", duration.as_secs_f64()),
+        60..3600 => format!("{}m{}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
@@ -0,0 +1,157 @@
+use super::*;
+
+fn fields(name: &str) -> Option<String> {
+    match name {
+        "cwd" => Some("~/src".to_string()),
+        "git_branch" => Some("main".to_string()),
+        "last_exit" => Some(String::new()),
+        _ => None,
+    }
+}
+
+fn render(template: &str) -> Rendered {
+    Template::parse(template).unwrap().render(fields)
+}
+
+#[test]
+fn test_render_fields() {
+    assert_eq!(render("{cwd}> ").plain, "~/src> ");
+    assert_eq!(render("{cwd}{git_branch: ({})}> ").plain, "~/src (main)> ");
+    assert_eq!(render("{cwd}{last_exit: [{}]}> ").plain, "~/src> ");
+    assert_eq!(render("{{{cwd}}}").plain, "{~/src}");
+    // an unknown field shows up as written
+    assert_eq!(render("{nope}> ").plain, "{nope}> ");
+    assert_eq!(render("").plain, "");
+}
+
+#[test]
+fn test_render_colors() {
+    let rendered = render("{BOLD_BLUE}{cwd}{RESET}{git_branch: {PURPLE}{}}> ");
+    assert_eq!(rendered.plain, "~/src main> ");
+    assert_eq!(
+        rendered.colored,
+        "\x1b[1;34m~/src\x1b[0m \x1b[35mmain> \x1b[0m"
+    );
+    assert_eq!(render("{cwd}").colored, "~/src");
+}
+
+#[test]
+fn test_parse_errors() {
+    for (template, message) in [
+        ("{cwd", "unclosed `{`, write `{{` for a brace"),
+        ("{cwd: ({})", "unclosed `{`, write `{{` for a brace"),
+        ("cwd}", "unmatched `}`, write `}}` for a brace"),
+    ] {
+        assert_eq!(
+            Template::parse(template).unwrap_err().to_string(),
+            message,
+            "{template}"
+        );
+    }
+
+    let env = HashMap::from([(PROMPT_VAR.to_string(), "{cwd".to_string())]);
+    assert_eq!(
+        Templates::from_env(&env),
+        Templates::from_env(&HashMap::new())
+    );
+}
+
+#[test]
+fn test_custom_fields_come_first() {
+    let env = HashMap::from([
+        (PROMPT_VAR.to_string(), "{jobs} {last_exit}> ".to_string()),
+        (RIGHT_PROMPT_VAR.to_string(), "{BOLD}{duration}".to_string()),
+    ]);
+    let templates = Templates::from_env(&env);
+    assert_eq!(templates.field_names(), ["duration", "jobs", "last_exit"]);
+
+    let context = Context {
+        cwd: Path::new("/"),
+        env: &env,
+        last_exit: 1,
+        duration: Duration::from_millis(2500),
+    };
+    let custom = HashMap::from([("jobs".to_string(), "2".to_string())]);
+    let prompts = templates.render(&context, &custom);
+    assert_eq!(prompts.prompt.plain, "2 1> ");
+    assert_eq!(prompts.right.plain, "2.5s");
+    assert_eq!(prompts.continuation.plain, "... ");
+
+    let custom = HashMap::from([("last_exit".to_string(), "x".to_string())]);
+    assert_eq!(
+        templates.render(&context, &custom).prompt.plain,
+        "{jobs} x> "
+    );
+}
+
+#[test]
+fn test_cwd_fields() {
+    let env = HashMap::from([("HOME".to_string(), "/home/me".to_string())]);
+    let cwd = |cwd: &str| tilde_cwd(Path::new(cwd), &env);
+    assert_eq!(cwd("/home/me"), "~");
+    assert_eq!(cwd("/home/me/src/oxipy"), "~/src/oxipy");
+    assert_eq!(cwd("/home/melody"), "/home/melody");
+    assert_eq!(cwd("/usr/lib"), "/usr/lib");
+
+    assert_eq!(short_cwd("~/src/oxipy"), "~/s/oxipy");
+    assert_eq!(short_cwd("~/.config/oxipy/rc.d"), "~/.c/o/rc.d");
+    assert_eq!(short_cwd("/usr"), "/usr");
+    assert_eq!(short_cwd("/"), "/");
+    assert_eq!(short_cwd("~"), "~");
+}
+
+#[test]
+fn test_git_branch() {
+    let temp = tempfile::tempdir().unwrap();
+    let repo = temp.path();
+    let nested = repo.join("src");
+    std::fs::create_dir_all(repo.join(".git")).unwrap();
+    std::fs::create_dir_all(&nested).unwrap();
+    let branch = || git_dir(&nested).and_then(|dir| git_branch(&dir));
+
+    std::fs::write(repo.join(".git/HEAD"), "ref: refs/heads/feature/x\n").unwrap();
+    assert_eq!(branch().as_deref(), Some("feature/x"));
+    std::fs::write(repo.join(".git/HEAD"), "0123456789abcdef\n").unwrap();
+    assert_eq!(branch().as_deref(), Some("0123456"));
+
+    // a worktree points to its git directory
+    let worktree = repo.join("worktree");
+    std::fs::create_dir_all(&worktree).unwrap();
+    std::fs::write(worktree.join(".git"), "gitdir: ../.git\n").unwrap();
+    assert_eq!(git_dir(&worktree), Some(worktree.join("../.git")));
+}
+
+#[test]
+fn test_git_dirty() {
+    let temp = tempfile::tempdir().unwrap();
+    let repo = temp.path();
+    let env: HashMap<String, String> = std::env::vars().collect();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .current_dir(repo)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "{args:?}");
+    };
+    git(&["init", "-q"]);
+    std::fs::write(repo.join("file"), "a\n").unwrap();
+    git(&["add", "file"]);
+    git(&["commit", "-q", "-m", "a"]);
+    assert!(!git_dirty(repo, &env, Duration::from_secs(10)));
+
+    std::fs::write(repo.join("file"), "b\n").unwrap();
+    assert!(git_dirty(repo, &env, Duration::from_secs(10)));
+    // a slow `git status` is not waited for
+    assert!(!git_dirty(repo, &env, Duration::ZERO));
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(Duration::from_millis(400)), "");
+    assert_eq!(format_duration(Duration::from_millis(2500)), "2.5s");
+    assert_eq!(format_duration(Duration::from_secs(65)), "1m5s");
+    assert_eq!(format_duration(Duration::from_secs(7390)), "2h3m");
+}
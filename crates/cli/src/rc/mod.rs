@@ -0,0 +1,113 @@
+//! RC files, run in the session before the REPL starts.
+//!
+//! Without `--rc`, RC files are looked up from the most general to the most specific, so the
+//! user's settings win: `/etc/oxipy`, then the `oxipy` directory of each of `$XDG_CONFIG_DIRS`
+//! and finally the one of `$XDG_CONFIG_HOME` (`~/.config/oxipy`). In each directory, `rc.oxy`
+//! runs first, then the sources of `rc.d` in name order.
+
+#[cfg(test)]
+mod rc_test;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::check::SOURCE_EXTENSIONS;
+use crate::session::{Outcome, Session};
+
+const SYSTEM_DIR: &str = "/etc/oxipy";
+const DEFAULT_XDG_CONFIG_DIRS: &str = "/etc/xdg";
+
+/// The directories RC files are looked up in, from the most general to the most specific.
+fn config_dirs(env: &HashMap<String, String>) -> Vec<PathBuf> {
+    let non_empty = |name: &str| env.get(name).filter(|value| !value.is_empty());
+    let mut dirs = vec![PathBuf::from(SYSTEM_DIR)];
+    let xdg_dirs = non_empty("XDG_CONFIG_DIRS").map_or(DEFAULT_XDG_CONFIG_DIRS, String::as_str);
+    // the first of `$XDG_CONFIG_DIRS` is the most important, so it is loaded last
+    let mut xdg_dirs: Vec<_> = std::env::split_paths(xdg_dirs).collect();
+    xdg_dirs.reverse();
+    dirs.extend(xdg_dirs.into_iter().map(|dir| dir.join("oxipy")));
+    let config_home = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| Path::new(home).join(".config")));
+    if let Some(config_home) = config_home {
+        dirs.push(config_home.join("oxipy"));
+    }
+    dirs
+}
+
+/// The RC files that exist when `--rc` isn't given, in the order they run.
+pub(crate) fn default_files(env: &HashMap<String, String>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in config_dirs(env) {
+        let rc = dir.join("rc.oxy");
+        if rc.is_file() {
+            files.push(rc);
+        }
+        files.extend(sources_in(&dir.join("rc.d")));
+    }
+    files
+}
+
+/// The oxipy sources directly in `dir`, in name order. A missing directory has none.
+fn sources_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// The files to run for the paths given with `--rc`. Directories stand for the sources in them;
+/// paths that don't exist are reported on `stderr`.
+fn given_files(paths: &[PathBuf], stderr: &mut impl Write) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(sources_in(path));
+        } else if path.exists() {
+            files.push(path.clone());
+        } else {
+            writeln!(stderr, "oxipy: RC file not found: {}", path.display())?;
+        }
+    }
+    Ok(files)
+}
+
+/// Runs the RC files given with `--rc`, or else the default ones, in `session`.
+///
+/// The interpreter reports the errors of a file with their file and line. A failing file, or
+/// one that calls `exit`, doesn't stop the ones after it nor the session.
+pub(crate) fn load(
+    session: &mut Session,
+    paths: Option<&[PathBuf]>,
+    stderr: &mut impl Write,
+) -> Result<()> {
+    let files = match paths {
+        Some(paths) => given_files(paths, stderr)?,
+        None => default_files(session.state().env_vars()),
+    };
+    for file in files {
+        log::info!("Loading RC file: {}", file.display());
+        if let Outcome::Exit(code) = session.source(&file) {
+            writeln!(
+                stderr,
+                "oxipy: {} tried to exit with status {code}, ignoring",
+                file.display()
+            )?;
+        }
+    }
+    Ok(())
+}
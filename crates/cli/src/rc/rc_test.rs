@@ -0,0 +1,136 @@
+use super::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cmdgroup::ShellState;
+
+use crate::session::Interpreter;
+
+fn touch(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, "").unwrap();
+}
+
+#[test]
+fn test_config_dirs() {
+    let env = HashMap::from([
+        ("HOME".to_string(), "/home/me".to_string()),
+        ("XDG_CONFIG_DIRS".to_string(), "/a:/b".to_string()),
+    ]);
+    assert_eq!(
+        config_dirs(&env),
+        [
+            "/etc/oxipy",
+            "/b/oxipy",
+            "/a/oxipy",
+            "/home/me/.config/oxipy"
+        ]
+        .map(PathBuf::from)
+    );
+    let env = HashMap::from([
+        ("HOME".to_string(), "/home/me".to_string()),
+        ("XDG_CONFIG_HOME".to_string(), "/config".to_string()),
+        ("XDG_CONFIG_DIRS".to_string(), String::new()),
+    ]);
+    assert_eq!(
+        config_dirs(&env),
+        ["/etc/oxipy", "/etc/xdg/oxipy", "/config/oxipy"].map(PathBuf::from)
+    );
+}
+
+#[test]
+fn test_default_files() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    let home = dir.join("home");
+    let config = home.join(".config/oxipy");
+    touch(&config.join("rc.oxy"));
+    touch(&config.join("rc.d/20-b.xsh"));
+    touch(&config.join("rc.d/10-a.oxy"));
+    touch(&config.join("rc.d/notes.txt"));
+    let env = HashMap::from([
+        ("HOME".to_string(), home.display().to_string()),
+        (
+            "XDG_CONFIG_DIRS".to_string(),
+            dir.join("xdg").display().to_string(),
+        ),
+    ]);
+    let files = default_files(&env);
+
+    let ours: Vec<_> = files.iter().filter(|file| file.starts_with(dir)).collect();
+    assert_eq!(
+        ours,
+        [
+            config.join("rc.oxy"),
+            config.join("rc.d/10-a.oxy"),
+            config.join("rc.d/20-b.xsh"),
+        ]
+        .iter()
+        .collect::<Vec<_>>()
+    );
+}
+
+/// Sources files by recording them, failing the ones named `exit.oxy`.
+struct Sourcer(Rc<RefCell<Vec<PathBuf>>>);
+
+impl Interpreter for Sourcer {
+    fn run(&mut self, _source: &str, _filename: &str, _state: &mut ShellState) -> Outcome {
+        Outcome::Status(0)
+    }
+
+    fn run_script(&mut self, _path: &Path, _args: &[String], _state: &mut ShellState) -> Outcome {
+        Outcome::Status(0)
+    }
+
+    fn source(&mut self, path: &Path, _state: &mut ShellState) -> Outcome {
+        self.0.borrow_mut().push(path.to_path_buf());
+        if path.ends_with("exit.oxy") {
+            Outcome::Exit(4)
+        } else {
+            Outcome::Status(0)
+        }
+    }
+}
+
+#[test]
+fn test_load_given_paths() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    touch(&dir.join("exit.oxy"));
+    touch(&dir.join("rc.d/a.oxy"));
+    touch(&dir.join("plain"));
+    let sourced = Rc::new(RefCell::new(Vec::new()));
+    let mut session = Session::new(
+        HashMap::new(),
+        &std::env::current_dir().unwrap(),
+        Box::new(Sourcer(sourced.clone())),
+    )
+    .unwrap();
+    let paths = [
+        dir.join("exit.oxy"),
+        dir.join("missing.oxy"),
+        dir.join("rc.d"),
+        dir.join("plain"),
+    ];
+    let mut stderr = Vec::new();
+    load(&mut session, Some(&paths), &mut stderr).unwrap();
+
+    assert_eq!(
+        *sourced.borrow(),
+        [
+            dir.join("exit.oxy"),
+            dir.join("rc.d/a.oxy"),
+            dir.join("plain")
+        ]
+    );
+    assert_eq!(
+        String::from_utf8(stderr).unwrap(),
+        format!(
+            "oxipy: RC file not found: {}\n\
+             oxipy: {} tried to exit with status 4, ignoring\n",
+            dir.join("missing.oxy").display(),
+            dir.join("exit.oxy").display()
+        )
+    );
+}
@@ -0,0 +1,350 @@
+//! The state an interactive session keeps between entries.
+//!
+//! Each entry is parsed with `ruff_python_parser`. An entry made only of plain commands, like
+//! `cd src && ls -la`, runs directly through `cmdgroup`; anything else is Python and is handed
+//! to the [`Interpreter`]. Both share one [`ShellState`], so `cd`, `export` and variables carry
+//! over from one entry to the next whichever ran it.
+
+#[cfg(test)]
+mod session_test;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use cmdgroup::parser::{RedirectOp, RedirectOpInput, RedirectOpOutput, SequentialList};
+use cmdgroup::{
+    ExecuteResult, KillSignal, ShellCommand, ShellPipeReader, ShellPipeWriter, ShellState,
+};
+use ruff_python_ast::helpers::is_compound_statement;
+use ruff_python_ast::subproc::{CaptureKind, Pipeline, RedirectKind, list_operator};
+use ruff_python_ast::{Expr, Stmt};
+use ruff_python_parser::{LexicalErrorType, ParseErrorType};
+use ruff_text_size::Ranged;
+
+use crate::command_list::{self, ListBuilder, Operator};
+
+/// Runs the Python code of a session.
+///
+/// The CLI doesn't link Python itself: the extension module implements this and passes it to
+/// [`Cli::run`](crate::Cli::run).
+pub trait Interpreter {
+    /// Runs oxipy `source` in the session's namespace and reports its errors.
+    ///
+    /// `state` holds the variables and directory of the shell when the code starts, and must
+    /// hold the ones the code left when it returns.
+    fn run(&mut self, source: &str, filename: &str, state: &mut ShellState) -> Outcome;
+
+    /// Runs the script at `path`, or the program on stdin when `path` is `-`, with `args` as
+    /// its arguments, and reports its errors.
+    fn run_script(&mut self, path: &Path, args: &[String], state: &mut ShellState) -> Outcome;
+
+    /// Runs the file at `path` in the session's namespace, like `source` in a shell, and
+    /// reports its errors with the file and line they come from.
+    fn source(&mut self, path: &Path, state: &mut ShellState) -> Outcome;
+
+    /// Called after the session ran an entry of plain commands itself, so the interpreter can
+    /// expose how it went, e.g. as `$?`.
+    fn record(&mut self, run: &CommandRun) {
+        let _ = run;
+    }
+
+    /// The names and attributes of the session's namespace completing `prefix`, like `os.pa`
+    /// or `pri`, each as it would replace `prefix`.
+    fn complete(&mut self, prefix: &str) -> Vec<String> {
+        let _ = prefix;
+        Vec::new()
+    }
+
+    /// The values of the prompt fields among `names` that the session's code set, e.g. with
+    /// callables from an RC file. Fields it doesn't know are left out.
+    fn prompt_fields(
+        &mut self,
+        names: &[String],
+        state: &mut ShellState,
+    ) -> HashMap<String, String> {
+        let _ = (names, state);
+        HashMap::new()
+    }
+}
+
+/// How an entry ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The entry finished with this exit status.
+    Status(i32),
+    /// The entry asked to end the session with this exit status, e.g. with `sys.exit()`.
+    Exit(i32),
+}
+
+impl Outcome {
+    pub fn code(self) -> i32 {
+        match self {
+            Outcome::Status(code) | Outcome::Exit(code) => code,
+        }
+    }
+}
+
+/// A pipeline of plain commands run by the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandRun {
+    /// The words of each stage, including the program name.
+    pub args: Vec<Vec<String>>,
+    pub returncode: i32,
+    /// The exit code of each command in the last pipeline, like `PIPESTATUS`.
+    pub pipestatus: Vec<i32>,
+    /// Seconds since the epoch when the commands started.
+    pub started: f64,
+    /// Seconds the commands ran for.
+    pub duration: f64,
+}
+
+/// A statement that `cmdgroup` can run without Python.
+#[derive(Debug)]
+struct PlainCommand {
+    list: SequentialList,
+    args: Vec<Vec<String>>,
+}
+
+impl PlainCommand {
+    /// Returns the command when `stmt` is a bare command, `$[...]` or `![...]` in the
+    /// foreground whose words and redirects are all written literally.
+    ///
+    /// The list is built from the lowered words like `ox.cmd(...)` builds it, so they are not
+    /// expanded again and only the operators the parser marked separate commands.
+    fn from_stmt(stmt: &Stmt, source: &str) -> Option<Self> {
+        let Stmt::Expr(stmt) = stmt else {
+            return None;
+        };
+        let pipeline = Pipeline::from_expr(&stmt.value)?;
+        if !matches!(pipeline.capture, CaptureKind::Hide | CaptureKind::Run) {
+            return None;
+        }
+        let mut list = ListBuilder::default();
+        let mut args = Vec::with_capacity(pipeline.stages.len());
+        for (index, stage) in pipeline.stages.iter().enumerate() {
+            let only_redirects = stage
+                .arguments
+                .keywords
+                .iter()
+                .all(|keyword| matches!(keyword.value, Expr::Dict(_)));
+            if stage.is_background() || !only_redirects {
+                return None;
+            }
+            let redirects = stage
+                .redirects()
+                .map(|redirect| {
+                    let op = match redirect.kind {
+                        RedirectKind::Write => RedirectOp::Output(RedirectOpOutput::Overwrite),
+                        RedirectKind::Append => RedirectOp::Output(RedirectOpOutput::Append),
+                        RedirectKind::Read => RedirectOp::Input(RedirectOpInput::Redirect),
+                    };
+                    let from = match redirect.source {
+                        Some(from) => literal_text(from, source)?,
+                        None => "",
+                    };
+                    command_list::redirect(from, literal_text(redirect.target, source)?, op).ok()
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            let mut stage_args = Vec::new();
+            let mut words = Vec::new();
+            for word in stage.words() {
+                if let Some(text) = list_operator(word) {
+                    let op = match text {
+                        "&&" => Operator::And,
+                        "||" => Operator::Or,
+                        _ => Operator::Then,
+                    };
+                    list.push(&std::mem::take(&mut words), &[], Some(op)).ok()?;
+                    stage_args.push(text.to_string());
+                } else {
+                    let text = literal_text(word, source)?.to_string();
+                    words.push(text.clone());
+                    stage_args.push(text);
+                }
+            }
+            let op = (index + 1 < pipeline.stages.len()).then_some(Operator::Pipe);
+            list.push(&words, &redirects, op).ok()?;
+            args.push(stage_args);
+        }
+        Some(Self {
+            list: list.finish().ok()?,
+            args,
+        })
+    }
+}
+
+/// The text of a word or redirect written literally, like `ls` or the `2` of `2> err.log`.
+fn literal_text<'a>(expr: &'a Expr, source: &'a str) -> Option<&'a str> {
+    match expr {
+        Expr::StringLiteral(literal) => Some(literal.value.to_str()),
+        Expr::NumberLiteral(_) => Some(&source[expr.range()]),
+        _ => None,
+    }
+}
+
+/// Whether `source` is the start of an entry that goes on over the next line: after an open
+/// bracket, string or `\`, or in a block like `if x:` until an empty line ends it, as in the
+/// Python REPL.
+pub(crate) fn needs_more(source: &str) -> bool {
+    match ruff_python_parser::parse_module(source) {
+        Err(err) => {
+            let open = matches!(
+                err.error,
+                ParseErrorType::Lexical(
+                    LexicalErrorType::Eof | LexicalErrorType::UnclosedStringError
+                )
+            );
+            // other errors at the end go on until an empty line, and are reported then
+            open || (!source.ends_with('\n')
+                && err.location.start().to_usize() >= source.trim_end().len())
+        }
+        Ok(parsed) => {
+            !source.ends_with('\n')
+                && parsed
+                    .syntax()
+                    .body
+                    .last()
+                    .is_some_and(is_compound_statement)
+        }
+    }
+}
+
+/// The statements of `source` when all of them are plain commands.
+fn plain_commands(source: &str) -> Option<Vec<PlainCommand>> {
+    let parsed = ruff_python_parser::parse_module(source).ok()?;
+    let body = &parsed.syntax().body;
+    if body.is_empty() {
+        return None;
+    }
+    body.iter()
+        .map(|stmt| PlainCommand::from_stmt(stmt, source))
+        .collect()
+}
+
+pub(crate) struct Session {
+    state: ShellState,
+    /// Shared with the [`PythonNames`] of the line editor, which only uses it between entries.
+    interpreter: Rc<RefCell<Box<dyn Interpreter>>>,
+    runtime: tokio::runtime::Runtime,
+}
+
+/// Completes Python names from the namespace of a session, see [`Interpreter::complete`].
+#[derive(Clone)]
+pub(crate) struct PythonNames(Rc<RefCell<Box<dyn Interpreter>>>);
+
+impl PythonNames {
+    pub(crate) fn complete(&self, prefix: &str) -> Vec<String> {
+        // there is nothing to offer while an entry runs
+        self.0
+            .try_borrow_mut()
+            .map(|mut interpreter| interpreter.complete(prefix))
+            .unwrap_or_default()
+    }
+}
+
+impl Session {
+    pub(crate) fn new(
+        env_vars: HashMap<String, String>,
+        cwd: &Path,
+        interpreter: Box<dyn Interpreter>,
+    ) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            state: ShellState::new(env_vars, cwd, HashMap::new(), KillSignal::default()),
+            interpreter: Rc::new(RefCell::new(interpreter)),
+            runtime,
+        })
+    }
+
+    pub(crate) fn state(&self) -> &ShellState {
+        &self.state
+    }
+
+    /// Adds a command that entries of plain commands run like the builtins of `cmdgroup`, in
+    /// pipes and lists.
+    pub(crate) fn add_builtin(&mut self, name: &str, command: Rc<dyn ShellCommand>) {
+        self.state.add_command(name.to_string(), command);
+    }
+
+    pub(crate) fn python_names(&self) -> PythonNames {
+        PythonNames(self.interpreter.clone())
+    }
+
+    /// Runs one entry, like a line typed at the prompt or the program of `-c`, which stops at
+    /// `exit`. Among Python, the command raises `SystemExit` for the interpreter to report.
+    pub(crate) fn run(&mut self, source: &str, filename: &str) -> Outcome {
+        match plain_commands(source) {
+            Some(commands) => {
+                let mut outcome = Outcome::Status(0);
+                for command in commands {
+                    outcome = self.run_command(command);
+                    if let Outcome::Exit(_) = outcome {
+                        break;
+                    }
+                }
+                outcome
+            }
+            None => self
+                .interpreter
+                .borrow_mut()
+                .run(source, filename, &mut self.state),
+        }
+    }
+
+    /// Runs a script file, see [`Interpreter::run_script`].
+    pub(crate) fn run_script(&mut self, path: &Path, args: &[String]) -> Outcome {
+        self.interpreter
+            .borrow_mut()
+            .run_script(path, args, &mut self.state)
+    }
+
+    /// The prompt fields among `names` set by the session's code, see
+    /// [`Interpreter::prompt_fields`].
+    pub(crate) fn prompt_fields(&mut self, names: &[String]) -> HashMap<String, String> {
+        self.interpreter
+            .borrow_mut()
+            .prompt_fields(names, &mut self.state)
+    }
+
+    /// Runs a file in the session, see [`Interpreter::source`].
+    pub(crate) fn source(&mut self, path: &Path) -> Outcome {
+        self.interpreter.borrow_mut().source(path, &mut self.state)
+    }
+
+    fn run_command(&mut self, command: PlainCommand) -> Outcome {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_secs_f64());
+        let timer = Instant::now();
+        let local = tokio::task::LocalSet::new();
+        let result = local.block_on(
+            &self.runtime,
+            cmdgroup::execute_with_state(
+                command.list,
+                &mut self.state,
+                ShellPipeReader::stdin(),
+                ShellPipeWriter::stdout(),
+                ShellPipeWriter::stderr(),
+            ),
+        );
+        let returncode = result.exit_code();
+        self.interpreter.borrow_mut().record(&CommandRun {
+            args: command.args,
+            returncode,
+            pipestatus: self.state.pipe_status(),
+            started,
+            duration: timer.elapsed().as_secs_f64(),
+        });
+        match result {
+            ExecuteResult::Exit(..) => Outcome::Exit(returncode),
+            ExecuteResult::Continue(..) => Outcome::Status(returncode),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::*;
+
+fn plain_args(source: &str) -> Option<Vec<Vec<Vec<String>>>> {
+    plain_commands(source)
+        .map(|commands| commands.into_iter().map(|command| command.args).collect())
+}
+
+#[test]
+fn test_plain_commands() {
+    assert_eq!(
+        plain_args("ls -la | grep x"),
+        Some(vec![vec![
+            vec!["ls".to_string(), "-la".to_string()],
+            vec!["grep".to_string(), "x".to_string()],
+        ]])
+    );
+    assert_eq!(
+        plain_args("$[echo 'a b']\n![cd /tmp]"),
+        Some(vec![
+            vec![vec!["echo".to_string(), "a b".to_string()]],
+            vec![vec!["cd".to_string(), "/tmp".to_string()]],
+        ])
+    );
+    assert!(plain_commands("echo hi > out.txt").is_some());
+}
+
+#[test]
+fn test_plain_command_lists() {
+    let commands = plain_commands("echo a && echo b; echo c").unwrap();
+    assert_eq!(commands[0].list.items.len(), 2);
+    // quoted operators are arguments, like in `ox.cmd(...)`
+    let commands = plain_commands("echo ';' '&&' x").unwrap();
+    assert_eq!(commands[0].list.items.len(), 1);
+    assert_eq!(commands[0].args, [["echo", ";", "&&", "x"]]);
+}
+
+#[test]
+fn test_python_entries() {
+    assert!(plain_commands("x = 1").is_none());
+    assert!(plain_commands("print('hi')").is_none());
+    assert!(plain_commands("").is_none());
+    // captured output is a Python value
+    assert!(plain_commands("$(ls)").is_none());
+    // interpolated words need the interpreter
+    assert!(plain_commands("echo @(x)").is_none());
+    assert!(plain_commands("echo $HOME").is_none());
+    // a single Python statement makes the whole entry Python
+    assert!(plain_commands("ls\nx = 1").is_none());
+}
+
+#[test]
+fn test_needs_more() {
+    for complete in [
+        "ls -la",
+        "x = 1",
+        "print('a')",
+        "if x:\n    pass\n",
+        "x = (\n1)",
+        "",
+    ] {
+        assert!(!needs_more(complete), "{complete:?}");
+    }
+    for incomplete in [
+        "print(",
+        "ls \\",
+        "x = '''doc",
+        "if x:",
+        "for i in range(3):\n    print(i)",
+        "def f(\n",
+    ] {
+        assert!(needs_more(incomplete), "{incomplete:?}");
+    }
+    // an empty line ends a broken entry, to report it
+    assert!(needs_more("x = 1 +"));
+    assert!(!needs_more("x = 1 +\n"));
+}
+
+/// Runs entries as if every one of them was Python.
+#[derive(Default)]
+struct Recorder {
+    sources: Vec<String>,
+    runs: Vec<i32>,
+}
+
+struct SharedRecorder(std::rc::Rc<std::cell::RefCell<Recorder>>);
+
+impl Interpreter for SharedRecorder {
+    fn run(&mut self, source: &str, _filename: &str, state: &mut ShellState) -> Outcome {
+        self.0.borrow_mut().sources.push(source.to_string());
+        state.apply_env_var("FROM_PYTHON", "1");
+        Outcome::Status(0)
+    }
+
+    fn run_script(&mut self, path: &Path, _args: &[String], _state: &mut ShellState) -> Outcome {
+        self.0.borrow_mut().sources.push(path.display().to_string());
+        Outcome::Status(0)
+    }
+
+    fn source(&mut self, path: &Path, _state: &mut ShellState) -> Outcome {
+        self.0.borrow_mut().sources.push(path.display().to_string());
+        Outcome::Status(0)
+    }
+
+    fn record(&mut self, run: &CommandRun) {
+        self.0.borrow_mut().runs.push(run.returncode);
+    }
+}
+
+#[test]
+fn test_state_is_shared() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let recorder = std::rc::Rc::new(std::cell::RefCell::new(Recorder::default()));
+    let mut session = Session::new(
+        HashMap::new(),
+        &std::env::current_dir().unwrap(),
+        Box::new(SharedRecorder(recorder.clone())),
+    )
+    .unwrap();
+    let cd = format!("cd {}", dir.display());
+    assert_eq!(session.run(&cd, "<test>"), Outcome::Status(0));
+    assert_eq!(session.state().cwd(), &dir);
+    assert_eq!(
+        session.run("export GREETING=hi", "<test>"),
+        Outcome::Status(0)
+    );
+    assert_eq!(session.run("x = 1", "<test>"), Outcome::Status(0));
+    assert_eq!(
+        session.run("exit 3\nexport SKIPPED=1", "<test>"),
+        Outcome::Exit(3)
+    );
+    let state = session.state();
+    assert_eq!(state.get_var("GREETING").map(String::as_str), Some("hi"));
+    assert_eq!(state.get_var("FROM_PYTHON").map(String::as_str), Some("1"));
+    assert_eq!(state.get_var("SKIPPED"), None);
+    assert_eq!(recorder.borrow().sources, ["x = 1"]);
+    assert_eq!(recorder.borrow().runs, [0, 0, 3]);
+}
@@ -1,25 +1,106 @@
 use std::borrow::Cow::{self, Borrowed, Owned};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use rustyline::completion::FilenameCompleter;
+use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
-use rustyline::highlight::{CmdKind, Highlighter, MatchingBracketHighlighter};
+use rustyline::highlight::{CmdKind, Highlighter};
 use rustyline::hint::HistoryHinter;
 use rustyline::history::DefaultHistory;
-use rustyline::validate::MatchingBracketValidator;
-use rustyline::{CompletionType, Config, EditMode, Editor};
-use rustyline::{Completer, Helper, Hinter, Validator};
+use rustyline::{
+    Cmd, CompletionType, Config, ConditionalEventHandler, Context, EditMode, Editor, Event,
+    EventContext, EventHandler, KeyEvent, Movement, RepeatCount,
+};
+use rustyline::{Helper, Hinter, Validator};
 use anyhow::Result;
+use cmdgroup::ShellState;
 
-#[derive(Helper, Completer, Hinter, Validator)]
+use crate::bash_completion::BashCompleter;
+use crate::completion::{CompletionContext, command_words};
+use crate::history::{HistoryCommand, HistoryStore};
+use crate::prompt::{Context as PromptContext, Prompts, Rendered, Templates};
+use crate::session::{self, Outcome, PythonNames, Session};
+use crate::theme::Theme;
+use crate::which::CommandKind;
+
+#[derive(Helper, Hinter, Validator)]
 struct ShellHelper {
-    #[rustyline(Completer)]
-    completer: FilenameCompleter,
-    highlighter: MatchingBracketHighlighter,
-    #[rustyline(Validator)]
-    validator: MatchingBracketValidator,
+    names: PythonNames,
+    bash: BashCompleter,
+    theme: Theme,
+    /// The directory and variables of the session when the prompt was shown, to resolve
+    /// command names and paths with.
+    cwd: PathBuf,
+    env_vars: HashMap<String, String>,
+    /// Command names resolved since the prompt was shown, as highlighting runs on every key.
+    commands: RefCell<HashMap<String, CommandKind>>,
     #[rustyline(Hinter)]
     hinter: HistoryHinter,
-    colored_prompt: String,
+    /// The prompt being read with, and the right prompt when it is the first line of an entry.
+    prompt: Rendered,
+    right_prompt: Rendered,
+    /// The width of the terminal when the prompt was shown.
+    columns: usize,
+}
+
+impl ShellHelper {
+    /// Catches up with the session before a new prompt.
+    fn update(&mut self, state: &ShellState) {
+        self.theme = Theme::from_env(state.env_vars());
+        self.cwd.clone_from(state.cwd());
+        self.env_vars.clone_from(state.env_vars());
+        self.commands.get_mut().clear();
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let context = CompletionContext::from_source(line);
+        // the completions of bash come first, then paths
+        if let CompletionContext::Argument(_) = context {
+            let words = command_words(line);
+            let found = self.bash.complete(&words, &self.cwd, &self.env_vars);
+            if let Some(texts) = found.filter(|texts| !texts.is_empty()) {
+                let word = words.last().map_or(0, String::len);
+                return Ok((pos - word, pairs(texts)));
+            }
+        }
+        let mut texts: Vec<String> = context
+            .candidates(&self.cwd, &self.env_vars)
+            .into_iter()
+            .map(|candidate| candidate.text)
+            .collect();
+        if let CompletionContext::Name(prefix) | CompletionContext::Python(prefix) = context {
+            texts.extend(self.names.complete(prefix));
+        }
+        // a command may also be a Python name
+        let mut seen = HashSet::new();
+        texts.retain(|text| seen.insert(text.clone()));
+        Ok((pos - context.word().len(), pairs(texts)))
+    }
+}
+
+fn pairs(texts: Vec<String>) -> Vec<Pair> {
+    texts
+        .into_iter()
+        .map(|text| Pair {
+            display: text.clone(),
+            replacement: text,
+        })
+        .collect()
 }
 
 impl Highlighter for ShellHelper {
@@ -29,7 +110,7 @@ impl Highlighter for ShellHelper {
         default: bool,
     ) -> Cow<'b, str> {
         if default {
-            Borrowed(&self.colored_prompt)
+            Borrowed(&self.prompt.colored)
         } else {
             Borrowed(prompt)
         }
@@ -39,75 +120,167 @@ impl Highlighter for ShellHelper {
         Owned("\x1b[1m".to_owned() + hint + "\x1b[m")
     }
 
-    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
-        self.highlighter.highlight(line, pos)
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let mut commands = self.commands.borrow_mut();
+        let mut highlighted = self.theme.highlight(line, |name| {
+            commands
+                .entry(name.to_string())
+                .or_insert_with(|| CommandKind::resolve(name, &self.cwd, &self.env_vars))
+                .clone()
+        });
+        // drawn at the right edge with the cursor put back, so the editor's layout holds; it
+        // goes away when the line gets close to it
+        let width = self.right_prompt.plain.chars().count();
+        let used = self.prompt.plain.chars().count() + line.chars().count();
+        if width > 0 && !line.contains('\n') && used + width + 1 < self.columns {
+            let column = self.columns - width;
+            let _ = write!(
+                highlighted,
+                "\x1b7\x1b[{column}G{}\x1b8",
+                self.right_prompt.colored
+            );
+        }
+        Owned(highlighted)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        // the colors only depend on the text, not on where the cursor is
+        !matches!(kind, CmdKind::MoveCursor)
     }
+}
+
+/// Ctrl-R: replaces the line with the best match in the history for the text typed so far,
+/// and with the next one on each press. Nothing found leaves the usual reverse search.
+struct FuzzySearch {
+    history: Arc<Mutex<HistoryStore>>,
+    /// The working directory of the session, whose entries come first.
+    cwd: Arc<Mutex<PathBuf>>,
+    /// The query, the place of the match shown for it and the match.
+    shown: Mutex<Option<(String, usize, String)>>,
+}
 
-    fn highlight_char(&self, line: &str, pos: usize, kind: CmdKind) -> bool {
-        self.highlighter.highlight_char(line, pos, kind)
+impl ConditionalEventHandler for FuzzySearch {
+    fn handle(
+        &self,
+        _evt: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        ctx: &EventContext<'_>,
+    ) -> Option<Cmd> {
+        let mut shown = self.shown.lock().ok()?;
+        let (query, index) = match shown.take() {
+            Some((query, index, line)) if line == ctx.line() => (query, index + 1),
+            _ => (ctx.line().to_string(), 0),
+        };
+        let history = self.history.lock().ok()?;
+        let matches = history.search(&query, &self.cwd.lock().ok()?);
+        if matches.is_empty() {
+            return None;
+        }
+        let index = index % matches.len();
+        let line = matches[index].to_string();
+        *shown = Some((query, index, line.clone()));
+        Some(Cmd::Replace(Movement::WholeLine, Some(line)))
     }
 }
 
 pub(crate) struct Shell {
     editor: Editor<ShellHelper, DefaultHistory>,
-    prompt: String,
-    history_file: Option<String>,
+    prompts: Prompts,
+    history: Arc<Mutex<HistoryStore>>,
+    /// Shared with [`FuzzySearch`].
+    cwd: Arc<Mutex<PathBuf>>,
+    session: Session,
 }
 
 impl Shell {
-    pub(crate) fn new() -> Result<Self> {
+    pub(crate) fn new(mut session: Session) -> Result<Self> {
         let config = Config::builder()
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
         .edit_mode(EditMode::Emacs)
         .build();
     let helper = ShellHelper {
-        completer: FilenameCompleter::new(),
-        highlighter: MatchingBracketHighlighter::new(),
+        names: session.python_names(),
+        bash: BashCompleter::new(session.state().cwd(), session.state().env_vars()),
+        // set from the session before each prompt
+        theme: Theme::default(),
+        cwd: PathBuf::new(),
+        env_vars: HashMap::new(),
+        commands: RefCell::default(),
         hinter: HistoryHinter::new(),
-        colored_prompt: String::new(),
-        validator: MatchingBracketValidator::new(),
+        prompt: Rendered::default(),
+        right_prompt: Rendered::default(),
+        columns: 0,
     };
         let mut editor = Editor::with_config(config)?;
         editor.set_helper(Some(helper));
 
-        // Set default history file in home directory
-        let history_file = dirs::home_dir()
-            .map(|mut path| {
-                path.push(".oxsh_history");
-                path.to_string_lossy().into_owned()
-            });
-
-        if let Some(ref history) = history_file {
-            if editor.load_history(history).is_err() {
-                log::warn!("No previous history.");
+        let mut history = HistoryStore::open(HistoryStore::default_path());
+        if let Some(legacy) = HistoryStore::legacy_path() {
+            if let Err(err) = history.import_legacy(&legacy) {
+                log::warn!("Could not import {}: {err}", legacy.display());
             }
         }
+        for entry in history.entries() {
+            editor.add_history_entry(entry.command.as_str())?;
+        }
+        let history = Arc::new(Mutex::new(history));
+        session.add_builtin("history", Rc::new(HistoryCommand(history.clone())));
+        let cwd = Arc::new(Mutex::new(session.state().cwd().clone()));
+        editor.bind_sequence(
+            KeyEvent::ctrl('R'),
+            EventHandler::Conditional(Box::new(FuzzySearch {
+                history: history.clone(),
+                cwd: cwd.clone(),
+                shown: Mutex::new(None),
+            })),
+        );
 
         Ok(Shell {
             editor,
-            prompt: "oxsh> ".to_string(),
-            history_file,
+            prompts: Prompts::default(),
+            history,
+            cwd,
+            session,
         })
     }
 
-    pub(crate) fn run(&mut self) -> Result<()> {
+    /// Reads and runs entries until `exit`, end of input or `sys.exit()`, and returns the exit
+    /// status of the shell.
+    pub(crate) fn run(&mut self) -> Result<i32> {
+        let mut status = 0;
+        let mut duration = Duration::ZERO;
         loop {
-            match self.editor.readline(&self.prompt) {
-                Ok(line) => {
-                    let line = line.trim();
+            self.update_prompts(status, duration);
+            if let Some(helper) = self.editor.helper_mut() {
+                helper.update(self.session.state());
+            }
+            self.cwd
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone_from(self.session.state().cwd());
+            match self.read_entry() {
+                Ok(entry) => {
+                    let line = entry.trim();
                     if line.is_empty() {
                         continue;
                     }
 
                     // Add to history
                     self.editor.add_history_entry(line)?;
+                    if matches!(line, "exit" | "quit") {
+                        break;
+                    }
 
-                    // Handle built-in commands
-                    match line {
-                        "exit" | "quit" => break,
-                        "history" => self.show_history(),
-                        _ => self.execute_command(line),
+                    let cwd = self.session.state().cwd().clone();
+                    let started = SystemTime::now();
+                    let outcome = self.session.run(line, "<stdin>");
+                    status = outcome.code();
+                    duration = started.elapsed().unwrap_or_default();
+                    self.record(line, &cwd, started, status, duration);
+                    if let Outcome::Exit(_) = outcome {
+                        break;
                     }
                 }
                 Err(ReadlineError::Interrupted) => {
@@ -123,46 +296,57 @@ impl Shell {
             }
         }
 
-        // Save history on exit
-        if let Some(ref history) = self.history_file {
-            let _ = self.editor.save_history(history);
+        Ok(status)
+    }
+
+    /// Renders the prompts for the next entry, after one that ended with `last_exit` and ran
+    /// for `duration`.
+    fn update_prompts(&mut self, last_exit: i32, duration: Duration) {
+        let templates = Templates::from_env(self.session.state().env_vars());
+        let custom = self.session.prompt_fields(&templates.field_names());
+        let state = self.session.state();
+        let context = PromptContext {
+            cwd: state.cwd(),
+            env: state.env_vars(),
+            last_exit,
+            duration,
+        };
+        self.prompts = templates.render(&context, &custom);
+        let columns = self.editor.dimensions().map_or(0, |(columns, _)| usize::from(columns));
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.columns = columns;
         }
-        Ok(())
     }
 
-    fn show_history(&self) {
-        let history = self.editor.history();
-        for (i, entry) in history.iter().enumerate() {
-            log::warn!("{:5} {}", i + 1, entry);
+    /// Reads an entry, with more lines after the continuation prompt while it is incomplete,
+    /// like after `if x:`.
+    fn read_entry(&mut self) -> rustyline::Result<String> {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.prompt = self.prompts.prompt.clone();
+            helper.right_prompt = self.prompts.right.clone();
         }
+        let mut entry = self.editor.readline(&self.prompts.prompt.plain)?;
+        while session::needs_more(&entry) {
+            if let Some(helper) = self.editor.helper_mut() {
+                helper.prompt = self.prompts.continuation.clone();
+                helper.right_prompt = Rendered::default();
+            }
+            let line = self.editor.readline(&self.prompts.continuation.plain)?;
+            entry.push('\n');
+            entry.push_str(&line);
+        }
+        Ok(entry)
     }
 
-    fn execute_command(&self, command: &str) {
-        let parts = command.split_whitespace();
-        log::info!("Executing command: {:?} {}", parts.collect::<Vec<&str>>(), self.prompt);
-        // if let Some(program) = parts.next() {
-        //     let args: Vec<&str> = parts.collect();
-        //     log::info!("Executing command: {:?}", command);
-        //     // match Command::new(program)
-        //     //     .args(&args)
-        //     //     .stdout(Stdio::inherit())
-        //     //     .stderr(Stdio::inherit())
-        //     //     .spawn()
-        //     // {
-        //     //     Ok(mut child) => {
-        //     //         match child.wait() {
-        //     //             Ok(status) => {
-        //     //                 if !status.success() {
-        //     //                     if let Some(code) = status.code() {
-        //     //                         eprintln!("Process exited with code: {}", code);
-        //     //                     }
-        //     //                 }
-        //     //             }
-        //     //             Err(e) => eprintln!("Error waiting for process: {}", e),
-        //     //         }
-        //     //     }
-        //     //     Err(e) => eprintln!("Failed to execute command: {}", e),
-        //     // }
-        // }
+    /// Adds an entry to the history, with where it ran and how it went.
+    fn record(&self, line: &str, cwd: &Path, started: SystemTime, status: i32, duration: Duration) {
+        let timestamp = started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_secs_f64());
+        let duration = duration.as_secs_f64();
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(err) = history.append(line, cwd, timestamp, status, duration) {
+            log::warn!("Could not save history: {err}");
+        }
     }
 }
@@ -0,0 +1,135 @@
+//! Colors of the REPL's syntax highlighting.
+//!
+//! The ranges come from [`semantic_tokens`], so the REPL highlights like the language server
+//! does. Command names are colored by what they resolve to, and `$OXIPY_COLORS` overrides the
+//! default styles with `name=SGR` entries separated by `:`, like `$LS_COLORS`:
+//!
+//! ```text
+//! OXIPY_COLORS='command=1;32:command-missing=4;31:comment=90'
+//! ```
+//!
+//! The names are the ones of [`SemanticRole::as_str`], plus `command-builtin` and
+//! `command-missing`; `command` is a command found on `PATH`. An empty style leaves the ranges
+//! with that name plain.
+
+#[cfg(test)]
+mod theme_test;
+
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+
+use crate::highlight::{SemanticRole, semantic_tokens};
+use crate::which::CommandKind;
+
+/// The variable read for overrides of the default theme.
+pub(crate) const COLORS_VAR: &str = "OXIPY_COLORS";
+
+const COMMAND_BUILTIN: &str = "command-builtin";
+const COMMAND_MISSING: &str = "command-missing";
+
+/// The styles of the default theme, as SGR parameters.
+const DEFAULT_STYLES: &[(&str, &str)] = &[
+    ("command", "1;32"),
+    (COMMAND_BUILTIN, "1;36"),
+    (COMMAND_MISSING, "1;31"),
+    ("argument", ""),
+    ("flag", "36"),
+    ("glob", "35"),
+    ("redirect", "1;33"),
+    ("env-var", "33"),
+    ("path", "34"),
+    ("regex", "35"),
+    ("subproc", "1;35"),
+    ("operator", ""),
+    ("python-keyword", "1;34"),
+    ("string", "32"),
+    ("number", "36"),
+    ("comment", "90"),
+];
+
+/// The style of each highlighted name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Theme {
+    styles: HashMap<String, String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            styles: DEFAULT_STYLES
+                .iter()
+                .map(|(name, style)| ((*name).to_string(), (*style).to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Theme {
+    /// The default theme with the overrides of `spec`, see the module docs.
+    pub(crate) fn from_spec(spec: &str) -> Result<Self> {
+        let mut theme = Self::default();
+        for entry in spec.split(':').filter(|entry| !entry.trim().is_empty()) {
+            let Some((name, style)) = entry.split_once('=') else {
+                bail!("expected NAME=STYLE, got `{entry}`");
+            };
+            let (name, style) = (name.trim(), style.trim());
+            let Some(current) = theme.styles.get_mut(name) else {
+                bail!("unknown name `{name}`");
+            };
+            if !style.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                bail!("invalid style `{style}` for `{name}`, expected SGR parameters like `1;32`");
+            }
+            *current = style.to_string();
+        }
+        Ok(theme)
+    }
+
+    /// The theme set in `env`, or the default one when it isn't set or is invalid.
+    pub(crate) fn from_env(env: &HashMap<String, String>) -> Self {
+        let Some(spec) = env.get(COLORS_VAR) else {
+            return Self::default();
+        };
+        Self::from_spec(spec).unwrap_or_else(|err| {
+            log::warn!("Ignoring ${COLORS_VAR}: {err}");
+            Self::default()
+        })
+    }
+
+    fn style(&self, name: &str) -> &str {
+        self.styles.get(name).map_or("", String::as_str)
+    }
+
+    /// `line` with ANSI colors. `resolve` tells what a command name refers to.
+    pub(crate) fn highlight(
+        &self,
+        line: &str,
+        mut resolve: impl FnMut(&str) -> CommandKind,
+    ) -> String {
+        let mut highlighted = String::with_capacity(line.len() * 2);
+        let mut end = 0;
+        for token in semantic_tokens(line) {
+            let text = &line[token.range];
+            let name = match token.role {
+                SemanticRole::Command => {
+                    match resolve(text.trim_matches(|c| c == '\'' || c == '"')) {
+                        CommandKind::Builtin => COMMAND_BUILTIN,
+                        CommandKind::External(_) => SemanticRole::Command.as_str(),
+                        CommandKind::NotFound => COMMAND_MISSING,
+                    }
+                }
+                role => role.as_str(),
+            };
+            highlighted.push_str(&line[end..token.range.start().to_usize()]);
+            let style = self.style(name);
+            if style.is_empty() {
+                highlighted.push_str(text);
+            } else {
+                highlighted.push_str(&format!("\x1b[{style}m{text}\x1b[0m"));
+            }
+            end = token.range.end().to_usize();
+        }
+        highlighted.push_str(&line[end..]);
+        highlighted
+    }
+}
@@ -0,0 +1,65 @@
+use super::*;
+
+use std::path::PathBuf;
+
+fn resolve(name: &str) -> CommandKind {
+    match name {
+        "cd" => CommandKind::Builtin,
+        "ls" => CommandKind::External(PathBuf::from("/bin/ls")),
+        _ => CommandKind::NotFound,
+    }
+}
+
+#[test]
+fn test_highlight_commands() {
+    let theme = Theme::default();
+    assert_eq!(
+        theme.highlight("ls -la", resolve),
+        "\x1b[1;32mls\x1b[0m \x1b[36m-la\x1b[0m"
+    );
+    assert_eq!(
+        theme.highlight("$[cd src]", resolve),
+        "\x1b[1;35m$[\x1b[0m\x1b[1;36mcd\x1b[0m src]"
+    );
+    assert_eq!(
+        theme.highlight("$[nope]", resolve),
+        "\x1b[1;35m$[\x1b[0m\x1b[1;31mnope\x1b[0m]"
+    );
+}
+
+#[test]
+fn test_highlight_python() {
+    let theme = Theme::default();
+    assert_eq!(theme.highlight("x = 1", resolve), "x = \x1b[36m1\x1b[0m");
+    assert_eq!(
+        theme.highlight("if 'a':", resolve),
+        "\x1b[1;34mif\x1b[0m \x1b[32m'a'\x1b[0m:"
+    );
+    // incomplete input keeps its text
+    assert_eq!(theme.highlight("x = (", resolve), "x = (");
+}
+
+#[test]
+fn test_theme_from_spec() {
+    let theme = Theme::from_spec("number=4;33: command-missing=").unwrap();
+    assert_eq!(theme.highlight("x = 1", resolve), "x = \x1b[4;33m1\x1b[0m");
+    assert_eq!(
+        theme.highlight("$[nope]", resolve),
+        "\x1b[1;35m$[\x1b[0mnope]"
+    );
+    assert_eq!(Theme::from_spec("").unwrap(), Theme::default());
+
+    for (spec, message) in [
+        ("number", "expected NAME=STYLE, got `number`"),
+        ("numbers=1", "unknown name `numbers`"),
+        (
+            "number=red",
+            "invalid style `red` for `number`, expected SGR parameters like `1;32`",
+        ),
+    ] {
+        assert_eq!(Theme::from_spec(spec).unwrap_err().to_string(), message);
+    }
+
+    let env = HashMap::from([(COLORS_VAR.to_string(), "bogus".to_string())]);
+    assert_eq!(Theme::from_env(&env), Theme::default());
+}
@@ -0,0 +1,52 @@
+//! Resolution of command names shared by the checker, the language server and the REPL.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Commands the interactive shell adds to those of `cmdgroup`.
+const SHELL_BUILTINS: &[&str] = &["history"];
+
+/// Names of the builtin commands, those implemented by `cmdgroup` and by the shell.
+pub(crate) static BUILTINS: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    cmdgroup::builtin_commands()
+        .into_keys()
+        .chain(SHELL_BUILTINS.iter().map(ToString::to_string))
+        .collect()
+});
+
+/// What a command name refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandKind {
+    Builtin,
+    External(PathBuf),
+    NotFound,
+}
+
+impl CommandKind {
+    /// Resolves `name` like the executor would, using `PATH` from `env`.
+    pub fn resolve(name: &str, cwd: &Path, env: &HashMap<String, String>) -> Self {
+        if BUILTINS.contains(name) {
+            return CommandKind::Builtin;
+        }
+        cmdgroup::which::resolve_command_path(
+            name,
+            cwd,
+            |var| env.get(var).map(|value| Cow::Borrowed(value.as_str())),
+            std::env::current_exe,
+        )
+        .map_or(CommandKind::NotFound, CommandKind::External)
+    }
+
+    /// A one line markdown description, e.g. for hovers.
+    pub fn describe(&self, name: &str) -> String {
+        match self {
+            CommandKind::Builtin => format!("`{name}`: builtin command"),
+            CommandKind::External(path) => {
+                format!("`{name}`: external command at `{}`", path.display())
+            }
+            CommandKind::NotFound => format!("`{name}`: command not found"),
+        }
+    }
+}
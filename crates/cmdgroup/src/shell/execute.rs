@@ -117,6 +117,39 @@ pub async fn execute_with_pipes(
   }
 }
 
+/// Executes a `SequentialList` like `execute_with_pipes`, then applies the
+/// changes it made (`cd`, `export` and shell variables) to `state`.
+///
+/// This lets the next list run where this one left off, like the commands
+/// entered in an interactive shell.
+///
+/// # Returns
+///
+/// The result of the execution, which is `ExecuteResult::Exit` when the list
+/// ran `exit`, so an interactive shell knows to stop.
+pub async fn execute_with_state(
+  list: SequentialList,
+  state: &mut ShellState,
+  stdin: ShellPipeReader,
+  stdout: ShellPipeWriter,
+  stderr: ShellPipeWriter,
+) -> ExecuteResult {
+  let result = execute_sequential_list(
+    list,
+    state.clone(),
+    stdin,
+    stdout,
+    stderr,
+    AsyncCommandBehavior::Wait,
+  )
+  .await;
+
+  if let ExecuteResult::Continue(_, changes, _) = &result {
+    state.apply_changes(changes);
+  }
+  result
+}
+
 #[derive(Debug, PartialEq)]
 enum AsyncCommandBehavior {
   Wait,
@@ -343,7 +376,10 @@ async fn execute_pipeline_inner(
 ) -> ExecuteResult {
   match pipeline {
     PipelineInner::Command(command) => {
-      execute_command(command, state, stdin, stdout, stderr).await
+      let pipe_status = state.pipe_status_cell().clone();
+      let result = execute_command(command, state, stdin, stdout, stderr).await;
+      pipe_status.set(vec![result.exit_code()]);
+      result
     }
     PipelineInner::PipeSequence(pipe_sequence) => {
       execute_pipe_sequence(*pipe_sequence, state, stdin, stdout, stderr).await
@@ -567,6 +603,9 @@ async fn execute_pipe_sequence(
   });
   let mut results = futures::future::join_all(wait_tasks).await;
   output_handle.await.unwrap();
+  state
+    .pipe_status_cell()
+    .set(results.iter().map(ExecuteResult::exit_code).collect());
   let last_result = results.pop().unwrap();
   let all_handles = results.into_iter().flat_map(|r| r.into_handles());
   match last_result {
@@ -1,11 +1,13 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
+pub use commands::builtin_commands;
 pub use commands::ExecutableCommand;
 pub use commands::ExecuteCommandArgsContext;
 pub use commands::ShellCommand;
 pub use commands::ShellCommandContext;
 pub use execute::execute;
 pub use execute::execute_with_pipes;
+pub use execute::execute_with_state;
 pub use types::pipe;
 pub use types::EnvChange;
 pub use types::ExecuteResult;
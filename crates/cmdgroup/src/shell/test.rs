@@ -8,6 +8,12 @@ use futures::FutureExt;
 use crate::KillSignal;
 use crate::SignalKind;
 
+use crate::execute_with_state;
+use crate::parser::parse;
+use crate::ShellPipeReader;
+use crate::ShellPipeWriter;
+use crate::ShellState;
+
 use super::test_builder::TestBuilder;
 use super::types::ExecuteResult;
 
@@ -377,6 +383,49 @@ async fn sequential_lists() {
     .await;
 }
 
+#[tokio::test]
+async fn state_is_kept_between_lists() {
+  let temp_dir = tempfile::tempdir().unwrap();
+  let cwd = crate::shell::fs_util::canonicalize_path(temp_dir.path()).unwrap();
+  std::fs::create_dir(cwd.join("sub")).unwrap();
+  let mut state = ShellState::new(
+    Default::default(),
+    &cwd,
+    Default::default(),
+    KillSignal::default(),
+  );
+  let local_set = tokio::task::LocalSet::new();
+  for command in ["cd sub && export NAME=value", "VAR=1"] {
+    let exit_code = local_set
+      .run_until(execute_with_state(
+        parse(command).unwrap(),
+        &mut state,
+        ShellPipeReader::stdin(),
+        ShellPipeWriter::null(),
+        ShellPipeWriter::null(),
+      ))
+      .await
+      .exit_code();
+    assert_eq!(exit_code, 0);
+  }
+  assert_eq!(state.cwd(), &cwd.join("sub"));
+  assert_eq!(state.get_var("NAME").unwrap(), "value");
+  assert_eq!(state.get_var("VAR").unwrap(), "1");
+  assert!(!state.env_vars().contains_key("VAR"));
+
+  let result = local_set
+    .run_until(execute_with_state(
+      parse("cd .. && exit 5").unwrap(),
+      &mut state,
+      ShellPipeReader::stdin(),
+      ShellPipeWriter::null(),
+      ShellPipeWriter::null(),
+    ))
+    .await;
+  assert!(matches!(result, ExecuteResult::Exit(5, _)));
+  assert_eq!(state.cwd(), &cwd.join("sub"));
+}
+
 #[tokio::test]
 async fn pipeline() {
   TestBuilder::new()
@@ -448,6 +497,37 @@ async fn pipeline() {
     .await;
 }
 
+#[tokio::test]
+async fn pipe_status() {
+  TestBuilder::new()
+    .command("exit 3")
+    .assert_exit_code(3)
+    .assert_pipe_status(&[3])
+    .run()
+    .await;
+
+  TestBuilder::new()
+    .command("echo 1 | exit 2 | cat")
+    .assert_pipe_status(&[0, 2, 0])
+    .run()
+    .await;
+
+  // the last pipeline that finished wins
+  TestBuilder::new()
+    .command("echo 1 | exit 4 && echo 2 || exit 5")
+    .assert_exit_code(5)
+    .assert_pipe_status(&[5])
+    .run()
+    .await;
+
+  TestBuilder::new()
+    .command("(exit 1) | cat; echo 1 | cat")
+    .assert_stdout("1\n")
+    .assert_pipe_status(&[0, 0])
+    .run()
+    .await;
+}
+
 #[tokio::test]
 async fn negated() {
   TestBuilder::new()
@@ -72,6 +72,7 @@ pub struct TestBuilder {
   expected_exit_code: i32,
   expected_stderr: String,
   expected_stdout: String,
+  expected_pipe_status: Option<Vec<i32>>,
   assertions: Vec<TestAssertion>,
 }
 
@@ -101,6 +102,7 @@ impl TestBuilder {
       expected_exit_code: 0,
       expected_stderr: Default::default(),
       expected_stdout: Default::default(),
+      expected_pipe_status: None,
       assertions: Default::default(),
     }
   }
@@ -179,6 +181,11 @@ impl TestBuilder {
     self
   }
 
+  pub fn assert_pipe_status(&mut self, exit_codes: &[i32]) -> &mut Self {
+    self.expected_pipe_status = Some(exit_codes.to_vec());
+    self
+  }
+
   pub fn assert_exists(&mut self, path: &str) -> &mut Self {
     self.ensure_temp_dir();
     self
@@ -229,7 +236,13 @@ impl TestBuilder {
       self.kill_signal.clone(),
     );
     let exit_code = local_set
-      .run_until(execute_with_pipes(list, state, stdin, stdout, stderr))
+      .run_until(execute_with_pipes(
+        list,
+        state.clone(),
+        stdin,
+        stdout,
+        stderr,
+      ))
       .await;
     let temp_dir = if let Some(temp_dir) = &self.temp_dir {
       temp_dir.cwd.display().to_string()
@@ -253,6 +266,14 @@ impl TestBuilder {
       "\n\nFailed for: {}",
       self.command
     );
+    if let Some(expected_pipe_status) = &self.expected_pipe_status {
+      assert_eq!(
+        &state.pipe_status(),
+        expected_pipe_status,
+        "\n\nFailed for: {}",
+        self.command
+      );
+    }
 
     for assertion in &self.assertions {
       match assertion {
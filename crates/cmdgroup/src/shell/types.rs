@@ -43,6 +43,21 @@ impl TreeExitCodeCell {
   }
 }
 
+/// Exit codes of the commands in the last pipeline that finished,
+/// like bash's `PIPESTATUS`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PipeStatusCell(Rc<RefCell<Vec<i32>>>);
+
+impl PipeStatusCell {
+  pub fn set(&self, exit_codes: Vec<i32>) {
+    *self.0.borrow_mut() = exit_codes;
+  }
+
+  pub fn get(&self) -> Vec<i32> {
+    self.0.borrow().clone()
+  }
+}
+
 #[derive(Clone)]
 pub struct ShellState {
   /// Environment variables that should be passed down to sub commands
@@ -56,6 +71,7 @@ pub struct ShellState {
   kill_signal: KillSignal,
   process_tracker: ChildProcessTracker,
   tree_exit_code_cell: TreeExitCodeCell,
+  pipe_status_cell: PipeStatusCell,
 }
 
 impl ShellState {
@@ -76,6 +92,7 @@ impl ShellState {
       kill_signal,
       process_tracker: ChildProcessTracker::new(),
       tree_exit_code_cell: Default::default(),
+      pipe_status_cell: Default::default(),
     };
     // ensure the data is normalized
     for (name, value) in env_vars {
@@ -172,6 +189,22 @@ impl ShellState {
     &self.tree_exit_code_cell
   }
 
+  pub(crate) fn pipe_status_cell(&self) -> &PipeStatusCell {
+    &self.pipe_status_cell
+  }
+
+  /// Exit codes of each command in the last pipeline that finished,
+  /// in pipeline order. Shared by clones of this state.
+  pub fn pipe_status(&self) -> Vec<i32> {
+    self.pipe_status_cell.get()
+  }
+
+  /// Adds a custom command, or replaces the one of the same name, for this
+  /// state and those cloned from it afterwards.
+  pub fn add_command(&mut self, name: String, command: Rc<dyn ShellCommand>) {
+    Rc::make_mut(&mut self.commands).insert(name, command);
+  }
+
   /// Resolves a custom command that was injected.
   pub fn resolve_custom_command(
     &self,
@@ -223,6 +256,13 @@ impl ExecuteResult {
     ExecuteResult::Continue(exit_code, Vec::new(), Vec::new())
   }
 
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      ExecuteResult::Exit(code, _) => *code,
+      ExecuteResult::Continue(code, _, _) => *code,
+    }
+  }
+
   pub fn into_exit_code_and_handles(self) -> (i32, Vec<JoinHandle<i32>>) {
     match self {
       ExecuteResult::Exit(code, handles) => (code, handles),
@@ -1,39 +1,97 @@
 /// A wrapper around the Python ast module.
-use pyo3::prelude::PyModule;
-use pyo3::types::{IntoPyDict, PyAnyMethods};
-use pyo3::{IntoPyObject, IntoPyObjectExt, PyObject, PyResult, Python};
+///
+/// Node classes, their `_fields` and the attribute names are looked up once per conversion and
+/// nodes are built with positional arguments, which avoids a `getattr` on the module and a
+/// kwargs dict for every node.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use pyo3::prelude::{Bound, PyModule};
+use pyo3::types::{PyAnyMethods, PyDict, PyDictMethods, PyString, PyTuple};
+use pyo3::{IntoPyObject, IntoPyObjectExt, Py, PyAny, PyObject, PyResult, Python, intern};
 use ruff_source_file::SourceCode;
 use ruff_text_size::TextRange;
 
+/// Names of the location attributes, in the order returned by [`AstModule::location`].
+const LOCATION_NAMES: [&str; 4] = ["lineno", "col_offset", "end_lineno", "end_col_offset"];
+
+/// A node class with its `_fields`.
+struct NodeClass {
+    class: PyObject,
+    fields: Vec<(String, Py<PyString>)>,
+}
+
 pub struct AstModule<'py> {
-    obj: PyObject,
+    module: Bound<'py, PyModule>,
     pub py: Python<'py>,
     source_code: &'py SourceCode<'py, 'py>,
+    classes: RefCell<HashMap<&'static str, Rc<NodeClass>>>,
+    /// Shared instances of field-less nodes like `Load` or `Add`, as `ast.parse` does.
+    singletons: RefCell<HashMap<&'static str, PyObject>>,
+    names: RefCell<HashMap<&'static str, Py<PyString>>>,
+    location_names: [Py<PyString>; 4],
+}
+
+/// A node class of the `ast` module, ready to build nodes.
+pub struct AstNode<'a, 'py> {
+    module: &'a AstModule<'py>,
+    class: Rc<NodeClass>,
 }
 
 impl<'py> AstModule<'py> {
     pub fn new(py: Python<'py>, source_code: &'py SourceCode) -> PyResult<Self> {
-        let obj: PyObject = PyModule::import(py, "ast")?.unbind().into();
         Ok(Self {
-            obj,
+            module: PyModule::import(py, "ast")?,
             py,
             source_code,
+            classes: RefCell::default(),
+            singletons: RefCell::default(),
+            names: RefCell::default(),
+            location_names: LOCATION_NAMES.map(|name| PyString::intern(py, name).unbind()),
         })
     }
-    pub(crate) fn attr(&self, name: &str) -> PyResult<Self> {
-        let obj = self.obj.getattr(self.py, name)?;
-        Ok(AstModule {
-            obj,
-            py: self.py,
-            source_code: self.source_code,
+    pub(crate) fn attr(&self, name: &'static str) -> PyResult<AstNode<'_, 'py>> {
+        if let Some(class) = self.classes.borrow().get(name) {
+            return Ok(AstNode {
+                module: self,
+                class: Rc::clone(class),
+            });
+        }
+        let class = self.module.getattr(name)?;
+        let fields = class
+            .getattr(intern!(self.py, "_fields"))?
+            .extract::<Vec<Bound<'py, PyString>>>()?
+            .into_iter()
+            .map(|field| Ok((field.extract::<String>()?, field.unbind())))
+            .collect::<PyResult<_>>()?;
+        let class = Rc::new(NodeClass {
+            class: class.unbind(),
+            fields,
+        });
+        self.classes.borrow_mut().insert(name, Rc::clone(&class));
+        Ok(AstNode {
+            module: self,
+            class,
         })
     }
-    pub fn call<T: IntoPyDict<'py>>(&self, range: TextRange, kwargs: T) -> PyResult<PyObject> {
-        let dict = kwargs.into_py_dict(self.py)?;
-        for (key, value) in self.location(range) {
-            dict.set_item(key, value)?;
+    /// Returns the shared instance of a node without fields, e.g. `Load` or `Add`.
+    pub(crate) fn singleton(&self, name: &'static str) -> PyResult<PyObject> {
+        if let Some(node) = self.singletons.borrow().get(name) {
+            return Ok(node.clone_ref(self.py));
         }
-        Ok(self.obj.bind(self.py).call((), Some(&dict))?.into())
+        let node = self.attr(name)?.call0()?;
+        self.singletons
+            .borrow_mut()
+            .insert(name, node.clone_ref(self.py));
+        Ok(node)
+    }
+    fn name(&self, name: &'static str) -> Py<PyString> {
+        self.names
+            .borrow_mut()
+            .entry(name)
+            .or_insert_with(|| PyString::intern(self.py, name).unbind())
+            .clone_ref(self.py)
     }
     pub(crate) fn location(&self, range: TextRange) -> [(&'static str, usize); 4] {
         let start = self.source_code.source_location(range.start());
@@ -46,19 +104,84 @@ impl<'py> AstModule<'py> {
             ("end_col_offset", end.column.get()),
         ]
     }
+    fn set_location(&self, node: &Bound<'py, PyAny>, range: TextRange) -> PyResult<()> {
+        for (name, (_, value)) in self.location_names.iter().zip(self.location(range)) {
+            node.setattr(name.bind(self.py), value)?;
+        }
+        Ok(())
+    }
     pub fn to_const<T: IntoPyObject<'py>>(&self, range: TextRange, value: T) -> PyResult<PyObject> {
         self.attr("Constant")?.call(range, [("value", value)])
     }
-    pub fn call0(&self) -> PyResult<PyObject> {
-        Ok(self.obj.bind(self.py).call0()?.into())
-    }
-    pub fn callk<T: IntoPyDict<'py>>(&self, kwargs: T) -> PyResult<PyObject> {
-        let kwargs = kwargs.into_py_dict(self.py)?;
-        Ok(self.obj.bind(self.py).call((), Some(&kwargs))?.into())
-    }
 
     pub fn empty_list(&self) -> PyResult<PyObject> {
         let empty_vec: Vec<i32> = vec![]; // Explicitly specify the type of Vec
         empty_vec.into_py_any(self.py)
     }
 }
+
+impl<'py> AstNode<'_, 'py> {
+    /// Builds a node with the given fields and the location of `range`.
+    pub fn call<V: IntoPyObject<'py>>(
+        &self,
+        range: TextRange,
+        fields: impl IntoIterator<Item = (&'static str, V)>,
+    ) -> PyResult<PyObject> {
+        let node = self.build(fields)?;
+        self.module.set_location(&node, range)?;
+        Ok(node.unbind())
+    }
+    /// Builds a node with the given fields and no location.
+    pub fn callk<V: IntoPyObject<'py>>(
+        &self,
+        fields: impl IntoIterator<Item = (&'static str, V)>,
+    ) -> PyResult<PyObject> {
+        Ok(self.build(fields)?.unbind())
+    }
+    pub fn call0(&self) -> PyResult<PyObject> {
+        Ok(self.class.class.bind(self.module.py).call0()?.unbind())
+    }
+
+    /// Passes the leading run of given `_fields` positionally and the other fields by keyword.
+    /// Names that aren't fields, like the location, are set as attributes.
+    fn build<V: IntoPyObject<'py>>(
+        &self,
+        fields: impl IntoIterator<Item = (&'static str, V)>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let py = self.module.py;
+        let mut values: Vec<Option<PyObject>> = std::iter::repeat_with(|| None)
+            .take(self.class.fields.len())
+            .collect();
+        let mut extra = Vec::new();
+        for (name, value) in fields {
+            let value = value.into_py_any(py)?;
+            let index = self
+                .class
+                .fields
+                .iter()
+                .position(|(field, _)| field == name);
+            match index {
+                Some(index) => values[index] = Some(value),
+                None => extra.push((name, value)),
+            }
+        }
+
+        let positional = values.iter().take_while(|value| value.is_some()).count();
+        let mut values = values.into_iter();
+        let args: Vec<PyObject> = values.by_ref().take(positional).flatten().collect();
+        let args = PyTuple::new(py, args)?;
+        // Fields after a gap go by keyword so the constructor still fills in its defaults.
+        let kwargs = PyDict::new(py);
+        for ((_, name), value) in self.class.fields[positional..].iter().zip(values) {
+            if let Some(value) = value {
+                kwargs.set_item(name.bind(py), value)?;
+            }
+        }
+        let kwargs = (!kwargs.is_empty()).then_some(kwargs);
+        let node = self.class.class.bind(py).call(args, kwargs.as_ref())?;
+        for (name, value) in extra {
+            node.setattr(self.module.name(name).bind(py), value)?;
+        }
+        Ok(node)
+    }
+}
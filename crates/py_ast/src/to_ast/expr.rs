@@ -171,16 +171,16 @@ impl ToAst for FStringElement {
 impl ToAst for CmpOp {
     fn to_ast(&self, module: &AstModule) -> PyResult {
         let obj = match self {
-            CmpOp::Eq => module.attr("Eq")?.call0()?,
-            CmpOp::NotEq => module.attr("NotEq")?.call0()?,
-            CmpOp::Lt => module.attr("Lt")?.call0()?,
-            CmpOp::LtE => module.attr("LtE")?.call0()?,
-            CmpOp::Gt => module.attr("Gt")?.call0()?,
-            CmpOp::GtE => module.attr("GtE")?.call0()?,
-            CmpOp::Is => module.attr("Is")?.call0()?,
-            CmpOp::IsNot => module.attr("IsNot")?.call0()?,
-            CmpOp::In => module.attr("In")?.call0()?,
-            CmpOp::NotIn => module.attr("NotIn")?.call0()?,
+            CmpOp::Eq => module.singleton("Eq")?,
+            CmpOp::NotEq => module.singleton("NotEq")?,
+            CmpOp::Lt => module.singleton("Lt")?,
+            CmpOp::LtE => module.singleton("LtE")?,
+            CmpOp::Gt => module.singleton("Gt")?,
+            CmpOp::GtE => module.singleton("GtE")?,
+            CmpOp::Is => module.singleton("Is")?,
+            CmpOp::IsNot => module.singleton("IsNot")?,
+            CmpOp::In => module.singleton("In")?,
+            CmpOp::NotIn => module.singleton("NotIn")?,
         };
         Ok(obj)
     }
@@ -219,9 +219,9 @@ impl_to_ast!(ExprSlice, call "Slice" with fields [lower, upper, step]);
 impl ToAst for ExprContext {
     fn to_ast(&self, module: &AstModule) -> PyResult {
         let obj = match self {
-            ExprContext::Del => module.attr("Del")?.call0()?,
-            ExprContext::Load => module.attr("Load")?.call0()?,
-            ExprContext::Store => module.attr("Store")?.call0()?,
+            ExprContext::Del => module.singleton("Del")?,
+            ExprContext::Load => module.singleton("Load")?,
+            ExprContext::Store => module.singleton("Store")?,
             ExprContext::Invalid => todo!(),
         };
         Ok(obj)
@@ -304,10 +304,10 @@ impl_to_ast!(ExprBinOp, call "BinOp" with fields [left, op, right]);
 impl ToAst for UnaryOp {
     fn to_ast(&self, module: &AstModule) -> PyResult {
         let obj = match self {
-            UnaryOp::Invert => module.attr("Invert")?.call0()?,
-            UnaryOp::Not => module.attr("Not")?.call0()?,
-            UnaryOp::UAdd => module.attr("UAdd")?.call0()?,
-            UnaryOp::USub => module.attr("USub")?.call0()?,
+            UnaryOp::Invert => module.singleton("Invert")?,
+            UnaryOp::Not => module.singleton("Not")?,
+            UnaryOp::UAdd => module.singleton("UAdd")?,
+            UnaryOp::USub => module.singleton("USub")?,
         };
         Ok(obj)
     }
@@ -318,19 +318,19 @@ impl_to_ast!(ExprUnaryOp, call "UnaryOp" with fields [op, operand]);
 impl ToAst for Operator {
     fn to_ast(&self, module: &AstModule) -> PyResult {
         let obj = match self {
-            Operator::Add => module.attr("Add")?.call0()?,
-            Operator::Sub => module.attr("Sub")?.call0()?,
-            Operator::Mult => module.attr("Mult")?.call0()?,
-            Operator::MatMult => module.attr("MatMult")?.call0()?,
-            Operator::Div => module.attr("Div")?.call0()?,
-            Operator::Mod => module.attr("Mod")?.call0()?,
-            Operator::Pow => module.attr("Pow")?.call0()?,
-            Operator::LShift => module.attr("LShift")?.call0()?,
-            Operator::RShift => module.attr("RShift")?.call0()?,
-            Operator::BitOr => module.attr("BitOr")?.call0()?,
-            Operator::BitXor => module.attr("BitXor")?.call0()?,
-            Operator::FloorDiv => module.attr("FloorDiv")?.call0()?,
-            Operator::BitAnd => module.attr("BitAnd")?.call0()?,
+            Operator::Add => module.singleton("Add")?,
+            Operator::Sub => module.singleton("Sub")?,
+            Operator::Mult => module.singleton("Mult")?,
+            Operator::MatMult => module.singleton("MatMult")?,
+            Operator::Div => module.singleton("Div")?,
+            Operator::Mod => module.singleton("Mod")?,
+            Operator::Pow => module.singleton("Pow")?,
+            Operator::LShift => module.singleton("LShift")?,
+            Operator::RShift => module.singleton("RShift")?,
+            Operator::BitOr => module.singleton("BitOr")?,
+            Operator::BitXor => module.singleton("BitXor")?,
+            Operator::FloorDiv => module.singleton("FloorDiv")?,
+            Operator::BitAnd => module.singleton("BitAnd")?,
         };
         Ok(obj)
     }
@@ -338,8 +338,8 @@ impl ToAst for Operator {
 impl ToAst for BoolOp {
     fn to_ast(&self, module: &AstModule) -> PyResult {
         let obj = match self {
-            BoolOp::And | BoolOp::And2 => module.attr("And")?.call0()?,
-            BoolOp::Or | BoolOp::Or2 => module.attr("Or")?.call0()?,
+            BoolOp::And | BoolOp::And2 => module.singleton("And")?,
+            BoolOp::Or | BoolOp::Or2 => module.singleton("Or")?,
         };
         Ok(obj)
     }
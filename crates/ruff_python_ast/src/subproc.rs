@@ -0,0 +1,279 @@
+//! Helpers to recognise the subprocess expressions lowered by the parser.
+//!
+//! The parser rewrites `$(ls -la | grep x)` into
+//! `ox.cmd("ls", "-la").pipe("grep", "x").out()` and `$HOME` into `ox.env["HOME"]`.
+//! The types here give a structured view over those calls.
+
+use ruff_text_size::{Ranged, TextRange};
+
+use crate::{Arguments, DictItem, Expr, ExprCall, ExprContext, ExprSubscript, Keyword};
+
+/// The name bound to the runtime module in lowered code.
+pub const RUNTIME_NAME: &str = "ox";
+
+/// Characters that make an unquoted argument subject to glob expansion.
+pub const GLOB_CHARS: &[char] = &['*', '?', '['];
+
+/// How the output of a subprocess pipeline is consumed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaptureKind {
+    /// `$(...)`: captured stdout.
+    Out,
+    /// `!(...)`: captured process object.
+    Obj,
+    /// `$[...]`: uncaptured, returns `None`.
+    Run,
+    /// `![...]` and bare commands: uncaptured, returns the process object.
+    Hide,
+    /// `@$(...)`: output is split into arguments.
+    Inject,
+}
+
+impl CaptureKind {
+    pub fn from_method(method: &str) -> Option<Self> {
+        Some(match method {
+            "out" => CaptureKind::Out,
+            "obj" => CaptureKind::Obj,
+            "run" => CaptureKind::Run,
+            "hide" => CaptureKind::Hide,
+            "inject" => CaptureKind::Inject,
+            _ => return None,
+        })
+    }
+}
+
+/// Direction of a redirect keyword on a pipeline stage.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `> file`
+    Write,
+    /// `>> file`
+    Append,
+    /// `< file`
+    Read,
+}
+
+impl RedirectKind {
+    fn from_keyword(name: &str) -> Option<Self> {
+        Some(match name {
+            "writes" => RedirectKind::Write,
+            "appends" => RedirectKind::Append,
+            "reads" => RedirectKind::Read,
+            _ => return None,
+        })
+    }
+}
+
+/// A single redirect like `2> err.log`.
+#[derive(Debug)]
+pub struct Redirect<'a> {
+    pub kind: RedirectKind,
+    /// The stream being redirected (`""`, `2`, `e`, `a`, ...).
+    pub source: Option<&'a Expr>,
+    pub target: &'a Expr,
+}
+
+/// A single command of a pipeline, i.e. the arguments of one `cmd(...)`/`pipe(...)` call.
+#[derive(Debug)]
+pub struct Stage<'a> {
+    pub arguments: &'a Arguments,
+}
+
+impl<'a> Stage<'a> {
+    /// The words of the command, including the program name.
+    pub fn words(&self) -> &'a [Expr] {
+        &self.arguments.args
+    }
+
+    /// The program name when it is written literally.
+    pub fn program(&self) -> Option<&'a str> {
+        match self.words().first()? {
+            Expr::StringLiteral(literal) => Some(literal.value.to_str()),
+            _ => None,
+        }
+    }
+
+    /// The expression holding the program name.
+    pub fn program_expr(&self) -> Option<&'a Expr> {
+        self.words().first()
+    }
+
+    pub fn redirects(&self) -> impl Iterator<Item = Redirect<'a>> {
+        self.arguments
+            .keywords
+            .iter()
+            .flat_map(|keyword: &'a Keyword| {
+                let kind = keyword
+                    .arg
+                    .as_ref()
+                    .and_then(|arg| RedirectKind::from_keyword(arg.as_str()));
+                let items: &'a [DictItem] = match (&keyword.value, kind) {
+                    (Expr::Dict(dict), Some(_)) => &dict.items,
+                    _ => &[],
+                };
+                items.iter().map(move |item| Redirect {
+                    kind: kind.unwrap_or(RedirectKind::Write),
+                    source: item.key.as_ref(),
+                    target: &item.value,
+                })
+            })
+    }
+
+    /// Whether the stage was sent to the background with a trailing `&`.
+    pub fn is_background(&self) -> bool {
+        self.arguments
+            .keywords
+            .iter()
+            .any(|keyword| keyword.arg.as_ref().is_some_and(|arg| arg.as_str() == "bg"))
+    }
+}
+
+impl Ranged for Stage<'_> {
+    fn range(&self) -> TextRange {
+        self.arguments.range
+    }
+}
+
+/// A lowered subprocess pipeline: `ox.cmd(...).pipe(...).<method>()`.
+#[derive(Debug)]
+pub struct Pipeline<'a> {
+    pub capture: CaptureKind,
+    /// Stages in source order.
+    pub stages: Vec<Stage<'a>>,
+    range: TextRange,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Returns the pipeline when `expr` has the shape produced by the parser.
+    pub fn from_expr(expr: &'a Expr) -> Option<Self> {
+        let Expr::Call(ExprCall {
+            func, arguments, ..
+        }) = expr
+        else {
+            return None;
+        };
+        if !arguments.is_empty() {
+            return None;
+        }
+        let Expr::Attribute(method) = func.as_ref() else {
+            return None;
+        };
+        let capture = CaptureKind::from_method(method.attr.as_str())?;
+
+        let mut stages = Vec::new();
+        let mut current = method.value.as_ref();
+        loop {
+            let Expr::Call(ExprCall {
+                func, arguments, ..
+            }) = current
+            else {
+                return None;
+            };
+            let Expr::Attribute(attr) = func.as_ref() else {
+                return None;
+            };
+            stages.push(Stage { arguments });
+            match attr.attr.as_str() {
+                "pipe" => current = attr.value.as_ref(),
+                "cmd" if is_runtime_name(&attr.value) => break,
+                _ => return None,
+            }
+        }
+        stages.reverse();
+
+        Some(Self {
+            capture,
+            stages,
+            range: expr.range(),
+        })
+    }
+}
+
+impl Ranged for Pipeline<'_> {
+    fn range(&self) -> TextRange {
+        self.range
+    }
+}
+
+/// An environment variable access like `$HOME` or `${name}`.
+#[derive(Debug)]
+pub struct EnvVarRef<'a> {
+    /// The literal variable name, `None` for `${expr}` with a non-literal expression.
+    pub name: Option<&'a str>,
+    pub ctx: ExprContext,
+    range: TextRange,
+}
+
+impl<'a> EnvVarRef<'a> {
+    pub fn from_expr(expr: &'a Expr) -> Option<Self> {
+        let Expr::Subscript(ExprSubscript {
+            value,
+            slice,
+            ctx,
+            range,
+        }) = expr
+        else {
+            return None;
+        };
+        if !is_runtime_attr(value, "env") {
+            return None;
+        }
+        let name = match slice.as_ref() {
+            Expr::StringLiteral(literal) => Some(literal.value.to_str()),
+            _ => None,
+        };
+        Some(Self {
+            name,
+            ctx: *ctx,
+            range: *range,
+        })
+    }
+}
+
+impl Ranged for EnvVarRef<'_> {
+    fn range(&self) -> TextRange {
+        self.range
+    }
+}
+
+/// Returns the text of a command word written without quotes, like `*.py` in `ls *.py`.
+pub fn unquoted_word<'a>(expr: &'a Expr, source: &str) -> Option<&'a str> {
+    let Expr::StringLiteral(literal) = expr else {
+        return None;
+    };
+    let raw = source.get(expr.start().to_usize()..expr.end().to_usize())?;
+    (!raw.starts_with(['"', '\''])).then(|| literal.value.to_str())
+}
+
+/// Returns `true` if `expr` is the runtime module name `ox`.
+pub fn is_runtime_name(expr: &Expr) -> bool {
+    matches!(expr, Expr::Name(name) if name.id == RUNTIME_NAME)
+}
+
+/// Returns `true` if `expr` is `ox.<attr>`.
+pub fn is_runtime_attr(expr: &Expr, attr: &str) -> bool {
+    matches!(expr, Expr::Attribute(attribute)
+        if attribute.attr.as_str() == attr && is_runtime_name(&attribute.value))
+}
+
+/// Returns `&&`, `||` or `;` if `expr` is `ox.AND`, `ox.OR` or `ox.THEN`, which the parser
+/// lowers those operators to so that quoted or interpolated words never separate commands.
+pub fn list_operator(expr: &Expr) -> Option<&'static str> {
+    [("AND", "&&"), ("OR", "||"), ("THEN", ";")]
+        .into_iter()
+        .find(|(attr, _)| is_runtime_attr(expr, attr))
+        .map(|(_, operator)| operator)
+}
+
+/// Returns the runtime function name if `expr` is a call like `ox.<name>(...)`.
+pub fn runtime_call_name(expr: &Expr) -> Option<&str> {
+    let Expr::Call(ExprCall { func, .. }) = expr else {
+        return None;
+    };
+    match func.as_ref() {
+        Expr::Attribute(attribute) if is_runtime_name(&attribute.value) => {
+            Some(attribute.attr.as_str())
+        }
+        _ => None,
+    }
+}
@@ -607,8 +607,9 @@ impl<'src> Parser<'src> {
                 Expr::IpyEscapeCommand(self.parse_ipython_escape_command_expression())
             }
             TokenKind::String | TokenKind::FStringStart => {
+                let path_fstring = self.at_path_fstring();
                 let expr = self.parse_strings();
-                self.parse_special_strings(expr, start)
+                self.parse_special_strings(expr, start, path_fstring)
             }
             TokenKind::Lpar => {
                 return self.parse_parenthesized_expression();
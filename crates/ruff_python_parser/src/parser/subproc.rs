@@ -8,7 +8,7 @@ use ruff_text_size::{Ranged, TextRange, TextSize};
 use crate::ParseErrorType;
 use crate::{
     parser::{Parser, ParserProgress},
-    token::TokenKind,
+    token::{TokenFlags, TokenKind},
 };
 
 use super::combinators::{Combinator as _, ParseResult};
@@ -149,6 +149,16 @@ impl Parser<'_> {
             | TokenKind::Dollar
             | TokenKind::DollarLParen
             | TokenKind::AtDollarLParen => self.parse_atom().expr,
+            TokenKind::DoubleAmp | TokenKind::DoublePipe | TokenKind::Semi => {
+                let name = match kind {
+                    TokenKind::DoubleAmp => "AND",
+                    TokenKind::DoublePipe => "OR",
+                    _ => "THEN",
+                };
+                let operator = self.xonsh_attr(name, None);
+                self.bump_any();
+                operator
+            }
             tk if tk.is_proc_op() => {
                 let range = self.current_token_range();
                 self.bump_any();
@@ -234,7 +244,9 @@ impl Parser<'_> {
             }
             TokenKind::Name if self.peek() == TokenKind::String => {
                 let start = self.node_start();
-                let name = Expr::from(self.parse_name());
+                // search functions are looked up by name, see `ox.Pattern.register`
+                let name = self.parse_name();
+                let name = string_literal(name.range, name.id.to_string());
                 let string = self.parse_strings();
                 let range = self.node_range(start);
                 self.xonsh_attr("Pattern", None)
@@ -300,10 +312,24 @@ impl Parser<'_> {
     pub(super) fn parse_env_name(&mut self) -> ParseResult<Expr> {
         // Match $ followed by a name
         let dollar = TokenKind::Dollar.parse(self)?;
+        if self.at(TokenKind::Question) && self.node_start() == dollar.end() {
+            // `$?` is the exit status of the last command
+            let range = TextRange::new(dollar.start(), self.current_token_range().end());
+            let attr = self.xonsh_attr("last_status", Some(range));
+            self.bump_any();
+            return Ok(attr);
+        }
         let attr = self.xonsh_attr("env", Some(dollar));
         let start = self.node_start();
-        let name = TokenKind::Name.parse(self)?;
-        let slice = self.to_string_literal(name);
+        let slice = if self.at(TokenKind::Int) && start == dollar.end() {
+            // `$0`, `$1`, ... are the arguments of the script being run
+            let number = self.current_token_range();
+            self.bump_any();
+            self.to_string_literal(number)
+        } else {
+            let name = TokenKind::Name.parse(self)?;
+            self.to_string_literal(name)
+        };
         let ast = ast::ExprSubscript {
             value: Box::new(attr),
             slice: Box::new(slice),
@@ -338,7 +364,27 @@ impl Parser<'_> {
         };
         Ok(Expr::Subscript(ast))
     }
-    pub(super) fn parse_special_strings(&mut self, expr: Expr, start: TextSize) -> Expr {
+    /// Returns `true` at the start of a `pf"..."` string. The AST flags of an f-string don't keep
+    /// the `p` prefix, so it is read from the token before the string is parsed.
+    pub(super) fn at_path_fstring(&self) -> bool {
+        self.at(TokenKind::FStringStart)
+            && self
+                .tokens
+                .current_flags()
+                .intersects(TokenFlags::PATH_STRING)
+    }
+
+    pub(super) fn parse_special_strings(
+        &mut self,
+        expr: Expr,
+        start: TextSize,
+        path_fstring: bool,
+    ) -> Expr {
+        if path_fstring {
+            return self
+                .xonsh_attr("path", None)
+                .call0(vec![expr], self.node_range(start));
+        }
         if let Expr::StringLiteral(s) = &expr {
             if s.value.is_path() {
                 return self
@@ -350,6 +396,12 @@ impl Parser<'_> {
                     .call0(vec![expr], self.node_range(start))
                     .attr("regex", self.node_range(start))
                     .call_empty(self.node_range(start));
+            } else if s.value.is_glob() {
+                return self
+                    .xonsh_attr("Pattern", None)
+                    .call0(vec![expr], self.node_range(start))
+                    .attr("glob", self.node_range(start))
+                    .call_empty(self.node_range(start));
             }
         }
         expr
@@ -1,12 +1,14 @@
 from ._oxipy import cli_main
 
 
-def main(args: list[str] | None = None):
+def main(args: list[str] | None = None) -> int:
     import sys
 
     args = args or sys.argv[1:]
-    cli_main(*args)
+    return cli_main(*args)
 
 
 if __name__ == "__main__":
-    main()
+    import sys
+
+    sys.exit(main())
@@ -1,5 +1,11 @@
+import os
 from ast import AST
-from typing import Sequence
+from collections.abc import Callable, Iterable, Iterator
+from typing import TypeVar
+
+from .paths import ShellPath
+
+_T = TypeVar("_T")
 
 class Token:
     start: int
@@ -14,9 +20,25 @@ class Token:
     @property
     def lexpos(self) -> int: ...
 
+class SemanticToken:
+    start: int
+    end: int
+
+    @property
+    def role(self) -> str: ...
+    @property
+    def lineno(self) -> int: ...
+    @property
+    def end_lineno(self) -> int: ...
+    @property
+    def value(self) -> str: ...
+
 class Parser:
-    def __init__(self, src: str, file_name: str | None = None) -> None: ...
+    def __init__(
+        self, src: str | bytes, file_name: str | bytes | os.PathLike | None = None
+    ) -> None: ...
     def tokens(self, tolerant=False) -> list[Token]: ...
+    def semantic_tokens(self) -> list[SemanticToken]: ...
     def subproc_toks(
         self,
         returnline: bool = False,
@@ -25,8 +47,98 @@ class Parser:
         maxcol: int | None = None,
     ) -> str | None: ...
     @staticmethod
-    def parse_file(path: str) -> AST: ...
+    def parse_file(path: str | bytes | os.PathLike) -> AST: ...
+    @staticmethod
+    def parse_many(
+        paths: Iterable[str | bytes | os.PathLike],
+    ) -> list[AST | SyntaxError | OSError | UnicodeDecodeError]: ...
     def parse(self) -> AST: ...
     def split(self) -> list[str]: ...
 
-def cli_main(args: Sequence[str, ...]) -> None: ...
+class PipeReader:
+    """The stdin of a callable pipeline stage."""
+
+    closed: bool
+
+    def read(self, size: int = -1) -> str: ...
+    def readline(self) -> str: ...
+    def readlines(self) -> list[str]: ...
+    def __iter__(self) -> Iterator[str]: ...
+    def __next__(self) -> str: ...
+    def readable(self) -> bool: ...
+    def isatty(self) -> bool: ...
+    def close(self) -> None: ...
+
+class PipeWriter:
+    """The stdout or stderr of a callable pipeline stage."""
+
+    closed: bool
+
+    def write(self, data: str | bytes) -> int: ...
+    def writelines(self, lines: Iterable[str | bytes]) -> None: ...
+    def flush(self) -> None: ...
+    def writable(self) -> bool: ...
+    def isatty(self) -> bool: ...
+    def close(self) -> None: ...
+
+class CompletedCommand:
+    args: list[list[str]]
+    returncode: int
+    pipestatus: list[int]
+    output: str | None
+    errors: str | None
+    started: float
+    duration: float
+
+    def __bool__(self) -> bool: ...
+
+class ListOperator:
+    """`&&`, `||` or `;` between the commands of a subprocess."""
+
+AND: ListOperator
+OR: ListOperator
+THEN: ListOperator
+
+class Cmd:
+    def __init__(
+        self,
+        *args: object,
+        writes: dict[str | int, str | os.PathLike] | None = None,
+        appends: dict[str | int, str | os.PathLike] | None = None,
+        reads: dict[str | int, str | os.PathLike] | None = None,
+        bg: bool = False,
+    ) -> None: ...
+    def pipe(
+        self,
+        *args: object,
+        writes: dict[str | int, str | os.PathLike] | None = None,
+        appends: dict[str | int, str | os.PathLike] | None = None,
+        reads: dict[str | int, str | os.PathLike] | None = None,
+        bg: bool = False,
+    ) -> Cmd: ...
+    def out(self) -> str: ...
+    def obj(self) -> CompletedCommand: ...
+    def run(self) -> None: ...
+    def hide(self) -> CompletedCommand: ...
+    def inject(self) -> list[str]: ...
+
+class Pattern:
+    pattern: str
+
+    def __init__(self, pattern: object) -> None: ...
+    def regex(self) -> list[str]: ...
+    def glob(self) -> list[str]: ...
+    def invoke(self, name: str) -> list[str]: ...
+    @staticmethod
+    def register(name: str, func: Callable[[str], Iterable[str | os.PathLike]]) -> Callable: ...
+    @staticmethod
+    def unregister(name: str) -> Callable: ...
+    @staticmethod
+    def unregister_all() -> None: ...
+
+def last_command() -> CompletedCommand | None: ...
+def help(obj: _T) -> _T: ...
+def superhelp(obj: _T) -> _T: ...
+def path(value: str | bytes | os.PathLike) -> ShellPath: ...
+
+def cli_main(*args: str) -> int: ...
@@ -0,0 +1,108 @@
+"""A `__pycache__`-style cache of compiled `.oxy` sources.
+
+Entries are written next to the source as `__pycache__/<name>.<cache_tag>.oxyc`. They hold a
+header with a digest of the source bytes, the oxipy build (`__build__`), the Python bytecode
+magic and the compile options, followed by the marshal'd code object. An entry whose digest
+doesn't match is recompiled and rewritten, so stale caches are never used.
+"""
+
+import hashlib
+import importlib.util
+import json
+import marshal
+import os
+import sys
+from pathlib import Path
+from types import CodeType
+
+from ._oxipy import Parser, __build__
+
+MAGIC = b"OXYC"
+SUFFIX = ".oxyc"
+DIGEST_SIZE = 16
+
+ENABLED = not os.environ.get("OXIPY_NO_CACHE")
+"""Set `OXIPY_NO_CACHE` or pass `--no-cache` to never read or write cache files."""
+
+
+def build_key() -> bytes:
+    return json.dumps(__build__, sort_keys=True, default=str).encode()
+
+
+def cache_key(source: bytes, optimize: int = -1) -> bytes:
+    """The digest an entry must carry for `source` compiled with `optimize`."""
+    digest = hashlib.blake2b(digest_size=DIGEST_SIZE)
+    for part in (
+        source,
+        build_key(),
+        importlib.util.MAGIC_NUMBER,
+        json.dumps({"optimize": optimize}).encode(),
+    ):
+        digest.update(len(part).to_bytes(8, "little"))
+        digest.update(part)
+    return digest.digest()
+
+
+def cache_from_source(path: str | os.PathLike) -> Path:
+    """The cache file for the source at `path`."""
+    path = Path(path)
+    tag = sys.implementation.cache_tag or "oxipy"
+    return path.parent / "__pycache__" / f"{path.stem}.{tag}{SUFFIX}"
+
+
+def source_to_code(
+    source: bytes, path: str | os.PathLike, optimize: int = -1
+) -> CodeType:
+    """Parses and compiles `source` without touching the cache."""
+    filename = os.fspath(path)
+    tree = Parser(source, file_name=filename).parse()
+    return compile(tree, filename, "exec", dont_inherit=True, optimize=optimize)
+
+
+def read_cache(cache: Path, key: bytes) -> CodeType | None:
+    try:
+        data = cache.read_bytes()
+    except OSError:
+        return None
+    header = MAGIC + key
+    if not data.startswith(header):
+        return None
+    try:
+        code = marshal.loads(data[len(header) :])
+    except (EOFError, ValueError, TypeError):
+        return None
+    return code if isinstance(code, CodeType) else None
+
+
+def write_cache(cache: Path, key: bytes, code: CodeType) -> None:
+    """Writes atomically and gives up quietly, e.g. on read-only directories."""
+    tmp = cache.with_name(f"{cache.name}.{os.getpid()}.tmp")
+    try:
+        cache.parent.mkdir(exist_ok=True)
+        tmp.write_bytes(MAGIC + key + marshal.dumps(code))
+        os.replace(tmp, cache)
+    except OSError:
+        tmp.unlink(missing_ok=True)
+
+
+def get_code(
+    path: str | os.PathLike, *, use_cache: bool | None = None, optimize: int = -1
+) -> CodeType:
+    """Returns the code object for the source at `path`, using the cache when allowed.
+
+    `use_cache` defaults to `ENABLED`. Nothing is written when `sys.dont_write_bytecode` is set.
+    """
+    if use_cache is None:
+        use_cache = ENABLED
+    source = Path(path).read_bytes()
+    if not use_cache:
+        return source_to_code(source, path, optimize)
+
+    key = cache_key(source, optimize)
+    cache = cache_from_source(path)
+    code = read_cache(cache, key)
+    if code is None:
+        code = source_to_code(source, path, optimize)
+        if not sys.dont_write_bytecode:
+            write_cache(cache, key, code)
+    return code
@@ -0,0 +1,264 @@
+"""Typed environment variables, bound to `ox.env`.
+
+Child processes only see strings, but variables registered with a type hold Python values:
+`PATH` is an `EnvPath` list, `SHLVL` is an `int`, and so on. Values are converted when they are
+set and turned back into strings by `Env.env_vars` when a command runs. Variables without a
+registration are plain strings. Those registered with `export=False`, like `$ARGS`, are only
+seen by Python code and not by child processes.
+
+    ox.env.register("OXIPY_DEBUG", bool, default=False, doc="Print debug output.")
+    $PATH.add("~/bin", front=True)
+"""
+
+import os
+import shlex
+from collections.abc import Callable, Iterable, Iterator, Mapping, MutableMapping, MutableSequence
+from dataclasses import dataclass
+from typing import Any
+
+MISSING: Any = object()
+
+FALSE_WORDS = frozenset({"", "0", "n", "no", "f", "false", "off", "none"})
+
+
+class EnvPath(MutableSequence[str]):
+    """A list of paths that is joined with `os.pathsep` for child processes.
+
+    Strings are split on `os.pathsep` and a leading `~` is expanded when a path is added.
+    """
+
+    def __init__(self, paths: "str | os.PathLike | Iterable[str | os.PathLike]" = ()):
+        if isinstance(paths, str | bytes | os.PathLike):
+            paths = split_paths(paths)
+        self._paths: list[str] = [normalize_path(path) for path in paths]
+
+    def __getitem__(self, index):
+        if isinstance(index, slice):
+            return EnvPath(self._paths[index])
+        return self._paths[index]
+
+    def __setitem__(self, index, value):
+        if isinstance(index, slice):
+            self._paths[index] = [normalize_path(path) for path in value]
+        else:
+            self._paths[index] = normalize_path(value)
+
+    def __delitem__(self, index):
+        del self._paths[index]
+
+    def __len__(self) -> int:
+        return len(self._paths)
+
+    def insert(self, index: int, value: "str | os.PathLike") -> None:
+        self._paths.insert(index, normalize_path(value))
+
+    def add(self, path: "str | os.PathLike", front: bool = False, replace: bool = False) -> None:
+        """Adds `path` at the end, or the front. With `replace`, an existing entry is moved."""
+        path = normalize_path(path)
+        if path in self._paths:
+            if not replace:
+                return
+            self._paths.remove(path)
+        if front:
+            self._paths.insert(0, path)
+        else:
+            self._paths.append(path)
+
+    def __eq__(self, other) -> bool:
+        if isinstance(other, EnvPath):
+            return self._paths == other._paths
+        if isinstance(other, list | tuple):
+            return self._paths == [normalize_path(path) for path in other]
+        return NotImplemented
+
+    def __str__(self) -> str:
+        return os.pathsep.join(self._paths)
+
+    def __repr__(self) -> str:
+        return f"EnvPath({self._paths!r})"
+
+
+def split_paths(value: "str | bytes | os.PathLike") -> list[str]:
+    text = os.fsdecode(value)
+    return [path for path in text.split(os.pathsep) if path] if text else []
+
+
+def normalize_path(path: "str | os.PathLike") -> str:
+    return os.path.expanduser(os.fsdecode(path))
+
+
+def to_bool(value) -> bool:
+    if isinstance(value, str):
+        return value.strip().lower() not in FALSE_WORDS
+    return bool(value)
+
+
+def to_int(value) -> int:
+    if isinstance(value, str):
+        return int(value.strip())
+    return int(value)
+
+
+def bool_to_str(value: bool) -> str:
+    return "1" if value else "0"
+
+
+def to_args(value) -> list[str]:
+    if isinstance(value, str):
+        return shlex.split(value)
+    return [str(arg) for arg in value]
+
+
+@dataclass(frozen=True)
+class Var:
+    """How a variable is converted, what it defaults to and what it is for."""
+
+    convert: Callable[[Any], Any]
+    """Turns a value being set, often a string from the environment, into the stored value."""
+    detype: Callable[[Any], str] = str
+    """Turns the stored value into the string child processes see."""
+    default: Any = MISSING
+    """Returned when the variable isn't set; it is not passed to child processes."""
+    doc: str = ""
+    export: bool = True
+    """Whether child processes see the variable."""
+
+
+CONVERTERS: dict[Any, tuple[Callable[[Any], Any], Callable[[Any], str]]] = {
+    str: (str, str),
+    bool: (to_bool, bool_to_str),
+    int: (to_int, str),
+    float: (float, str),
+    EnvPath: (EnvPath, str),
+}
+
+
+def var(
+    type: Any = str,
+    *,
+    convert: Callable[[Any], Any] | None = None,
+    detype: Callable[[Any], str] | None = None,
+    default: Any = MISSING,
+    doc: str = "",
+    export: bool = True,
+) -> Var:
+    """Describes a variable of one of the `CONVERTERS` types, or with custom converters."""
+    if convert is None:
+        try:
+            convert, type_detype = CONVERTERS[type]
+        except KeyError:
+            raise TypeError(f"no converter for {type!r}, pass `convert`") from None
+        detype = detype or type_detype
+    return Var(convert, detype or str, default, doc, export)
+
+
+DEFAULT_VARS: dict[str, Var] = {
+    "PATH": var(EnvPath, doc="Directories searched for commands."),
+    "PYTHONPATH": var(EnvPath, doc="Directories searched for Python modules."),
+    "MANPATH": var(EnvPath, doc="Directories searched for manual pages."),
+    "LD_LIBRARY_PATH": var(EnvPath, doc="Directories searched for shared libraries."),
+    "CDPATH": var(EnvPath, doc="Directories searched by `cd` for relative paths."),
+    "SHLVL": var(int, default=0, doc="How many shells deep this one is."),
+    "COLUMNS": var(int, doc="Width of the terminal."),
+    "LINES": var(int, doc="Height of the terminal."),
+    "OXIPY_COLORS": var(
+        doc="Colors of the REPL's syntax highlighting, like `command=1;32:comment=90`."
+    ),
+    "OXIPY_PROMPT": var(
+        doc="Template of the REPL's prompt, like `{short_cwd}{git_branch: ({})}> `."
+    ),
+    "OXIPY_RIGHT_PROMPT": var(doc="Template of the prompt at the right edge of the terminal."),
+    "OXIPY_CONTINUATION_PROMPT": var(doc="Template of the prompt of an entry's later lines."),
+    "ARGS": Var(
+        to_args,
+        shlex.join,
+        doc="The script being run and its arguments, like sys.argv.",
+        export=False,
+    ),
+}
+
+
+class Env(MutableMapping[str, Any]):
+    """Environment variables with registered types; see the module docs."""
+
+    def __init__(self, environ: Mapping[str, str] | None = None):
+        self._vars: dict[str, Var] = dict(DEFAULT_VARS)
+        self._values: dict[str, Any] = {}
+        for name, value in (os.environ if environ is None else environ).items():
+            try:
+                self[name] = value
+            except (TypeError, ValueError):
+                # an unusable value from outside is kept as it was given
+                self._values[name] = value
+
+    def register(
+        self,
+        name: str,
+        type: Any = str,
+        *,
+        convert: Callable[[Any], Any] | None = None,
+        detype: Callable[[Any], str] | None = None,
+        default: Any = MISSING,
+        doc: str = "",
+        export: bool = True,
+    ) -> None:
+        """Gives `name` a type, default and docs, converting its current value if it's set.
+
+        `type` is one of `str`, `bool`, `int`, `float` or `EnvPath`; pass `convert` and
+        `detype` for any other type. With `export=False`, child processes don't see it.
+        """
+        self._vars[name] = var(
+            type, convert=convert, detype=detype, default=default, doc=doc, export=export
+        )
+        if name in self._values:
+            self[name] = self._values[name]
+
+    def describe(self, name: str) -> Var | None:
+        """The registration of `name`, if it has one."""
+        return self._vars.get(name)
+
+    def doc(self, name: str) -> str:
+        registered = self._vars.get(name)
+        return registered.doc if registered else ""
+
+    def __getitem__(self, name: str):
+        try:
+            return self._values[name]
+        except KeyError:
+            registered = self._vars.get(name)
+            if registered is None or registered.default is MISSING:
+                raise
+            return registered.default
+
+    def __setitem__(self, name: str, value) -> None:
+        registered = self._vars.get(name)
+        self._values[name] = str(value) if registered is None else registered.convert(value)
+
+    def __delitem__(self, name: str) -> None:
+        del self._values[name]
+
+    def __iter__(self) -> Iterator[str]:
+        return iter(self._values)
+
+    def __len__(self) -> int:
+        return len(self._values)
+
+    def __contains__(self, name) -> bool:
+        return name in self._values
+
+    def detype(self, name: str) -> str:
+        """The string a child process sees for `name`."""
+        value = self._values[name]
+        registered = self._vars.get(name)
+        return registered.detype(value) if registered else str(value)
+
+    def is_exported(self, name: str) -> bool:
+        registered = self._vars.get(name)
+        return registered is None or registered.export
+
+    def env_vars(self) -> dict[str, str]:
+        """Every exported variable as a string, for the environment of child processes."""
+        return {name: self.detype(name) for name in self._values if self.is_exported(name)}
+
+    def __repr__(self) -> str:
+        return f"Env({self._values!r})"
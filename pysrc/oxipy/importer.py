@@ -0,0 +1,66 @@
+"""Importing `.oxy` modules, compiled through `oxipy.cache`.
+
+`install()` appends `OxyFinder` to `sys.meta_path`, so regular `.py` modules still win. The
+shell installs it when it starts.
+"""
+
+import importlib.abc
+import importlib.machinery
+import importlib.util
+import os
+import sys
+from pathlib import Path
+
+from . import cache, ox
+
+SOURCE_SUFFIXES = (".oxy",)
+
+
+class OxyLoader(importlib.abc.FileLoader, importlib.abc.SourceLoader):
+    """Loads a `.oxy` module, reading and writing its `.oxyc` cache entry."""
+
+    def get_code(self, fullname):
+        return cache.get_code(self.get_filename(fullname))
+
+    def exec_module(self, module):
+        # lowered commands refer to the runtime as `ox`, like in the REPL's namespace
+        module.ox = ox
+        super().exec_module(module)
+
+
+class OxyFinder(importlib.abc.MetaPathFinder):
+    @classmethod
+    def find_spec(cls, fullname, path=None, target=None):
+        name = fullname.rpartition(".")[2]
+        for entry in path if path is not None else sys.path:
+            if not isinstance(entry, str):
+                continue
+            base = Path(entry or os.getcwd()) / name
+            for suffix in SOURCE_SUFFIXES:
+                init = base / f"__init__{suffix}"
+                if init.is_file():
+                    return cls.spec(fullname, init, is_package=True)
+                module = base.with_name(name + suffix)
+                if module.is_file():
+                    return cls.spec(fullname, module)
+        return None
+
+    @staticmethod
+    def spec(fullname: str, path: Path, is_package: bool = False):
+        location = os.fspath(path)
+        return importlib.util.spec_from_file_location(
+            fullname,
+            location,
+            loader=OxyLoader(fullname, location),
+            submodule_search_locations=[os.fspath(path.parent)] if is_package else None,
+        )
+
+
+def install() -> None:
+    if OxyFinder not in sys.meta_path:
+        sys.meta_path.append(OxyFinder)
+
+
+def uninstall() -> None:
+    if OxyFinder in sys.meta_path:
+        sys.meta_path.remove(OxyFinder)
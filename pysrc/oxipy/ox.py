@@ -0,0 +1,103 @@
+"""The runtime that oxipy source is lowered to.
+
+The parser turns shell syntax into calls on the `ox` name, e.g. `$(ls | wc -l)` becomes
+`ox.cmd('ls').pipe('wc', '-l').out()`; code runs with this module bound to `ox`.
+
+A Python callable may be a pipeline stage, as in `$(ls | @(func) -x)`. It is called with as many
+of `(args, stdin, stdout, stderr)` as it accepts and runs on its own thread, reading and writing
+the pipes of the stage while the other commands run. Returning `None` exits with 0, an `int` is
+the exit code, a `str` or `bytes` is written to stdout, and a tuple `(stdout, stderr)` or
+`(stdout, stderr, code)` writes both streams. An exception prints its traceback to the stage's
+stderr and exits with 1.
+
+Commands run with the variables in `ox.env` rather than `os.environ`; see `oxipy.env`. Its `PWD`
+is the shell's working directory: `cd` and `export` in `$[...]`, `![...]` and bare commands change
+`ox.env` like they would in a shell, while `$(...)` and `!(...)` run like command substitution and
+keep no changes. `p"..."` paths are relative to `PWD`, not to the directory of the process.
+
+Regex (`` `...` ``) and glob (`g"..."`) literals search for files in `PWD` and give sorted paths
+relative to it; `@name"..."` calls a search function added with `ox.Pattern.register`.
+
+`ox.last` is the `CompletedCommand` of the last foreground pipeline, with the exit code of each
+of its commands in `pipestatus`, and `ox.last_status` (`$?`) its exit code.
+
+`obj?` and `obj??` call `ox.help` and `ox.superhelp`, which print the signature, docstring and
+file of an object, and for `??` its source. A command name like `"ls"?` shows where the command
+resolves to and its `--help` output. Both return the object, so help can be chained.
+"""
+
+import os
+from collections.abc import Callable, Iterable
+from typing import Any
+
+from ._oxipy import (
+    AND,
+    OR,
+    THEN,
+    Cmd as cmd,
+    CompletedCommand,
+    Pattern,
+    PipeReader,
+    PipeWriter,
+    help,
+    last_command,
+    path,
+    superhelp,
+)
+from .env import Env, EnvPath
+from .paths import ShellPath
+
+__all__ = [
+    "AND",
+    "OR",
+    "THEN",
+    "cmd",
+    "env",
+    "Env",
+    "EnvPath",
+    "help",
+    "superhelp",
+    "path",
+    "Pattern",
+    "ShellPath",
+    "CompletedCommand",
+    "PipeReader",
+    "PipeWriter",
+    "list_of_strs_or_callables",
+    "last",
+    "last_status",
+    "prompt_fields",
+]
+
+env = Env()
+"""`$NAME`: the variables commands run with, see `oxipy.env`."""
+env["PWD"] = os.getcwd()
+
+prompt_fields: dict[str, Any] = {}
+"""Fields of the REPL's prompts set from Python, like `ox.prompt_fields["jobs"] = count_jobs`.
+
+A callable is called each time the prompt is shown. These come before the fields of the REPL,
+so `cwd` or `git_branch` can be replaced too.
+"""
+
+
+def __getattr__(name: str):
+    if name == "last":
+        return last_command()
+    if name == "last_status":
+        last = last_command()
+        return 0 if last is None else last.returncode
+    raise AttributeError(f"module {__name__!r} has no attribute {name!r}")
+
+
+def list_of_strs_or_callables(x) -> list[str | Callable]:
+    """Expands the value of `@(x)` into command arguments.
+
+    Strings and callables are kept as a single argument, other iterables give one argument per
+    item and any other value is converted with `str`.
+    """
+    if isinstance(x, str | bytes) or callable(x):
+        return [x]
+    if isinstance(x, Iterable):
+        return [item if isinstance(item, str) or callable(item) else str(item) for item in x]
+    return [str(x)]
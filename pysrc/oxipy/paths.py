@@ -0,0 +1,17 @@
+"""The paths that `p"..."` literals evaluate to."""
+
+import shlex
+from pathlib import Path
+
+
+class ShellPath(type(Path())):
+    """A `pathlib.Path` made by `ox.path`, with `~` and variables expanded and made absolute
+    against the shell's working directory.
+
+    Passed to a command, it is always one argument, whatever characters it contains.
+    """
+
+    @property
+    def quoted(self) -> str:
+        """The path quoted for a POSIX shell, to build a command line like `sh -c`."""
+        return shlex.quote(str(self))
@@ -0,0 +1,161 @@
+"""Running the Python entries of the REPL, and scripts.
+
+The shell runs entries made only of plain commands itself; the rest come here, one entry at a
+time, with a namespace that lives as long as the session.
+"""
+
+import ast
+import builtins
+import os
+import rlcompleter
+import sys
+import traceback
+from collections.abc import Iterable
+from types import CodeType
+
+from . import cache, ox
+from ._oxipy import Parser
+
+COMMAND_METHODS = frozenset({"hide", "run"})
+"""The methods ending pipelines whose output already went to the terminal."""
+
+
+def namespace() -> dict:
+    """The globals of a new session."""
+    return {"__name__": "__main__", "__builtins__": builtins, "ox": ox}
+
+
+def is_command(node: ast.stmt) -> bool:
+    """Whether `node` is a statement like `ls` or `$[ls]`, whose value isn't worth showing."""
+    if not (isinstance(node, ast.Expr) and isinstance(node.value, ast.Call)):
+        return False
+    func = node.value.func
+    return isinstance(func, ast.Attribute) and func.attr in COMMAND_METHODS
+
+
+def run(source: str, namespace: dict, filename: str = "<stdin>") -> int:
+    """Runs `source` like an entry typed at the prompt and returns its exit status.
+
+    The values of expressions are shown with `sys.displayhook`. The status is the one of the
+    last command that ran, or 1 if there was an error, which is printed. `SystemExit` is left
+    for the shell to end the session with.
+    """
+    try:
+        tree = Parser(source, file_name=filename).parse()
+    except SyntaxError as err:
+        # the message holds the code frame of the error
+        print(err.msg, file=sys.stderr)
+        return 1
+    return execute(
+        (
+            compile(ast.Module([node], []), filename, "exec")
+            if is_command(node)
+            else compile(ast.Interactive([node]), filename, "single")
+            for node in tree.body
+        ),
+        namespace,
+    )
+
+
+def run_script(path: "str | os.PathLike", args: list[str], namespace: dict) -> int:
+    """Runs a script, or the program on stdin when `path` is `-`, and returns its exit status.
+
+    `sys.argv` is `[path, *args]`, and `$ARGS`, `$0`, `$1`... hold the same for commands. The
+    script is compiled through `oxipy.cache`.
+    """
+    path = os.fspath(path)
+    set_args([path, *args])
+    if path != "-":
+        namespace["__file__"] = path
+        sys.path.insert(0, os.path.dirname(os.path.abspath(path)))
+    return source(path, namespace)
+
+
+def source(path: "str | os.PathLike", namespace: dict) -> int:
+    """Runs a file in `namespace`, like `source` in a shell, and returns its exit status.
+
+    Unlike `run_script`, the arguments of the session are left alone. Errors are printed with
+    the file and line they come from.
+    """
+    path = os.fspath(path)
+    try:
+        if path == "-":
+            code = cache.source_to_code(sys.stdin.buffer.read(), "<stdin>")
+        else:
+            code = cache.get_code(path)
+    except SyntaxError as err:
+        print(err.msg, file=sys.stderr)
+        return 1
+    except OSError as err:
+        print(f"oxipy: can't open file {path!r}: {err.strerror}", file=sys.stderr)
+        return 2
+    return execute([code], namespace)
+
+
+def set_args(argv: list[str]) -> None:
+    """Sets `sys.argv` and `$ARGS`, `$0`, `$1`..., which child processes don't inherit."""
+    sys.argv[:] = argv
+    ox.env["ARGS"] = argv
+    for name in [name for name in ox.env if name.isdigit() and int(name) >= len(argv)]:
+        del ox.env[name]
+    for index, arg in enumerate(argv):
+        ox.env.register(str(index), export=False, doc="An argument of the script being run.")
+        ox.env[str(index)] = arg
+
+
+def execute(codes: Iterable[CodeType], namespace: dict) -> int:
+    """Runs the code objects in turn and returns the status of the last command that ran, or 1
+    if one raised, after printing the error."""
+    last = ox.last
+    try:
+        for code in codes:
+            exec(code, namespace)
+    except SystemExit:
+        raise
+    except BaseException as err:
+        traceback.print_exception(type(err), err, err.__traceback__.tb_next)
+        return 1
+    finally:
+        sys.stdout.flush()
+    done = ox.last
+    return 0 if done is None or done is last else done.returncode
+
+
+def complete(prefix: str, namespace: dict) -> list[str]:
+    """The names completing `prefix` in `namespace`, or the attributes for a dotted `prefix`
+    like `os.pa`, as the Python REPL offers them."""
+    completer = rlcompleter.Completer(namespace)
+    if "." in prefix:
+        matches = completer.attr_matches(prefix)
+    else:
+        matches = completer.global_matches(prefix)
+    return sorted(set(matches))
+
+
+def prompt_fields(names: list[str]) -> dict[str, str]:
+    """The values of the fields among `names` set in `ox.prompt_fields`, calling the callables.
+
+    A field that raises is printed and left empty, as is one that returns `None`.
+    """
+    values = {}
+    for name in names:
+        if name not in ox.prompt_fields:
+            continue
+        field = ox.prompt_fields[name]
+        try:
+            value = field() if callable(field) else field
+        except Exception as err:
+            print(f"oxipy: prompt field {name!r} failed: {err!r}", file=sys.stderr)
+            value = None
+        values[name] = "" if value is None else str(value)
+    return values
+
+
+def exit_code(err: SystemExit) -> int:
+    """The status `sys.exit(code)` asks for. Other values are printed, like Python does."""
+    if err.code is None:
+        return 0
+    if isinstance(err.code, int):
+        return err.code
+    print(err.code, file=sys.stderr)
+    return 1
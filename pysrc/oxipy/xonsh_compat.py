@@ -0,0 +1,304 @@
+"""A drop-in replacement for xonsh's `Parser`, backed by the oxipy parser.
+
+The tree is lowered to the `__xonsh__.*` runtime calls that xonsh's own parser produces, so
+xonsh can execute it as usual, e.g. `$(ls -l | wc)` becomes
+`__xonsh__.subproc_captured_stdout(['ls', '-l'], '|', ['wc'])`.
+"""
+
+import ast
+
+from ._oxipy import Parser as _Parser
+
+OX = "ox"
+XONSH = "__xonsh__"
+
+SUBPROC_METHODS = {
+    "out": "subproc_captured_stdout",
+    "obj": "subproc_captured_object",
+    "run": "subproc_uncaptured",
+    "hide": "subproc_captured_hiddenobject",
+    "inject": "subproc_captured_inject",
+}
+"""Methods ending an oxipy pipeline and the xonsh function running it."""
+
+LIST_OPERATORS = {"AND": ast.And, "OR": ast.Or, "THEN": None}
+"""The `ox.AND`, `ox.OR` and `ox.THEN` markers between commands and the Python operator
+xonsh joins them with, like `![a] and ![b]` for `a && b`; `;` runs the next one regardless."""
+
+REDIRECT_OPS = {"writes": ">", "appends": ">>", "reads": "<"}
+
+LAST_STATUS = "LAST_RETURN_CODE"
+"""The xonsh variable with the status of the last command, `$?` and `ox.last_status` here."""
+
+SEARCH_METHODS = {"regex": "regexsearch", "glob": "globsearch"}
+"""`ox.Pattern(pattern)` methods and the xonsh function doing the same search."""
+
+
+def is_ox_attr(node: ast.AST, name: str) -> bool:
+    return (
+        isinstance(node, ast.Attribute)
+        and node.attr == name
+        and isinstance(node.value, ast.Name)
+        and node.value.id == OX
+    )
+
+
+def is_ox_call(node: ast.AST, name: str) -> bool:
+    return isinstance(node, ast.Call) and is_ox_attr(node.func, name)
+
+
+def list_operator(node: ast.AST) -> str | None:
+    """The name of the `ox.AND`, `ox.OR` or `ox.THEN` marker `node` is."""
+    for name in LIST_OPERATORS:
+        if is_ox_attr(node, name):
+            return name
+    return None
+
+
+def is_method_call(node: ast.AST, name: str) -> bool:
+    return (
+        isinstance(node, ast.Call)
+        and isinstance(node.func, ast.Attribute)
+        and node.func.attr == name
+    )
+
+
+def xonsh_attr(name: str) -> ast.Attribute:
+    return ast.Attribute(ast.Name(XONSH, ast.Load()), name, ast.Load())
+
+
+def xonsh_call(name: str, *args: ast.expr) -> ast.Call:
+    return ast.Call(xonsh_attr(name), list(args), [])
+
+
+def const(value) -> ast.Constant:
+    return ast.Constant(value)
+
+
+class XonshLowering(ast.NodeTransformer):
+    """Rewrites the `ox.*` runtime calls of an oxipy tree into xonsh's `__xonsh__.*` calls."""
+
+    def visit_Name(self, node: ast.Name):
+        if node.id == OX:
+            return ast.copy_location(ast.Name(XONSH, node.ctx), node)
+        return node
+
+    def visit_Attribute(self, node: ast.Attribute):
+        if is_ox_attr(node, "last_status"):
+            env = xonsh_attr("env")
+            status = ast.Subscript(env, const(LAST_STATUS), node.ctx)
+            return ast.copy_location(status, node)
+        return self.generic_visit(node)
+
+    def visit_Call(self, node: ast.Call):
+        if (stages := self.pipeline(node)) is not None:
+            return ast.copy_location(self.command_list(*stages), node)
+        if is_ox_call(node, "path"):
+            args = [self.visit(arg) for arg in node.args]
+            return ast.copy_location(xonsh_call("path_literal", *args), node)
+        if (search := self.search_pattern(node)) is not None:
+            return ast.copy_location(self.path_search(*search, pymode=True), node)
+        return self.generic_visit(node)
+
+    def pipeline(
+        self, node: ast.AST
+    ) -> tuple[str, list[tuple[list[ast.expr], str | None]]] | None:
+        """Splits `ox.cmd(...).pipe(...).out()` into the method and the pipelines of xonsh's
+        command lists, each with the operator of the `ox.AND`, `ox.OR` or `ox.THEN` after it."""
+        if not (
+            isinstance(node, ast.Call)
+            and isinstance(node.func, ast.Attribute)
+            and node.func.attr in SUBPROC_METHODS
+            and not node.args
+        ):
+            return None
+        stages = []
+        call = node.func.value
+        while is_method_call(call, "pipe"):
+            stages.append(call)
+            call = call.func.value
+        if not is_ox_call(call, "cmd"):
+            return None
+        stages.append(call)
+
+        pipelines: list[tuple[list[ast.expr], str | None]] = []
+        cmds: list[ast.expr] = []
+        background = False
+        piped = False
+        for stage in reversed(stages):
+            commands, bg = self.commands(stage)
+            background |= bg
+            for elts, op in commands:
+                if elts:
+                    if piped:
+                        cmds.append(const("|"))
+                    cmds.append(ast.copy_location(ast.List(elts, ast.Load()), stage))
+                piped = op == "|"
+                if not piped and cmds:
+                    pipelines.append((cmds, op))
+                    cmds = []
+        if cmds:
+            pipelines.append((cmds, None))
+        if background and pipelines:
+            pipelines[-1][0].append(const("&"))
+        return node.func.attr, pipelines
+
+    def command_list(
+        self, method: str, pipelines: list[tuple[list[ast.expr], str | None]]
+    ) -> ast.expr:
+        """Runs the pipelines like xonsh runs `a && b || c; d`: each one is a call, joined by
+        `and` and `or`, and `;` starts a tuple whose last item is the result."""
+        if len(pipelines) == 1:
+            return xonsh_call(SUBPROC_METHODS[method], *pipelines[0][0])
+        if method in ("out", "inject"):
+            raise SyntaxError(
+                "xonsh can't capture the output of commands joined by &&, || or ;"
+            )
+        # `$[...]` returns `None`, which would stop an `and`
+        func = SUBPROC_METHODS["hide" if method == "run" else method]
+        items: list[ast.expr] = []
+        current: ast.expr | None = None
+        joined_by: str | None = None
+        for cmds, op in pipelines:
+            call = xonsh_call(func, *cmds)
+            if current is None:
+                current = call
+            elif isinstance(current, ast.BoolOp) and isinstance(
+                current.op, LIST_OPERATORS[joined_by]
+            ):
+                current.values.append(call)
+            else:
+                current = ast.BoolOp(LIST_OPERATORS[joined_by](), [current, call])
+            joined_by = op
+            if op in ("THEN", None):
+                items.append(current)
+                current = None
+        if len(items) == 1:
+            return items[0]
+        return ast.Subscript(ast.Tuple(items, ast.Load()), const(-1), ast.Load())
+
+    def commands(
+        self, call: ast.Call
+    ) -> tuple[list[tuple[list[ast.expr], str | None]], bool]:
+        """The commands of a stage, split on the list operators, each with the name of the
+        operator after it, or `|` for the last one, which pipes to the next stage."""
+        commands: list[tuple[list[ast.expr], str | None]] = []
+        elts: list[ast.expr] = []
+        for arg in call.args:
+            if (op := list_operator(arg)) is not None:
+                commands.append((elts, op))
+                elts = []
+            else:
+                elts.append(self.proc_arg(arg))
+        background = False
+        for keyword in call.keywords:
+            if keyword.arg == "bg":
+                background = True
+            elif keyword.arg in REDIRECT_OPS and isinstance(keyword.value, ast.Dict):
+                op = REDIRECT_OPS[keyword.arg]
+                for key, value in zip(keyword.value.keys, keyword.value.values):
+                    source = key.value if isinstance(key, ast.Constant) else ""
+                    elts.append(const(f"{source}{op}"))
+                    elts.append(self.visit(value))
+        commands.append((elts, "|"))
+        return commands, background
+
+    def proc_arg(self, arg: ast.expr) -> ast.expr:
+        """Lowers a command argument; arguments expanding to many words are starred."""
+        if isinstance(arg, ast.Starred) and is_method_call(arg.value, "invoke"):
+            pattern_call = arg.value.func.value
+            if is_ox_call(pattern_call, "Pattern"):
+                [name] = arg.value.args
+                [pattern] = pattern_call.args
+                # xonsh looks the search function up by its name in the namespace.
+                func = ast.copy_location(ast.Name(name.value, ast.Load()), name)
+                search = xonsh_call(
+                    "pathsearch", func, pattern, const(False), const(False)
+                )
+                return ast.copy_location(ast.Starred(search, ast.Load()), arg)
+        if is_ox_call(arg, "list_of_strs_or_callables"):
+            return ast.copy_location(ast.Starred(self.visit(arg), ast.Load()), arg)
+        if (search := self.search_pattern(arg)) is not None:
+            search = self.path_search(*search, pymode=False)
+            return ast.copy_location(ast.Starred(search, ast.Load()), arg)
+        if (stages := self.pipeline(arg)) is not None and stages[0] == "inject":
+            return ast.copy_location(ast.Starred(self.visit(arg), ast.Load()), arg)
+        if (
+            isinstance(arg, ast.Subscript)
+            and is_ox_attr(arg.value, "env")
+            and isinstance(arg.slice, ast.Constant)
+        ):
+            # xonsh expands `$NAME` in arguments itself.
+            return ast.copy_location(const(f"${arg.slice.value}"), arg)
+        if is_ox_attr(arg, "last_status"):
+            return ast.copy_location(const(f"${LAST_STATUS}"), arg)
+        return self.visit(arg)
+
+    @staticmethod
+    def search_pattern(node: ast.AST) -> tuple[str, ast.expr] | None:
+        """The xonsh search function and pattern of `ox.Pattern(pattern).regex()` or `.glob()`."""
+        if (
+            isinstance(node, ast.Call)
+            and isinstance(node.func, ast.Attribute)
+            and node.func.attr in SEARCH_METHODS
+            and not node.args
+            and is_ox_call(node.func.value, "Pattern")
+        ):
+            [pattern] = node.func.value.args
+            return SEARCH_METHODS[node.func.attr], pattern
+        return None
+
+    def path_search(self, searcher: str, pattern: ast.expr, pymode: bool) -> ast.Call:
+        return xonsh_call(
+            "pathsearch",
+            xonsh_attr(searcher),
+            self.visit(pattern),
+            const(pymode),
+            const(False),
+        )
+
+
+class Lexer:
+    """The parts of xonsh's `Lexer` that callers outside the parser use."""
+
+    def split(self, s: str) -> list[str]:
+        return _Parser(s).split()
+
+
+class Parser:
+    """Mirrors `xonsh.parsers.base.BaseParser`; the PLY table options are accepted and ignored."""
+
+    def __init__(
+        self,
+        yacc_optimize=True,
+        yacc_table="xonsh.parser_table",
+        yacc_debug=False,
+        outputdir=None,
+    ):
+        self.lexer = Lexer()
+
+    def parse(self, s, filename="<code>", mode="exec", debug_level=0):
+        """Returns an abstract syntax tree of xonsh code.
+
+        Raises `SyntaxError` like xonsh's parser does. `debug_level` is accepted and ignored.
+        """
+        tree = _Parser(s, file_name=filename).parse()
+        tree = XonshLowering().visit(tree)
+        if mode == "eval":
+            if len(tree.body) != 1 or not isinstance(tree.body[0], ast.Expr):
+                raise SyntaxError("invalid syntax", (filename, 1, 0, s))
+            tree = ast.Expression(tree.body[0].value)
+        elif mode == "single":
+            tree = ast.Interactive(tree.body)
+        elif mode != "exec":
+            raise ValueError(f"mode must be 'exec', 'eval' or 'single', not {mode!r}")
+        return ast.fix_missing_locations(tree)
+
+
+def subproc_toks(
+    line, mincol=-1, lexer=None, returnline=False, greedy=False, maxcol=None
+):
+    """`xonsh.tools.subproc_toks`, wrapping the subprocess part of `line` in `![...]`."""
+    return _Parser(line).subproc_toks(
+        mincol=mincol, returnline=returnline, greedy=greedy, maxcol=maxcol
+    )
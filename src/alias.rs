@@ -0,0 +1,454 @@
+//! Python callables running as pipeline stages ("callable aliases").
+//!
+//! A stage like `$(ls | @(func) -x)` runs `func` on a blocking thread of the pipeline's runtime.
+//! Its streams are the `cmdgroup` pipes of the stage, wrapped as [`PipeReader`] and
+//! [`PipeWriter`] file objects, so the callable reads what the previous stage writes while the
+//! pipeline is still running.
+//!
+//! The callable is called with as many of `(args, stdin, stdout, stderr)` as it accepts
+//! positionally, where `args` is the list of arguments after the callable. Its return value
+//! becomes the stage's output and exit code:
+//!
+//! - `None` exits with 0, and `True`/`False` with 0/1.
+//! - An `int` is the exit code.
+//! - A `str` or `bytes` is written to stdout and exits with 0.
+//! - A tuple `(stdout, stderr)` or `(stdout, stderr, code)` writes both streams; `None` items
+//!   are skipped.
+//!
+//! `SystemExit` exits with its code. Any other exception prints its traceback to the stage's
+//! stderr and exits with 1.
+
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use cmdgroup::{
+    ExecuteResult, ShellCommand, ShellCommandContext, ShellPipeReader, ShellPipeWriter,
+};
+use futures::future::LocalBoxFuture;
+use pyo3::exceptions::{PyBrokenPipeError, PyOSError, PySystemExit, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyList, PyString, PyTuple};
+
+/// The streams a callable accepts, in order.
+const MAX_POSITIONAL: usize = 4;
+const READ_CHUNK: usize = 8192;
+
+/// A `cmdgroup` command calling a Python callable.
+pub(crate) struct CallableAlias {
+    func: Arc<Py<PyAny>>,
+}
+
+impl CallableAlias {
+    pub(crate) fn new(func: Arc<Py<PyAny>>) -> Self {
+        Self { func }
+    }
+}
+
+impl ShellCommand for CallableAlias {
+    fn execute(&self, context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let func = self.func.clone();
+        let ShellCommandContext {
+            args,
+            stdin,
+            stdout,
+            stderr,
+            ..
+        } = context;
+        Box::pin(async move {
+            let stage = tokio::task::spawn_blocking(move || {
+                Python::with_gil(|py| call_alias(py, &func, args, stdin, stdout, stderr))
+            });
+            ExecuteResult::from_exit_code(stage.await.unwrap_or(1))
+        })
+    }
+}
+
+fn call_alias(
+    py: Python<'_>,
+    func: &Py<PyAny>,
+    args: Vec<String>,
+    stdin: ShellPipeReader,
+    stdout: ShellPipeWriter,
+    stderr: ShellPipeWriter,
+) -> i32 {
+    let streams = (
+        Bound::new(py, PipeReader::new(stdin)),
+        Bound::new(py, PipeWriter::new(stdout)),
+        Bound::new(py, PipeWriter::new(stderr)),
+    );
+    let (Ok(stdin), Ok(stdout), Ok(stderr)) = streams else {
+        return 1;
+    };
+    let result = invoke(py, func.bind(py), args, &stdin, &stdout, &stderr)
+        .and_then(|value| write_result(&value, stdout.get(), stderr.get()));
+    let code = match result {
+        Ok(code) => code,
+        Err(err) => exception_code(py, &err, stderr.get()),
+    };
+    // The next stage sees EOF only once every writer is dropped, even if Python keeps the
+    // stream objects alive.
+    stdin.get().close();
+    stdout.get().close();
+    stderr.get().close();
+    code
+}
+
+fn invoke<'py>(
+    py: Python<'py>,
+    func: &Bound<'py, PyAny>,
+    args: Vec<String>,
+    stdin: &Bound<'py, PipeReader>,
+    stdout: &Bound<'py, PipeWriter>,
+    stderr: &Bound<'py, PipeWriter>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let streams = [
+        PyList::new(py, args)?.into_any(),
+        stdin.clone().into_any(),
+        stdout.clone().into_any(),
+        stderr.clone().into_any(),
+    ];
+    let count = positional_count(func).min(MAX_POSITIONAL);
+    func.call1(PyTuple::new(py, &streams[..count])?)
+}
+
+/// How many positional arguments `func` takes; callables without a signature get all of them.
+fn positional_count(func: &Bound<'_, PyAny>) -> usize {
+    let count = || -> PyResult<usize> {
+        let inspect = func.py().import("inspect")?;
+        let parameter = inspect.getattr("Parameter")?;
+        let positional = [
+            parameter.getattr("POSITIONAL_ONLY")?,
+            parameter.getattr("POSITIONAL_OR_KEYWORD")?,
+        ];
+        let variadic = parameter.getattr("VAR_POSITIONAL")?;
+        let signature = inspect.call_method1("signature", (func,))?;
+        let mut count = 0;
+        for param in signature
+            .getattr("parameters")?
+            .call_method0("values")?
+            .try_iter()?
+        {
+            let kind = param?.getattr("kind")?;
+            if kind.eq(&variadic)? {
+                return Ok(MAX_POSITIONAL);
+            }
+            if positional.iter().any(|p| kind.eq(p).unwrap_or(false)) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    };
+    count().unwrap_or(MAX_POSITIONAL)
+}
+
+fn write_result(
+    value: &Bound<'_, PyAny>,
+    stdout: &PipeWriter,
+    stderr: &PipeWriter,
+) -> PyResult<i32> {
+    let py = value.py();
+    if value.is_none() {
+        return Ok(0);
+    }
+    if let Ok(flag) = value.downcast::<pyo3::types::PyBool>() {
+        return Ok(i32::from(!flag.is_true()));
+    }
+    if let Ok(code) = value.extract::<i32>() {
+        return Ok(code);
+    }
+    if let Ok(items) = value.downcast::<PyTuple>() {
+        if !(2..=3).contains(&items.len()) {
+            return Err(PyValueError::new_err(
+                "a callable alias may return (stdout, stderr) or (stdout, stderr, code)",
+            ));
+        }
+        for (item, stream) in items.iter().zip([stdout, stderr]) {
+            if !item.is_none() {
+                stream.write_bytes(py, &output_bytes(&item)?)?;
+            }
+        }
+        return match items.get_item(2) {
+            Ok(code) if !code.is_none() => code.extract(),
+            _ => Ok(0),
+        };
+    }
+    stdout.write_bytes(py, &output_bytes(value)?)?;
+    Ok(0)
+}
+
+fn output_bytes(value: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+    if let Ok(bytes) = value.downcast::<PyBytes>() {
+        return Ok(bytes.as_bytes().to_vec());
+    }
+    Ok(value.str()?.to_str()?.as_bytes().to_vec())
+}
+
+fn exception_code(py: Python<'_>, err: &PyErr, stderr: &PipeWriter) -> i32 {
+    if err.is_instance_of::<PySystemExit>(py) {
+        let code = err.value(py).getattr("code").ok();
+        return match code {
+            Some(code) if code.is_none() => 0,
+            Some(code) => code.extract().unwrap_or_else(|_| {
+                let _ = stderr.write_bytes(py, format!("{code}\n").as_bytes());
+                1
+            }),
+            None => 1,
+        };
+    }
+    // The next stage stopped reading, like `head`; a process would die of SIGPIPE quietly.
+    if err.is_instance_of::<PyBrokenPipeError>(py) {
+        return 1;
+    }
+    let traceback = py.import("traceback").and_then(|traceback| {
+        let lines = traceback.call_method1(
+            "format_exception",
+            (err.get_type(py), err.value(py), err.traceback(py)),
+        )?;
+        PyString::new(py, "")
+            .call_method1("join", (lines,))?
+            .extract::<String>()
+    });
+    let text = traceback.unwrap_or_else(|_| format!("{err}\n"));
+    let _ = stderr.write_bytes(py, text.as_bytes());
+    1
+}
+
+fn io_error(err: &anyhow::Error) -> PyErr {
+    match err.downcast_ref::<io::Error>() {
+        Some(err) => io::Error::new(err.kind(), err.to_string()).into(),
+        None => PyOSError::new_err(err.to_string()),
+    }
+}
+
+fn closed_error() -> PyErr {
+    PyValueError::new_err("I/O operation on closed pipe")
+}
+
+/// The stdin of a callable stage, a text file object over the `cmdgroup` pipe.
+#[pyclass(module = "oxipy", frozen)]
+pub(crate) struct PipeReader {
+    state: Mutex<ReaderState>,
+}
+
+struct ReaderState {
+    pipe: Option<ShellPipeReader>,
+    buffer: Vec<u8>,
+    eof: bool,
+}
+
+impl ReaderState {
+    /// Reads one more chunk into the buffer; returns false at the end of the stream.
+    fn fill(&mut self) -> PyResult<bool> {
+        let Some(pipe) = self.pipe.as_mut() else {
+            return Err(closed_error());
+        };
+        if self.eof {
+            return Ok(false);
+        }
+        let mut chunk = [0; READ_CHUNK];
+        let size = pipe.read(&mut chunk).map_err(|err| io_error(&err))?;
+        self.buffer.extend_from_slice(&chunk[..size]);
+        self.eof = size == 0;
+        Ok(size > 0)
+    }
+
+    /// Takes `size` bytes, extended to the end of a partially read character.
+    fn take(&mut self, size: usize) -> PyResult<String> {
+        let mut end = size.min(self.buffer.len());
+        while let Err(err) = std::str::from_utf8(&self.buffer[..end]) {
+            if err.error_len().is_some() || end == self.buffer.len() && !self.fill()? {
+                break;
+            }
+            end += 1;
+        }
+        let bytes: Vec<u8> = self.buffer.drain(..end).collect();
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+impl PipeReader {
+    fn new(pipe: ShellPipeReader) -> Self {
+        Self {
+            state: Mutex::new(ReaderState {
+                pipe: Some(pipe),
+                buffer: Vec::new(),
+                eof: false,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReaderState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn close(&self) {
+        self.lock().pipe = None;
+    }
+
+    fn check_open(&self) -> PyResult<()> {
+        match self.lock().pipe {
+            Some(_) => Ok(()),
+            None => Err(closed_error()),
+        }
+    }
+}
+
+#[pymethods]
+impl PipeReader {
+    /// Reads up to `size` bytes of text, or everything until EOF when `size` is negative.
+    #[pyo3(signature = (size = -1))]
+    fn read(&self, py: Python<'_>, size: isize) -> PyResult<String> {
+        py.allow_threads(|| {
+            let mut state = self.lock();
+            let size = usize::try_from(size).unwrap_or(usize::MAX);
+            while state.buffer.len() < size && state.fill()? {}
+            state.take(size)
+        })
+    }
+
+    /// Reads a line, keeping its newline; returns `""` at EOF.
+    fn readline(&self, py: Python<'_>) -> PyResult<String> {
+        py.allow_threads(|| {
+            let mut state = self.lock();
+            let mut searched = 0;
+            loop {
+                if let Some(pos) = state.buffer[searched..].iter().position(|b| *b == b'\n') {
+                    return state.take(searched + pos + 1);
+                }
+                searched = state.buffer.len();
+                if !state.fill()? {
+                    return state.take(searched);
+                }
+            }
+        })
+    }
+
+    fn readlines(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.readline(py)?;
+            if line.is_empty() {
+                return Ok(lines);
+            }
+            lines.push(line);
+        }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<String>> {
+        let line = self.readline(py)?;
+        Ok((!line.is_empty()).then_some(line))
+    }
+
+    fn readable(&self) -> PyResult<bool> {
+        self.check_open()?;
+        Ok(true)
+    }
+
+    fn isatty(&self) -> PyResult<bool> {
+        self.check_open()?;
+        Ok(false)
+    }
+
+    #[pyo3(name = "close")]
+    fn py_close(&self) {
+        self.close();
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.lock().pipe.is_none()
+    }
+}
+
+/// The stdout or stderr of a callable stage, a text file object over the `cmdgroup` pipe.
+///
+/// Accepts `bytes` as well, which are written unchanged.
+#[pyclass(module = "oxipy", frozen)]
+pub(crate) struct PipeWriter {
+    pipe: Mutex<Option<ShellPipeWriter>>,
+}
+
+impl PipeWriter {
+    fn new(pipe: ShellPipeWriter) -> Self {
+        Self {
+            pipe: Mutex::new(Some(pipe)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<ShellPipeWriter>> {
+        self.pipe
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Writes without the GIL, since the reading stage may be another callable.
+    fn write_bytes(&self, py: Python<'_>, bytes: &[u8]) -> PyResult<()> {
+        py.allow_threads(|| match self.lock().as_mut() {
+            Some(pipe) => pipe.write_all(bytes).map_err(|err| io_error(&err)),
+            None => Err(closed_error()),
+        })
+    }
+
+    fn close(&self) {
+        self.lock().take();
+    }
+
+    fn check_open(&self) -> PyResult<()> {
+        match *self.lock() {
+            Some(_) => Ok(()),
+            None => Err(closed_error()),
+        }
+    }
+}
+
+#[pymethods]
+impl PipeWriter {
+    fn write(&self, py: Python<'_>, data: &Bound<'_, PyAny>) -> PyResult<usize> {
+        if let Ok(text) = data.downcast::<PyString>() {
+            let text = text.to_str()?;
+            self.write_bytes(py, text.as_bytes())?;
+            return Ok(text.chars().count());
+        }
+        let bytes = data.downcast::<PyBytes>()?.as_bytes();
+        self.write_bytes(py, bytes)?;
+        Ok(bytes.len())
+    }
+
+    fn writelines(&self, py: Python<'_>, lines: &Bound<'_, PyAny>) -> PyResult<()> {
+        for line in lines.try_iter()? {
+            self.write(py, &line?)?;
+        }
+        Ok(())
+    }
+
+    /// Writes are unbuffered, so this only checks that the stream is open.
+    fn flush(&self) -> PyResult<()> {
+        self.check_open()
+    }
+
+    fn writable(&self) -> PyResult<bool> {
+        self.check_open()?;
+        Ok(true)
+    }
+
+    fn isatty(&self) -> PyResult<bool> {
+        self.check_open()?;
+        Ok(false)
+    }
+
+    #[pyo3(name = "close")]
+    fn py_close(&self) {
+        self.close();
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.lock().is_none()
+    }
+}
@@ -0,0 +1,201 @@
+//! Help for `obj?` and `obj??`.
+//!
+//! The parser lowers them to `ox.help(obj)` and `ox.superhelp(obj)`. Both print what they know
+//! about the object and return it, so `range?.index?` shows help for both. A string naming a
+//! command is described as the shell would run it instead of as a string.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use oxipy_cli::which::CommandKind;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+
+use crate::session::Session;
+
+/// How long `name --help` may run before it is killed, as some commands ignore `--help` and
+/// wait for input or run.
+const HELP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many bytes of `name --help` are shown.
+const HELP_LIMIT: usize = 64 * 1024;
+
+/// `obj?`: prints the signature, docstring and file of `obj`, and returns it.
+#[pyfunction]
+pub(crate) fn help(obj: Bound<'_, PyAny>) -> PyResult<Bound<'_, PyAny>> {
+    show(&obj, false)?;
+    Ok(obj)
+}
+
+/// `obj??`: prints the same as `obj?` with the source code, and returns `obj`.
+#[pyfunction]
+pub(crate) fn superhelp(obj: Bound<'_, PyAny>) -> PyResult<Bound<'_, PyAny>> {
+    show(&obj, true)?;
+    Ok(obj)
+}
+
+fn show(obj: &Bound<'_, PyAny>, source: bool) -> PyResult<()> {
+    let py = obj.py();
+    let command = match obj.downcast::<PyString>() {
+        Ok(name) => command_help(py, name.to_str()?, source)?,
+        Err(_) => None,
+    };
+    let fields = match command {
+        Some(fields) => fields,
+        None => object_help(obj, source),
+    };
+    let text: String = fields
+        .iter()
+        .map(|(label, value)| {
+            if value.contains('\n') {
+                format!("{label}:\n{}\n", value.trim_end())
+            } else {
+                format!("{label}: {value}\n")
+            }
+        })
+        .collect();
+    py.import("sys")?
+        .getattr("stdout")?
+        .call_method1("write", (text,))?;
+    Ok(())
+}
+
+/// Describes `name` if it is a command: what it resolves to and, for external commands, what
+/// `name --help` prints. `cmdgroup` builtins have no `--help`.
+fn command_help(
+    py: Python<'_>,
+    name: &str,
+    source: bool,
+) -> PyResult<Option<Vec<(&'static str, String)>>> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Ok(None);
+    }
+    let state = Session::load(py)?.state(HashMap::new());
+    let mut fields = vec![("Command", name.to_string())];
+    match CommandKind::resolve(name, state.cwd(), state.env_vars()) {
+        CommandKind::NotFound => return Ok(None),
+        CommandKind::Builtin => fields.push(("Kind", "cmdgroup builtin".to_string())),
+        CommandKind::External(path) => {
+            fields.push(("Kind", "external command".to_string()));
+            fields.push(("Path", path.display().to_string()));
+            let (cwd, env) = (state.cwd(), state.env_vars());
+            let help = py.allow_threads(|| help_output(name, &path, cwd, env));
+            fields.push(("Help", help));
+            if let Some(script) = source.then(|| script_source(&path)).flatten() {
+                fields.push(("Source", script));
+            }
+        }
+    }
+    Ok(Some(fields))
+}
+
+/// What `name --help` writes to stdout and stderr, cut at [`HELP_LIMIT`] bytes. A command still
+/// running after [`HELP_TIMEOUT`] is killed and shows nothing.
+fn help_output(name: &str, path: &Path, cwd: &Path, env: &HashMap<String, String>) -> String {
+    let (mut reader, writer) = cmdgroup::pipe();
+    let mut command = Command::new(path);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::arg0(&mut command, name);
+    let child = command
+        .arg("--help")
+        .current_dir(cwd)
+        .env_clear()
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(writer.clone().into_stdio())
+        .stderr(writer.into_stdio())
+        .spawn();
+    // the command holds the writing end of the pipe until it is dropped
+    drop(command);
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => return err.to_string(),
+    };
+    // read while the command runs, as it would block on a full pipe
+    let output = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buffer = [0; 8192];
+        while output.len() < HELP_LIMIT {
+            match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => output.extend_from_slice(&buffer[..read]),
+            }
+        }
+        output
+    });
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if output.is_finished() => {
+                // it closed its output, or wrote more than is shown
+                let _ = child.kill();
+                let _ = child.wait();
+                break;
+            }
+            Ok(None) if started.elapsed() < HELP_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return format!("[stopped after {}s]", HELP_TIMEOUT.as_secs());
+            }
+        }
+    }
+    let mut output = output.join().unwrap_or_default();
+    let truncated = output.len() >= HELP_LIMIT;
+    if truncated {
+        // end at a whole line
+        let end = output[..HELP_LIMIT].iter().rposition(|&byte| byte == b'\n');
+        output.truncate(end.map_or(HELP_LIMIT, |end| end + 1));
+    }
+    let text = String::from_utf8_lossy(&output);
+    if truncated {
+        format!("{}\n[truncated]", text.trim_end())
+    } else {
+        text.into_owned()
+    }
+}
+
+/// The text of a script that starts with a `#!` line, as opposed to a binary.
+fn script_source(path: &Path) -> Option<String> {
+    let text = String::from_utf8(std::fs::read(path).ok()?).ok()?;
+    text.starts_with("#!").then_some(text)
+}
+
+/// Describes a Python object with `inspect`. Whatever it can't find out is left out.
+fn object_help(obj: &Bound<'_, PyAny>, source: bool) -> Vec<(&'static str, String)> {
+    let py = obj.py();
+    let Ok(inspect) = py.import("inspect") else {
+        return Vec::new();
+    };
+    let call = |name: &str| inspect.call_method1(name, (obj,)).ok();
+    let mut fields = Vec::new();
+    if let Ok(name) = obj.get_type().qualname() {
+        fields.push(("Type", name.to_string()));
+    }
+    if let Ok(repr) = obj.repr() {
+        fields.push(("String form", repr.to_string()));
+    }
+    if let Some(signature) = obj.is_callable().then(|| call("signature")).flatten() {
+        fields.push(("Signature", signature.to_string()));
+    }
+    let file = call("getsourcefile").or_else(|| call("getfile"));
+    if let Some(file) = file.filter(|file| !file.is_none()) {
+        let line = call("getsourcelines").and_then(|lines| lines.get_item(1).ok());
+        match line {
+            Some(line) => fields.push(("File", format!("{file}:{line}"))),
+            None => fields.push(("File", file.to_string())),
+        }
+    }
+    if let Some(doc) = call("getdoc").filter(|doc| !doc.is_none()) {
+        fields.push(("Docstring", format!("{doc}\n")));
+    }
+    if let Some(code) = source.then(|| call("getsource")).flatten() {
+        fields.push(("Source", code.to_string()));
+    }
+    fields
+}
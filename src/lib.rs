@@ -1,10 +1,19 @@
 #![allow(clippy::useless_conversion)]
 
+mod alias;
 mod annotate_src;
+mod help;
 mod lexer;
 mod location;
 pub mod parser;
 mod parser_test;
+mod path;
+mod pattern;
+mod procs;
+mod repl;
+mod semantic_tokens;
+mod session;
+mod source;
 
 use oxipy_cli::{Cli, built};
 use pyo3::prelude::*;
@@ -17,17 +26,33 @@ mod oxipy {
 
     #[pymodule_export]
     use parser::PyParser;
+    #[pymodule_export]
+    use procs::{Cmd, CompletedCommand, ListOperator, last_command};
+    #[pymodule_export]
+    use alias::{PipeReader, PipeWriter};
+    #[pymodule_export]
+    use crate::help::{help, superhelp};
+    #[pymodule_export]
+    use path::path;
+    #[pymodule_export]
+    use pattern::Pattern;
 
     #[pymodule_init]
     fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
         let py = m.py();
         m.add("__build__", pyo3_built!(py, built, "git", "build"))?;
+        procs::add_operators(m)?;
         Ok(())
     }
 
     #[pyfunction] // This will be part of the module
     #[pyo3(signature = (*args))]
-    fn cli_main(args: Vec<String>) -> anyhow::Result<()> {
-        Cli::main(args)
+    fn cli_main(py: Python<'_>, args: Vec<String>) -> anyhow::Result<i32> {
+        let cli = Cli::parse_args(args);
+        if cli.no_cache {
+            py.import("oxipy.cache")?.setattr("ENABLED", false)?;
+        }
+        py.import("oxipy.importer")?.call_method0("install")?;
+        py.allow_threads(|| cli.run(Box::new(repl::Repl::default())))
     }
 }
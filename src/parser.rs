@@ -1,12 +1,16 @@
 use crate::lexer::{LexerExt, Token};
+use crate::semantic_tokens::SemanticToken;
+use crate::source;
 use py_ast::ast_module::AstModule;
 use py_ast::to_ast::ToAst;
 use pyo3::prelude::*;
-use pyo3::types::PyString;
+use pyo3::types::{PyBytes, PyString};
+use rayon::prelude::*;
 use ruff_python_ast::ModModule;
 use ruff_python_parser::{ParseError, Parsed};
 use ruff_source_file::{LineIndex, SourceCode};
 use ruff_text_size::Ranged;
+use std::path::Path;
 
 struct PyParseError<'a>(ParseError, &'a str, &'a str);
 
@@ -26,6 +30,57 @@ impl From<PyParseError<'_>> for PyErr {
 
 type ParseResult = PyResult<Parsed<ModModule>>;
 
+fn module_to_ast(py: Python<'_>, src: &str, parsed: Parsed<ModModule>) -> PyResult<PyObject> {
+    let line_index = LineIndex::from_source_text(src);
+    let source_code = SourceCode::new(src, &line_index);
+    let tree = parsed.into_syntax();
+    let module = AstModule::new(py, &source_code)?;
+    tree.to_ast(&module)
+}
+
+/// A file read and parsed without the GIL, for [`PyParser::parse_many`].
+enum FileParse {
+    Parsed(String, Parsed<ModModule>),
+    Invalid(String, ParseError),
+    /// Declares another encoding or isn't valid UTF-8, so decoding needs Python's codecs.
+    Undecoded(Vec<u8>),
+    Unreadable(std::io::Error),
+}
+
+impl FileParse {
+    fn read(path: &Path) -> Self {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) => return Self::Unreadable(err),
+        };
+        let Some(src) = source::decode_utf8(&bytes) else {
+            return Self::Undecoded(bytes);
+        };
+        let src = src.to_string();
+        match ruff_python_parser::parse_module(&src) {
+            Ok(parsed) => Self::Parsed(src, parsed),
+            Err(err) => Self::Invalid(src, err),
+        }
+    }
+
+    fn into_ast(self, py: Python<'_>, path: &Path) -> PyResult<PyObject> {
+        let file = path.display().to_string();
+        match self {
+            Self::Parsed(src, parsed) => module_to_ast(py, &src, parsed),
+            Self::Invalid(src, err) => Err(PyParseError::to_err(err, &file, &src)),
+            Self::Undecoded(bytes) => {
+                let src = source::decode_source(py, &bytes, &file)?;
+                let parser = PyParser {
+                    src: src.unbind(),
+                    file,
+                };
+                parser.parse(py)
+            }
+            Self::Unreadable(err) => Err(source::os_error(err, &file)),
+        }
+    }
+}
+
 #[pyclass(name = "Parser", module = "oxipy")]
 pub struct PyParser {
     src: Py<PyString>,
@@ -44,12 +99,22 @@ impl PyParser {
 
 #[pymethods]
 impl PyParser {
+    /// `src` may be `str` or undecoded `bytes`, which honour the PEP 263 coding cookie. A
+    /// leading BOM is dropped from both.
     #[new]
     #[pyo3(signature = (src, file_name = None))]
-    fn new(src: Bound<'_, PyString>, file_name: Option<&'_ str>) -> PyResult<Self> {
-        let file = file_name.unwrap_or("<code>").to_string();
+    fn new(src: &Bound<'_, PyAny>, file_name: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let file = match file_name {
+            Some(file_name) => source::fspath(file_name)?.display().to_string(),
+            None => "<code>".to_string(),
+        };
+        let src = if let Ok(bytes) = src.downcast::<PyBytes>() {
+            source::decode_source(src.py(), bytes.as_bytes(), &file)?
+        } else {
+            source::strip_bom(src.downcast::<PyString>()?)?
+        };
         Ok(Self {
-            src: src.into(),
+            src: src.unbind(),
             file,
         })
     }
@@ -57,18 +122,44 @@ impl PyParser {
     fn parse(&self, py: Python<'_>) -> PyResult<PyObject> {
         let src = self.src(py)?;
         let parsed = self.parse_module(src)?;
-        let line_index = LineIndex::from_source_text(src);
-        let source_code = SourceCode::new(src, &line_index);
-        let tree = parsed.into_syntax();
-        let module = AstModule::new(py, &source_code)?;
-        tree.to_ast(&module)
+        module_to_ast(py, src, parsed)
     }
 
+    /// Reads and parses `path`, which may be `str`, `bytes` or `os.PathLike`.
     #[staticmethod]
-    fn parse_file(py: Python<'_>, path: &str) -> PyResult<PyObject> {
-        let src = std::fs::read_to_string(path).unwrap();
-        let src = PyString::new(py, &src);
-        PyParser::new(src, Some(path))?.parse(py)
+    fn parse_file(py: Python<'_>, path: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let path = source::fspath(path)?;
+        let src = source::read_source(py, &path)?;
+        let parser = PyParser {
+            src: src.unbind(),
+            file: path.display().to_string(),
+        };
+        parser.parse(py)
+    }
+
+    /// Reads and parses many files on a thread pool with the GIL released. Only the conversion
+    /// to Python AST happens with the GIL held, at the end.
+    ///
+    /// Returns a list in the order of `paths` with the AST of each file, or the exception that
+    /// `parse_file` would raise for it, e.g. `SyntaxError` or `OSError`.
+    #[staticmethod]
+    fn parse_many(py: Python<'_>, paths: &Bound<'_, PyAny>) -> PyResult<Vec<PyObject>> {
+        let paths = paths
+            .try_iter()?
+            .map(|path| source::fspath(&path?))
+            .collect::<PyResult<Vec<_>>>()?;
+        let parsed: Vec<FileParse> =
+            py.allow_threads(|| paths.par_iter().map(|path| FileParse::read(path)).collect());
+        let trees = paths
+            .iter()
+            .zip(parsed)
+            .map(|(path, parsed)| {
+                parsed
+                    .into_ast(py, path)
+                    .unwrap_or_else(|err| err.into_value(py).into_any())
+            })
+            .collect();
+        Ok(trees)
     }
 
     #[pyo3(signature = (tolerant=false))]
@@ -98,6 +189,19 @@ impl PyParser {
         Ok(tokens)
     }
 
+    /// Classifies the source into ranges with highlighting roles like `command`, `flag` or
+    /// `env-var`, based on the parse tree. Incomplete input is classified as far as possible.
+    fn semantic_tokens(&self, py: Python<'_>) -> PyResult<Vec<SemanticToken>> {
+        let src = self.src(py)?;
+        let line_index = LineIndex::from_source_text(src);
+        let source_code = SourceCode::new(src, &line_index);
+        let tokens = oxipy_cli::highlight::semantic_tokens(src)
+            .into_iter()
+            .map(|token| SemanticToken::new(token, &source_code, self.src.clone_ref(py)))
+            .collect();
+        Ok(tokens)
+    }
+
     #[pyo3(signature = (mincol = None, returnline = None, greedy = None, maxcol = None))]
     fn subproc_toks(
         &mut self,
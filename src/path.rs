@@ -0,0 +1,76 @@
+//! Path literals.
+//!
+//! `p"~/src/$PROJECT"` and `pf"{root}/bin"` become `ox.path(...)`, which expands `~` and
+//! variables from the shell state and makes relative paths absolute against its working
+//! directory, so they follow `cd` rather than the directory of the process.
+
+use std::collections::HashMap;
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+
+use cmdgroup::ShellState;
+use pyo3::prelude::*;
+
+use crate::session::Session;
+use crate::source::fspath;
+
+/// `ox.path(text)`: the path of a literal, as an `oxipy.paths.ShellPath`.
+#[pyfunction]
+pub(crate) fn path<'py>(py: Python<'py>, value: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+    let text = fspath(value)?.to_string_lossy().into_owned();
+    let state = Session::load(py)?.state(HashMap::new());
+    let path = resolve(&expand(&text, &state), state.cwd());
+    py.import("oxipy.paths")?
+        .getattr("ShellPath")?
+        .call1((path,))
+}
+
+/// Expands a leading `~` to `$HOME`, and `$NAME` and `${NAME}` to their values. Unknown
+/// variables are left as they are written.
+pub(crate) fn expand(text: &str, state: &ShellState) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    let home = state
+        .get_var("HOME")
+        .or_else(|| state.get_var("USERPROFILE"));
+    match (text.strip_prefix('~'), home) {
+        (Some(after), Some(home))
+            if after.is_empty() || after.starts_with(['/', MAIN_SEPARATOR]) =>
+        {
+            expanded.push_str(home);
+            rest = after;
+        }
+        _ => {}
+    }
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+        let (name, tail) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                after.split_at(end)
+            }
+        };
+        match state.get_var(name).filter(|_| !name.is_empty()) {
+            Some(value) => expanded.push_str(value),
+            None => expanded.push_str(&rest[index..rest.len() - tail.len()]),
+        }
+        rest = tail;
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn resolve(path: &str, cwd: &Path) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        cwd.join(path)
+    }
+}
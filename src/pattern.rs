@@ -0,0 +1,182 @@
+//! Searching for files with regex and glob literals.
+//!
+//! `` `.*\.py` `` becomes `ox.Pattern('.*\\.py').regex()`, `g"**/*.rs"` becomes
+//! `ox.Pattern('**/*.rs').glob()` and `@name"..."` calls the search function registered as
+//! `name`. Searches run in the shell's working directory and return the matches relative to
+//! it, sorted, so a command sees them in the same order every time.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use regex::Regex;
+
+use crate::path::expand;
+use crate::session::Session;
+use crate::source::fspath;
+
+/// Search functions registered with `ox.Pattern.register`.
+static SEARCHES: Mutex<BTreeMap<String, Py<PyAny>>> = Mutex::new(BTreeMap::new());
+
+/// A pattern from a regex or glob literal.
+#[pyclass(module = "oxipy", frozen)]
+pub(crate) struct Pattern {
+    #[pyo3(get)]
+    pattern: String,
+}
+
+impl Pattern {
+    /// The paths whose components each fully match the `/`-separated parts of the pattern.
+    fn regex_search(&self, cwd: &Path) -> PyResult<Vec<String>> {
+        let (mut matches, parts) = match self.pattern.strip_prefix('/') {
+            Some(rest) => (vec![PathBuf::from("/")], rest),
+            None => (vec![PathBuf::new()], self.pattern.as_str()),
+        };
+        for part in parts.split('/').filter(|part| !part.is_empty()) {
+            let regex = Regex::new(&format!("^(?:{part})$"))
+                .map_err(|err| PyValueError::new_err(err.to_string()))?;
+            let mut next = Vec::new();
+            for dir in &matches {
+                let Ok(entries) = std::fs::read_dir(cwd.join(dir)) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    if regex.is_match(&name.to_string_lossy()) {
+                        next.push(dir.join(name));
+                    }
+                }
+            }
+            matches = next;
+        }
+        Ok(sorted(
+            matches.iter().map(|path| path.display().to_string()),
+        ))
+    }
+
+    /// The paths matching the glob, where `**` matches any number of directories. Names
+    /// starting with a dot only match a pattern that starts with one.
+    fn glob_search(pattern: &str, cwd: &Path) -> PyResult<Vec<String>> {
+        let relative = !Path::new(pattern).is_absolute();
+        let full = if relative {
+            format!(
+                "{}/{pattern}",
+                glob::Pattern::escape(&cwd.display().to_string())
+            )
+        } else {
+            pattern.to_string()
+        };
+        let options = glob::MatchOptions {
+            require_literal_leading_dot: true,
+            ..glob::MatchOptions::default()
+        };
+        let paths = glob::glob_with(&full, options)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(sorted(paths.flatten().map(
+            |path| match path.strip_prefix(cwd) {
+                Ok(relative_path) if relative => relative_path.display().to_string(),
+                _ => path.display().to_string(),
+            },
+        )))
+    }
+}
+
+#[pymethods]
+impl Pattern {
+    #[new]
+    fn new(pattern: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            pattern: pattern.str()?.to_str()?.to_string(),
+        })
+    }
+
+    /// `` `pattern` ``: the files matching a regex for each level of the path, like
+    /// `` `src/.*\.rs` ``.
+    fn regex(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        let cwd = Session::load(py)?.state(HashMap::new()).cwd().clone();
+        py.allow_threads(|| self.regex_search(&cwd))
+    }
+
+    /// `g"pattern"`: the files matching a glob, with `~` and variables expanded.
+    fn glob(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        let state = Session::load(py)?.state(HashMap::new());
+        let pattern = expand(&self.pattern, &state);
+        let cwd = state.cwd().clone();
+        py.allow_threads(|| Self::glob_search(&pattern, &cwd))
+    }
+
+    /// `@name"pattern"`: the paths found by the search function registered as `name`.
+    ///
+    /// `glob` and `regex` are always available.
+    fn invoke(&self, py: Python<'_>, name: &str) -> PyResult<Vec<String>> {
+        let search = SEARCHES
+            .lock()
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
+            .get(name)
+            .map(|search| search.clone_ref(py));
+        match (search, name) {
+            (Some(search), _) => {
+                let mut paths = Vec::new();
+                for path in search.bind(py).call1((&self.pattern,))?.try_iter()? {
+                    paths.push(fspath(&path?)?.display().to_string());
+                }
+                Ok(sorted(paths))
+            }
+            (None, "glob") => self.glob(py),
+            (None, "regex") => self.regex(py),
+            (None, _) => Err(PyKeyError::new_err(format!(
+                "no search function named {name:?}, add one with `ox.Pattern.register`"
+            ))),
+        }
+    }
+
+    /// Makes `@name"pattern"` call `func(pattern)`, which returns the paths it found.
+    #[staticmethod]
+    fn register(name: String, func: Bound<'_, PyAny>) -> PyResult<Bound<'_, PyAny>> {
+        let is_identifier = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(PyValueError::new_err(format!(
+                "search function names are identifiers, got {name:?}"
+            )));
+        }
+        SEARCHES
+            .lock()
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
+            .insert(name, func.clone().unbind());
+        Ok(func)
+    }
+
+    /// Removes the search function registered as `name` and returns it.
+    #[staticmethod]
+    fn unregister(name: &str) -> PyResult<Py<PyAny>> {
+        SEARCHES
+            .lock()
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
+            .remove(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no search function named {name:?}")))
+    }
+
+    /// Removes every registered search function.
+    #[staticmethod]
+    fn unregister_all() -> PyResult<()> {
+        SEARCHES
+            .lock()
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
+            .clear();
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Pattern({:?})", self.pattern)
+    }
+}
+
+fn sorted(paths: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut paths: Vec<String> = paths.into_iter().collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
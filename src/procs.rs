@@ -0,0 +1,526 @@
+//! Running the pipelines that subprocess syntax is lowered to.
+//!
+//! `$(ls -l | @(func) x > out.txt)` becomes
+//! `ox.cmd('ls', '-l').pipe(ox.list_of_strs_or_callables(func), 'x', writes={'': 'out.txt'}).out()`.
+//! [`Cmd`] collects the stages and builds a `cmdgroup` list from them, which runs on a
+//! single-threaded runtime with the GIL released. The words are already evaluated by Python, so
+//! they are passed to `cmdgroup` as quoted strings and never expanded again.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use cmdgroup::parser::{Redirect, RedirectOp, RedirectOpInput, RedirectOpOutput, SequentialList};
+use cmdgroup::{ExecuteResult, ShellCommand, ShellPipeReader, ShellPipeWriter};
+use oxipy_cli::CommandRun;
+use oxipy_cli::command_list::{self, Operator};
+use pyo3::exceptions::{PyRuntimeError, PySystemExit, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList, PyString, PyTuple};
+
+use crate::alias::CallableAlias;
+use crate::session::Session;
+
+#[derive(Clone)]
+enum Arg {
+    Word(String),
+    Callable(Arc<Py<PyAny>>),
+    /// `&&`, `||` or `;` between the commands of a stage, like `$[make && make install]`.
+    Operator(Operator),
+}
+
+/// `ox.AND`, `ox.OR` and `ox.THEN`, which the parser lowers `&&`, `||` and `;` to. Only these
+/// separate commands: the same text in a quoted word or an `@(...)` value is an argument.
+#[pyclass(name = "ListOperator", module = "oxipy", frozen)]
+pub(crate) struct ListOperator(Operator);
+
+#[pymethods]
+impl ListOperator {
+    fn __repr__(&self) -> &'static str {
+        match self.0 {
+            Operator::And => "ox.AND",
+            Operator::Or => "ox.OR",
+            Operator::Then | Operator::Pipe => "ox.THEN",
+        }
+    }
+}
+
+/// Adds `AND`, `OR` and `THEN` to the module.
+pub(crate) fn add_operators(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    for (name, op) in [
+        ("AND", Operator::And),
+        ("OR", Operator::Or),
+        ("THEN", Operator::Then),
+    ] {
+        module.add(name, ListOperator(op))?;
+    }
+    Ok(())
+}
+
+/// The arguments of one `ox.cmd(...)` or `.pipe(...)` call.
+#[derive(Clone)]
+struct Stage {
+    args: Vec<Arg>,
+    redirects: Vec<Redirect>,
+}
+
+impl Stage {
+    fn new(
+        args: &Bound<'_, PyTuple>,
+        writes: Option<&Bound<'_, PyDict>>,
+        appends: Option<&Bound<'_, PyDict>>,
+        reads: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let mut words = Vec::new();
+        for arg in args {
+            match arg.downcast::<ListOperator>() {
+                Ok(op) => words.push(Arg::Operator(op.get().0)),
+                Err(_) => push_args(&arg, &mut words)?,
+            }
+        }
+        let mut redirects = Vec::new();
+        for (targets, op) in [
+            (writes, RedirectOp::Output(RedirectOpOutput::Overwrite)),
+            (appends, RedirectOp::Output(RedirectOpOutput::Append)),
+            (reads, RedirectOp::Input(RedirectOpInput::Redirect)),
+        ] {
+            for (source, target) in targets.into_iter().flatten() {
+                redirects.push(redirect(&source, &target, op.clone())?);
+            }
+        }
+        Ok(Self {
+            args: words,
+            redirects,
+        })
+    }
+}
+
+/// Flattens an argument: lists from `@(...)` and `@$(...)` expand to many words.
+fn push_args(value: &Bound<'_, PyAny>, args: &mut Vec<Arg>) -> PyResult<()> {
+    if let Ok(text) = value.downcast::<PyString>() {
+        args.push(Arg::Word(text.to_str()?.to_string()));
+    } else if let Ok(bytes) = value.downcast::<PyBytes>() {
+        args.push(Arg::Word(
+            String::from_utf8_lossy(bytes.as_bytes()).into_owned(),
+        ));
+    } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        for item in value.try_iter()? {
+            push_args(&item?, args)?;
+        }
+    } else if value.hasattr("__fspath__")? {
+        args.push(Arg::Word(
+            crate::source::fspath(value)?.display().to_string(),
+        ));
+    } else if value.is_callable() {
+        args.push(Arg::Callable(Arc::new(value.clone().unbind())));
+    } else {
+        args.push(Arg::Word(value.str()?.to_str()?.to_string()));
+    }
+    Ok(())
+}
+
+/// Converts one `{source: target}` item of the `writes`, `appends` or `reads` keywords.
+fn redirect(
+    source: &Bound<'_, PyAny>,
+    target: &Bound<'_, PyAny>,
+    op: RedirectOp,
+) -> PyResult<Redirect> {
+    let source = source.str()?.to_str()?.to_string();
+    let target = if target.hasattr("__fspath__")? {
+        crate::source::fspath(target)?.display().to_string()
+    } else {
+        target.str()?.to_str()?.to_string()
+    };
+    command_list::redirect(&source, &target, op)
+        .map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// The result of the last foreground pipeline, read by `ox.last` and `$?`.
+static LAST: Mutex<Option<Py<CompletedCommand>>> = Mutex::new(None);
+
+/// A pipeline ready to run: the `cmdgroup` list, the callables its commands refer to and the
+/// session they run in.
+struct Job {
+    list: SequentialList,
+    aliases: Vec<(String, Arc<Py<PyAny>>)>,
+    session: Session,
+}
+
+/// How a [`Job`] finished.
+struct Status {
+    returncode: i32,
+    /// Whether `exit` ended the list, which then ends the script running it.
+    exited: bool,
+    /// The exit code of each command in the last pipeline, like `PIPESTATUS`.
+    pipestatus: Vec<i32>,
+    /// Seconds since the epoch when the job started.
+    started: f64,
+    /// Seconds the job ran for.
+    duration: f64,
+    /// The environment when the job ended, with `$PWD` following `cd`.
+    env_vars: HashMap<String, String>,
+}
+
+impl Job {
+    fn run(
+        self,
+        stdin: ShellPipeReader,
+        stdout: ShellPipeWriter,
+        stderr: ShellPipeWriter,
+    ) -> anyhow::Result<Status> {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_secs_f64());
+        let timer = Instant::now();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let commands = self
+            .aliases
+            .into_iter()
+            .map(|(name, func)| {
+                (
+                    name,
+                    Rc::new(CallableAlias::new(func)) as Rc<dyn ShellCommand>,
+                )
+            })
+            .collect();
+        let mut state = self.session.state(commands);
+        let local = tokio::task::LocalSet::new();
+        let result = local.block_on(
+            &runtime,
+            cmdgroup::execute_with_state(self.list, &mut state, stdin, stdout, stderr),
+        );
+        Ok(Status {
+            returncode: result.exit_code(),
+            exited: matches!(result, ExecuteResult::Exit(..)),
+            pipestatus: state.pipe_status(),
+            started,
+            duration: timer.elapsed().as_secs_f64(),
+            env_vars: state.env_vars().clone(),
+        })
+    }
+}
+
+/// Builds a `cmdgroup` list from the stages, naming the callables it runs.
+struct ListBuilder {
+    list: command_list::ListBuilder,
+    aliases: Vec<(String, Arc<Py<PyAny>>)>,
+    argv: Vec<Vec<String>>,
+}
+
+impl ListBuilder {
+    fn build(py: Python<'_>, stages: &[Stage]) -> PyResult<(Job, Vec<Vec<String>>)> {
+        let mut builder = Self {
+            list: command_list::ListBuilder::default(),
+            aliases: Vec::new(),
+            argv: Vec::new(),
+        };
+        for (index, stage) in stages.iter().enumerate() {
+            let mut words: Vec<Arg> = Vec::new();
+            for arg in &stage.args {
+                match arg {
+                    Arg::Operator(op) => {
+                        builder.push(py, std::mem::take(&mut words), &[], Some(*op))?;
+                    }
+                    _ => words.push(arg.clone()),
+                }
+            }
+            let op = (index + 1 < stages.len()).then_some(Operator::Pipe);
+            builder.push(py, words, &stage.redirects, op)?;
+        }
+        let Self {
+            list,
+            aliases,
+            argv,
+        } = builder;
+        let job = Job {
+            list: list
+                .finish()
+                .map_err(|err| PyValueError::new_err(err.to_string()))?,
+            aliases,
+            session: Session::load(py)?,
+        };
+        Ok((job, argv))
+    }
+
+    fn push(
+        &mut self,
+        py: Python<'_>,
+        args: Vec<Arg>,
+        redirects: &[Redirect],
+        op: Option<Operator>,
+    ) -> PyResult<()> {
+        let mut words = Vec::with_capacity(args.len());
+        let mut argv = Vec::with_capacity(args.len());
+        for (position, arg) in args.into_iter().enumerate() {
+            match arg {
+                Arg::Word(word) => {
+                    words.push(word.clone());
+                    argv.push(word);
+                }
+                Arg::Callable(func) if position == 0 => {
+                    let name = format!("@callable{}", self.aliases.len());
+                    argv.push(func.bind(py).repr()?.to_string());
+                    words.push(name.clone());
+                    self.aliases.push((name, func));
+                }
+                Arg::Callable(func) => {
+                    return Err(PyTypeError::new_err(format!(
+                        "only the first word of a command may be a callable, got {}",
+                        func.bind(py).repr()?
+                    )));
+                }
+                // split off by `build`
+                Arg::Operator(_) => {}
+            }
+        }
+        self.list
+            .push(&words, redirects, op)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        if !words.is_empty() {
+            self.argv.push(argv);
+        }
+        Ok(())
+    }
+}
+
+/// Reads a pipe to its end on another thread, so the pipeline never blocks on a full pipe.
+fn capture(reader: ShellPipeReader) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = reader.pipe_to(&mut output);
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
+fn join(handle: JoinHandle<String>) -> String {
+    handle.join().unwrap_or_default()
+}
+
+/// A subprocess pipeline, built by `ox.cmd(...)` and extended with `.pipe(...)`.
+#[pyclass(name = "Cmd", module = "oxipy", frozen)]
+pub(crate) struct Cmd {
+    stages: Vec<Stage>,
+    background: bool,
+}
+
+impl Cmd {
+    fn job(&self, py: Python<'_>) -> PyResult<(Job, Vec<Vec<String>>)> {
+        ListBuilder::build(py, &self.stages)
+    }
+
+    /// Runs with the terminal's streams, or in the background when it ends with `&`.
+    ///
+    /// The variables and directory the commands change are kept for the next ones, like in a
+    /// shell. Background jobs return at once with a zero `returncode`, change nothing and are
+    /// not recorded as `ox.last`. `exit` raises `SystemExit` with its status, so it ends the
+    /// script or entry like it ends a shell.
+    fn execute(&self, py: Python<'_>) -> PyResult<Py<CompletedCommand>> {
+        let (job, args) = self.job(py)?;
+        let session = job.session.clone();
+        let run = move || {
+            job.run(
+                ShellPipeReader::stdin(),
+                ShellPipeWriter::stdout(),
+                ShellPipeWriter::stderr(),
+            )
+        };
+        if self.background {
+            std::thread::spawn(run);
+            return Py::new(
+                py,
+                CompletedCommand {
+                    args,
+                    returncode: 0,
+                    pipestatus: Vec::new(),
+                    output: None,
+                    errors: None,
+                    started: 0.0,
+                    duration: 0.0,
+                },
+            );
+        }
+        let status = py
+            .allow_threads(run)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        session.store(py, &status.env_vars)?;
+        let exited = status.exited.then_some(status.returncode);
+        let done = CompletedCommand::new(args, status, None, None).record(py)?;
+        match exited {
+            Some(code) => Err(PySystemExit::new_err(code)),
+            None => Ok(done),
+        }
+    }
+
+    /// Runs in the foreground with stdout, and optionally stderr, captured. Like command
+    /// substitution in a shell, changes to variables and the directory are not kept.
+    fn captured(&self, py: Python<'_>, capture_stderr: bool) -> PyResult<Py<CompletedCommand>> {
+        let (job, args) = self.job(py)?;
+        let (out_reader, out_writer) = cmdgroup::pipe();
+        let output = capture(out_reader);
+        let (errors, err_writer) = if capture_stderr {
+            let (reader, writer) = cmdgroup::pipe();
+            (Some(capture(reader)), writer)
+        } else {
+            (None, ShellPipeWriter::stderr())
+        };
+        let status = py
+            .allow_threads(|| job.run(ShellPipeReader::stdin(), out_writer, err_writer))
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        let output = py.allow_threads(|| join(output));
+        let errors = py.allow_threads(|| errors.map(join));
+        CompletedCommand::new(args, status, Some(output), errors).record(py)
+    }
+}
+
+#[pymethods]
+impl Cmd {
+    #[new]
+    #[pyo3(signature = (*args, writes = None, appends = None, reads = None, bg = false))]
+    fn new(
+        args: &Bound<'_, PyTuple>,
+        writes: Option<&Bound<'_, PyDict>>,
+        appends: Option<&Bound<'_, PyDict>>,
+        reads: Option<&Bound<'_, PyDict>>,
+        bg: bool,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            stages: vec![Stage::new(args, writes, appends, reads)?],
+            background: bg,
+        })
+    }
+
+    /// Adds a stage reading the output of the previous one.
+    #[pyo3(signature = (*args, writes = None, appends = None, reads = None, bg = false))]
+    fn pipe(
+        &self,
+        args: &Bound<'_, PyTuple>,
+        writes: Option<&Bound<'_, PyDict>>,
+        appends: Option<&Bound<'_, PyDict>>,
+        reads: Option<&Bound<'_, PyDict>>,
+        bg: bool,
+    ) -> PyResult<Self> {
+        let mut stages = self.stages.clone();
+        stages.push(Stage::new(args, writes, appends, reads)?);
+        Ok(Self {
+            stages,
+            background: self.background || bg,
+        })
+    }
+
+    /// `$(...)`: the captured stdout.
+    fn out(&self, py: Python<'_>) -> PyResult<String> {
+        let done = self.captured(py, false)?;
+        Ok(done.get().output.clone().unwrap_or_default())
+    }
+
+    /// `!(...)`: the result with stdout and stderr captured.
+    fn obj(&self, py: Python<'_>) -> PyResult<Py<CompletedCommand>> {
+        self.captured(py, true)
+    }
+
+    /// `$[...]`: runs with the terminal's streams.
+    fn run(&self, py: Python<'_>) -> PyResult<()> {
+        self.execute(py)?;
+        Ok(())
+    }
+
+    /// `![...]` and bare commands: runs with the terminal's streams and returns the result.
+    fn hide(&self, py: Python<'_>) -> PyResult<Py<CompletedCommand>> {
+        self.execute(py)
+    }
+
+    /// `@$(...)`: the captured stdout split into words.
+    fn inject(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        let output = self.out(py)?;
+        Ok(output.split_whitespace().map(str::to_string).collect())
+    }
+}
+
+/// The result of a finished pipeline.
+#[pyclass(module = "oxipy", frozen, get_all)]
+pub(crate) struct CompletedCommand {
+    /// The words of each command; callables appear as their `repr`.
+    args: Vec<Vec<String>>,
+    returncode: i32,
+    /// The exit code of each command in the last pipeline that ran, like `PIPESTATUS`.
+    pipestatus: Vec<i32>,
+    /// Captured stdout, or `None` when it went to the terminal.
+    output: Option<String>,
+    errors: Option<String>,
+    /// Seconds since the epoch when the pipeline started.
+    started: f64,
+    /// Seconds the pipeline ran for.
+    duration: f64,
+}
+
+impl CompletedCommand {
+    fn new(
+        args: Vec<Vec<String>>,
+        status: Status,
+        output: Option<String>,
+        errors: Option<String>,
+    ) -> Self {
+        Self {
+            args,
+            returncode: status.returncode,
+            pipestatus: status.pipestatus,
+            output,
+            errors,
+            started: status.started,
+            duration: status.duration,
+        }
+    }
+
+    /// The result of commands the REPL ran without Python, so `ox.last` and `$?` see them.
+    pub(crate) fn from_run(run: &CommandRun) -> Self {
+        Self {
+            args: run.args.clone(),
+            returncode: run.returncode,
+            pipestatus: run.pipestatus.clone(),
+            output: None,
+            errors: None,
+            started: run.started,
+            duration: run.duration,
+        }
+    }
+
+    /// Stores the result as `ox.last`.
+    pub(crate) fn record(self, py: Python<'_>) -> PyResult<Py<Self>> {
+        let done = Py::new(py, self)?;
+        *LAST
+            .lock()
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))? = Some(done.clone_ref(py));
+        Ok(done)
+    }
+}
+
+/// The result of the last foreground pipeline, or `None` before any has run.
+#[pyfunction]
+pub(crate) fn last_command(py: Python<'_>) -> PyResult<Option<Py<CompletedCommand>>> {
+    let last = LAST
+        .lock()
+        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+    Ok(last.as_ref().map(|done| done.clone_ref(py)))
+}
+
+#[pymethods]
+impl CompletedCommand {
+    fn __bool__(&self) -> bool {
+        self.returncode == 0
+    }
+
+    fn __str__(&self) -> &str {
+        self.output.as_deref().unwrap_or_default()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CompletedCommand(args={:?}, returncode={}, pipestatus={:?})",
+            self.args, self.returncode, self.pipestatus
+        )
+    }
+}
@@ -0,0 +1,135 @@
+//! The interpreter behind the CLI's REPL.
+//!
+//! The CLI runs entries of plain commands itself and hands everything else to [`Repl`], which
+//! runs it with `oxipy.repl` in a namespace kept for the whole session. The shell state of the
+//! CLI is copied to `ox.env` before the code runs and back afterwards, so `cd` in either one
+//! is seen by the other.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use cmdgroup::ShellState;
+use oxipy_cli::{CommandRun, Interpreter, Outcome};
+use pyo3::exceptions::PySystemExit;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule};
+
+use crate::procs::CompletedCommand;
+use crate::session::Session;
+
+#[derive(Default)]
+pub(crate) struct Repl {
+    /// The globals of the session, made on the first entry.
+    namespace: Option<Py<PyDict>>,
+}
+
+impl Repl {
+    fn namespace<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        if let Some(namespace) = &self.namespace {
+            return Ok(namespace.bind(py).clone());
+        }
+        let namespace = py
+            .import("oxipy.repl")?
+            .call_method0("namespace")?
+            .downcast_into::<PyDict>()?;
+        self.namespace = Some(namespace.clone().unbind());
+        Ok(namespace)
+    }
+
+    /// Calls a function of `oxipy.repl` with the session's namespace, with `ox.env` and `state`
+    /// synchronised around it.
+    fn call<'py>(
+        &mut self,
+        py: Python<'py>,
+        state: &mut ShellState,
+        call: impl FnOnce(&Bound<'py, PyModule>, Bound<'py, PyDict>) -> PyResult<Bound<'py, PyAny>>,
+    ) -> PyResult<Outcome> {
+        let namespace = self.namespace(py)?;
+        Session::load(py)?.store(py, state.env_vars())?;
+        let repl = py.import("oxipy.repl")?;
+        let result = call(&repl, namespace);
+        Session::load(py)?.apply(state);
+        match result {
+            Ok(status) => Ok(Outcome::Status(status.extract()?)),
+            Err(err) if err.is_instance_of::<PySystemExit>(py) => Ok(Outcome::Exit(
+                repl.call_method1("exit_code", (err.value(py),))?
+                    .extract()?,
+            )),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Interpreter for Repl {
+    fn run(&mut self, source: &str, filename: &str, state: &mut ShellState) -> Outcome {
+        Python::with_gil(|py| {
+            let result = self.call(py, state, |repl, namespace| {
+                repl.call_method1("run", (source, namespace, filename))
+            });
+            reported(py, result)
+        })
+    }
+
+    fn run_script(&mut self, path: &Path, args: &[String], state: &mut ShellState) -> Outcome {
+        Python::with_gil(|py| {
+            let result = self.call(py, state, |repl, namespace| {
+                repl.call_method1("run_script", (path, args, namespace))
+            });
+            reported(py, result)
+        })
+    }
+
+    fn source(&mut self, path: &Path, state: &mut ShellState) -> Outcome {
+        Python::with_gil(|py| {
+            let result = self.call(py, state, |repl, namespace| {
+                repl.call_method1("source", (path, namespace))
+            });
+            reported(py, result)
+        })
+    }
+
+    fn record(&mut self, run: &CommandRun) {
+        Python::with_gil(|py| {
+            if let Err(err) = CompletedCommand::from_run(run).record(py) {
+                err.print(py);
+            }
+        });
+    }
+
+    fn complete(&mut self, prefix: &str) -> Vec<String> {
+        Python::with_gil(|py| {
+            let namespace = self.namespace(py)?;
+            py.import("oxipy.repl")?
+                .call_method1("complete", (prefix, namespace))?
+                .extract()
+        })
+        // an error would be printed over the line being edited
+        .unwrap_or_default()
+    }
+
+    fn prompt_fields(
+        &mut self,
+        names: &[String],
+        state: &mut ShellState,
+    ) -> HashMap<String, String> {
+        Python::with_gil(|py| {
+            let fields = Session::load(py)
+                .and_then(|session| session.store(py, state.env_vars()))
+                .and_then(|()| py.import("oxipy.repl"))
+                .and_then(|repl| repl.call_method1("prompt_fields", (names.to_vec(),)))
+                .and_then(|fields| fields.extract());
+            fields.unwrap_or_else(|err| {
+                err.print(py);
+                HashMap::new()
+            })
+        })
+    }
+}
+
+/// Prints an error of the interpreter itself, rather than of the code it ran.
+fn reported(py: Python<'_>, result: PyResult<Outcome>) -> Outcome {
+    result.unwrap_or_else(|err| {
+        err.print(py);
+        Outcome::Status(1)
+    })
+}
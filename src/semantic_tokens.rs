@@ -0,0 +1,86 @@
+use crate::location::HasSrcLocation;
+use oxipy_cli::highlight;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use ruff_source_file::{SourceCode, SourceLocation};
+use ruff_text_size::{Ranged, TextRange};
+use std::ops::Range;
+
+/// A source range classified with a highlighting role like `command` or `env-var`.
+#[derive(Debug)]
+#[pyclass]
+pub(crate) struct SemanticToken {
+    role: highlight::SemanticRole,
+    range: TextRange,
+    location: Range<SourceLocation>,
+    src: Py<PyString>,
+}
+
+impl SemanticToken {
+    pub fn new(token: highlight::SemanticToken, source: &SourceCode, src: Py<PyString>) -> Self {
+        let location = {
+            let start = source.source_location(token.start());
+            let end = source.source_location(token.end());
+            start..end
+        };
+        Self {
+            role: token.role,
+            range: token.range,
+            location,
+            src,
+        }
+    }
+}
+
+impl HasSrcLocation for SemanticToken {
+    fn start(&self) -> SourceLocation {
+        self.location.start.clone()
+    }
+
+    fn end(&self) -> SourceLocation {
+        self.location.end.clone()
+    }
+}
+
+#[pymethods]
+impl SemanticToken {
+    #[getter]
+    fn get_role(&self) -> &'static str {
+        self.role.as_str()
+    }
+    #[getter]
+    fn get_start(&self) -> usize {
+        self.range.start().to_usize()
+    }
+    #[getter]
+    fn get_end(&self) -> usize {
+        self.range.end().to_usize()
+    }
+    #[getter]
+    fn get_lineno(&self) -> usize {
+        self.lineno()
+    }
+    #[getter]
+    fn get_end_lineno(&self) -> usize {
+        self.end_lineno()
+    }
+    #[getter]
+    fn get_value(&self, py: Python<'_>) -> PyResult<String> {
+        let src = self.src.to_str(py)?;
+        Ok(src[self.range].to_string())
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "SemanticToken({:?}, {}..{})",
+            self.role.as_str(),
+            self.get_start(),
+            self.get_end()
+        )
+    }
+}
+
+impl Ranged for SemanticToken {
+    fn range(&self) -> TextRange {
+        self.range
+    }
+}
@@ -0,0 +1,80 @@
+//! The shell state that commands and paths share.
+//!
+//! `cmdgroup`'s [`ShellState`] can't outlive a pipeline, because pipelines may run on other
+//! threads, so it is rebuilt for each one from `ox.env`. Its working directory is `$PWD`, and
+//! the variables a pipeline changes (`cd`, `export`) are written back to `ox.env` when it ends,
+//! so the next one starts where it left off.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use cmdgroup::{EnvChange, KillSignal, ShellCommand, ShellState};
+use pyo3::prelude::*;
+
+/// A snapshot of `ox.env`, enough to build a [`ShellState`] away from the GIL.
+#[derive(Clone)]
+pub(crate) struct Session {
+    env_vars: HashMap<String, String>,
+    cwd: PathBuf,
+}
+
+impl Session {
+    /// Reads `ox.env` with every value stringified, as child processes see it.
+    pub(crate) fn load(py: Python<'_>) -> PyResult<Self> {
+        let env_vars: HashMap<String, String> = py
+            .import("oxipy.ox")?
+            .getattr("env")?
+            .call_method0("env_vars")?
+            .extract()?;
+        let cwd = match env_vars.get("PWD").map(PathBuf::from) {
+            Some(cwd) if cwd.is_absolute() && cwd.is_dir() => cwd,
+            _ => std::env::current_dir()?,
+        };
+        Ok(Self { env_vars, cwd })
+    }
+
+    pub(crate) fn state(&self, commands: HashMap<String, Rc<dyn ShellCommand>>) -> ShellState {
+        ShellState::new(
+            self.env_vars.clone(),
+            &self.cwd,
+            commands,
+            KillSignal::default(),
+        )
+    }
+
+    /// Makes `state` match this snapshot, keeping its shell variables.
+    pub(crate) fn apply(&self, state: &mut ShellState) {
+        let removed: Vec<String> = state
+            .env_vars()
+            .keys()
+            .filter(|name| !self.env_vars.contains_key(*name))
+            .cloned()
+            .collect();
+        for name in removed {
+            state.apply_change(&EnvChange::UnsetVar(name));
+        }
+        for (name, value) in &self.env_vars {
+            if state.env_vars().get(name) != Some(value) {
+                state.apply_env_var(name, value);
+            }
+        }
+        state.set_cwd(&self.cwd);
+    }
+
+    /// Writes the variables that differ in `env_vars` back to `ox.env`.
+    pub(crate) fn store(&self, py: Python<'_>, env_vars: &HashMap<String, String>) -> PyResult<()> {
+        let env = py.import("oxipy.ox")?.getattr("env")?;
+        for (name, value) in env_vars {
+            if self.env_vars.get(name) != Some(value) {
+                env.set_item(name, value)?;
+            }
+        }
+        for name in self.env_vars.keys() {
+            if !env_vars.contains_key(name) {
+                env.del_item(name)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Reading and decoding of source files, following PEP 263 coding cookies and the UTF-8 BOM.
+
+use pyo3::exceptions::{PyLookupError, PyOSError, PySyntaxError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+use std::path::{Path, PathBuf};
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const DEFAULT_ENCODING: &str = "utf-8";
+
+/// Converts a `str`, `bytes` or `os.PathLike` object to a path, like `os.fsdecode`.
+pub(crate) fn fspath(obj: &Bound<'_, PyAny>) -> PyResult<PathBuf> {
+    let os = obj.py().import("os")?;
+    os.getattr("fsdecode")?.call1((obj,))?.extract()
+}
+
+/// Reads the file at `path` and decodes it to a string.
+pub(crate) fn read_source<'py>(py: Python<'py>, path: &Path) -> PyResult<Bound<'py, PyString>> {
+    let filename = path.display().to_string();
+    let bytes = std::fs::read(path).map_err(|err| os_error(err, &filename))?;
+    decode_source(py, &bytes, &filename)
+}
+
+/// Decodes `source` using its coding cookie, defaulting to UTF-8. A leading BOM is stripped.
+///
+/// Raises `SyntaxError` for unknown or conflicting encodings and `UnicodeDecodeError` when the
+/// bytes don't match the declared encoding.
+pub(crate) fn decode_source<'py>(
+    py: Python<'py>,
+    source: &[u8],
+    filename: &str,
+) -> PyResult<Bound<'py, PyString>> {
+    let (body, has_bom) = match source.strip_prefix(UTF8_BOM) {
+        Some(body) => (body, true),
+        None => (source, false),
+    };
+    let (encoding, lineno) = match coding_cookie(body) {
+        Some((encoding, lineno)) => (encoding, lineno),
+        None => (DEFAULT_ENCODING.to_string(), 1),
+    };
+    if has_bom && !is_utf8(&encoding) {
+        return Err(syntax_error(
+            format!("encoding problem: {encoding} with BOM"),
+            filename,
+            lineno,
+        ));
+    }
+
+    let bytes = PyBytes::new(py, body);
+    PyString::from_object(&bytes, &encoding, "strict").map_err(|err| {
+        if err.is_instance_of::<PyLookupError>(py) {
+            syntax_error(format!("unknown encoding: {encoding}"), filename, lineno)
+        } else {
+            err
+        }
+    })
+}
+
+/// Returns `source` as a string when it is valid UTF-8 and doesn't declare another encoding.
+///
+/// This needs no Python codecs, so it can run without the GIL. Anything else goes through
+/// [`decode_source`] for the proper decoding and errors.
+pub(crate) fn decode_utf8(source: &[u8]) -> Option<&str> {
+    let body = source.strip_prefix(UTF8_BOM).unwrap_or(source);
+    match coding_cookie(body) {
+        Some((encoding, _)) if !is_utf8(&encoding) => None,
+        _ => std::str::from_utf8(body).ok(),
+    }
+}
+
+/// Returns `source` without a leading BOM, which a `str` keeps when its file was read as text.
+pub(crate) fn strip_bom<'py>(source: &Bound<'py, PyString>) -> PyResult<Bound<'py, PyString>> {
+    Ok(match source.to_str()?.strip_prefix('\u{feff}') {
+        Some(body) => PyString::new(source.py(), body),
+        None => source.clone(),
+    })
+}
+
+/// Finds the coding cookie on the first two lines and returns it with its line number.
+///
+/// The second line is only considered when the first one is blank or a comment.
+fn coding_cookie(source: &[u8]) -> Option<(String, usize)> {
+    let mut lines = source.split_inclusive(|&b| b == b'\n');
+    let first = lines.next()?;
+    if let Some(encoding) = cookie_in_line(first) {
+        return Some((encoding, 1));
+    }
+    let first = String::from_utf8_lossy(first);
+    let first = first.trim_start_matches([' ', '\t', '\x0c']);
+    if first.trim_end().is_empty() || first.starts_with('#') {
+        return cookie_in_line(lines.next()?).map(|encoding| (encoding, 2));
+    }
+    None
+}
+
+/// Matches `^[ \t\f]*#.*?coding[:=][ \t]*([-\w.]+)` on a single line.
+fn cookie_in_line(line: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(line);
+    let comment = line
+        .trim_start_matches([' ', '\t', '\x0c'])
+        .strip_prefix('#')?;
+    comment.match_indices("coding").find_map(|(index, _)| {
+        let rest = comment[index + "coding".len()..].strip_prefix([':', '='])?;
+        let rest = rest.trim_start_matches([' ', '\t']);
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(rest.len());
+        (end > 0).then(|| rest[..end].to_string())
+    })
+}
+
+fn is_utf8(encoding: &str) -> bool {
+    let normalized = encoding.to_ascii_lowercase().replace('_', "-");
+    matches!(normalized.as_str(), "utf-8" | "utf8") || normalized.starts_with("utf-8-")
+}
+
+/// An `OSError` carrying `filename`; `OSError` picks the subclass, e.g. `FileNotFoundError`.
+pub(crate) fn os_error(err: std::io::Error, filename: &str) -> PyErr {
+    match err.raw_os_error() {
+        Some(errno) => {
+            let message = err.to_string();
+            let strerror = message
+                .split(" (os error")
+                .next()
+                .unwrap_or(message.as_str());
+            PyOSError::new_err((errno, strerror.to_string(), filename.to_string()))
+        }
+        None => err.into(),
+    }
+}
+
+fn syntax_error(msg: String, filename: &str, lineno: usize) -> PyErr {
+    PySyntaxError::new_err((msg, (filename.to_string(), lineno, 0, String::new())))
+}
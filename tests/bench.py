@@ -1,5 +1,34 @@
+"""Parser benchmarks, run with `make bench` or by CodSpeed in CI.
+
+These only report timings; they aren't collected by a plain `pytest` run and
+a slow result doesn't fail the build. Compare `test_parse_corpus` with its
+`ast.parse` baseline to see the cost of building the Python tree.
+"""
+
+import ast
+import sys
+from pathlib import Path
+
 import pytest
 
+DATA_DIR = Path(__file__).parent / "data"
+
+
+def corpus_paths() -> list[Path]:
+    """Files from tests/data that the running Python can parse too."""
+    paths = []
+    for py in sorted(DATA_DIR.glob("*.py")):
+        if ".3_" in py.name:
+            _, syntax_version, _ = py.name.rsplit(".", 2)
+            if sys.version_info < tuple(int(v) for v in syntax_version.split("_")):
+                continue
+        paths.append(py)
+    return paths
+
+
+def corpus() -> list[str]:
+    return [path.read_text() for path in corpus_paths()]
+
 
 @pytest.mark.benchmark(group="parse_string")
 def test_parse_string(benchmark, parse_string):
@@ -17,3 +46,42 @@ def test_parse_file(benchmark, parse_file):
 
         path = Path(__file__).parent / "bench.py"
         return parse_file(str(path))
+
+
+@pytest.mark.benchmark(group="parse_corpus")
+def test_parse_corpus(benchmark, parse_string):
+    sources = corpus()
+
+    @benchmark
+    def main():
+        return [parse_string(src) for src in sources]
+
+
+@pytest.mark.benchmark(group="parse_corpus")
+def test_parse_corpus_cpython(benchmark):
+    """Baseline for `test_parse_corpus`: the same sources through `ast.parse`."""
+    sources = corpus()
+
+    @benchmark
+    def main():
+        return [ast.parse(src) for src in sources]
+
+
+@pytest.mark.benchmark(group="parse_many")
+def test_parse_files_serial(benchmark, parse_file):
+    paths = corpus_paths() * 20
+
+    @benchmark
+    def main():
+        return [parse_file(path) for path in paths]
+
+
+@pytest.mark.benchmark(group="parse_many")
+def test_parse_many(benchmark):
+    from oxipy import Parser
+
+    paths = corpus_paths() * 20
+
+    @benchmark
+    def main():
+        return Parser.parse_many(paths)
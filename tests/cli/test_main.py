@@ -4,12 +4,90 @@ import sys
 
 
 def test_main(capfd):
-    __main__.main(["-c", "echo hello", "-vv"])
+    assert __main__.main(["-c", "echo hello", "-vv"]) == 0
 
     captured = capfd.readouterr()
     # with capfd.disabled():
     #     print(captured.err)
     assert "Running command" in captured.err
+    assert captured.out == "hello\n"
+
+
+def run_command(program: str) -> subprocess.CompletedProcess:
+    return subprocess.run(
+        [sys.executable, "-m", "oxipy", "-c", program], capture_output=True, text=True
+    )
+
+
+def test_command_status():
+    child = run_command("cd /\nx = $(pwd)\nprint(x.strip())\nfalse")
+    assert child.stdout == "/\n"
+    assert child.returncode == 1
+    assert run_command("true").returncode == 0
+    assert run_command("import sys; sys.exit(4)").returncode == 4
+
+
+def test_command_exit():
+    child = run_command("echo before\nexit 3\necho after")
+    assert child.stdout == "before\n"
+    assert child.returncode == 3
+    # mixed with Python, the entry goes through the interpreter
+    child = run_command("x = 1; exit 3; echo after")
+    assert child.stdout == ""
+    assert child.returncode == 3
+    child = run_command("for i in range(3):\n    echo @(i)\n    if i == 1:\n        exit 5\n")
+    assert child.stdout == "0\n1\n"
+    assert child.returncode == 5
+
+
+def test_command_syntax_error():
+    child = run_command("x = (")
+    assert child.returncode == 1
+    assert "in <string>:" in child.stderr
+    assert "x = (" in child.stderr
+    assert "Traceback" not in child.stderr
+
+
+def test_defines_and_no_env(monkeypatch):
+    monkeypatch.setenv("SECRET", "1")
+    program = "printenv GREETING\nprintenv SECRET"
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", "-DGREETING=hi", "-c", program],
+        capture_output=True,
+        text=True,
+    )
+    assert child.stdout == "hi\n1\n"
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", "--no-env", "-DGREETING=hi", "-c", program],
+        capture_output=True,
+        text=True,
+    )
+    assert child.stdout == "hi\n"
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", "-DGREETING", "-c", "true"],
+        capture_output=True,
+        text=True,
+    )
+    assert child.returncode == 2
+    assert "expected NAME=VAL" in child.stderr
+
+
+def test_import_oxy_module(tmp_path):
+    (tmp_path / "helpers.oxy").write_text("def greet(name):\n    return $(echo hi @(name))\n")
+    child = subprocess.run(
+        [
+            sys.executable,
+            "-m",
+            "oxipy",
+            "-c",
+            "import helpers\nprint(helpers.greet('you'), end='')",
+        ],
+        capture_output=True,
+        text=True,
+        cwd=tmp_path,
+    )
+    assert child.stderr == ""
+    assert child.stdout == "hi you\n"
 
 
 def test_oxcli_help():
@@ -20,3 +98,98 @@ def test_oxcli_help():
     output = child.stdout.decode("utf-8")
     assert "Usage" in output
     assert "--help" in output
+
+
+def test_check(tmp_path, capfd):
+    script = tmp_path / "script.oxy"
+    script.write_text("$[echo *.py]\n$[echo *.txt]  # noqa: OX002\n")
+
+    assert __main__.main(["check", str(script)]) == 1
+
+    captured = capfd.readouterr()
+    assert f"{script}:1:8: OX002" in captured.out
+    assert ":2:" not in captured.out
+
+
+def test_convert(tmp_path, capfd):
+    script = tmp_path / "script.sh"
+    script.write_text("#!/bin/sh\nNAME=world\necho \"hi $NAME\" | tee out.txt\n")
+
+    assert __main__.main(["convert", str(script)]) == 0
+
+    captured = capfd.readouterr()
+    assert captured.out == (
+        "#!/usr/bin/env oxipy\n$NAME = 'world'\n$[echo @('hi ' + $NAME) | tee out.txt]\n"
+    )
+
+
+def test_script(tmp_path):
+    script = tmp_path / "deploy.oxy"
+    script.write_text(
+        "#!/usr/bin/env oxipy\n"
+        "import sys\n"
+        "print(sys.argv[1:], $1)\n"
+        "echo $2 @($ARGS[0] == sys.argv[0])\n"
+        "exit 7\n"
+    )
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", str(script), "--env", "prod"],
+        capture_output=True,
+        text=True,
+    )
+    assert child.stdout == "['--env', 'prod'] --env\nprod True\n"
+    assert child.returncode == 7
+
+
+def test_script_stops_at_exit(tmp_path):
+    script = tmp_path / "stop.oxy"
+    script.write_text("echo before\nexit 3\nprint('after')\necho after\n")
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", str(script)], capture_output=True, text=True
+    )
+    assert child.stdout == "before\n"
+    assert child.returncode == 3
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy"],
+        input="exit 3\nprint('after')\n",
+        capture_output=True,
+        text=True,
+    )
+    assert child.stdout == ""
+    assert child.returncode == 3
+
+
+def test_script_args_are_not_exported(tmp_path):
+    script = tmp_path / "env.oxy"
+    script.write_text("print($1)\nprintenv\n")
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", str(script), "first"],
+        capture_output=True,
+        text=True,
+    )
+    lines = child.stdout.splitlines()
+    assert lines[0] == "first"
+    names = {line.partition("=")[0] for line in lines[1:]}
+    assert "PATH" in names
+    assert not names & {"ARGS", "0", "1"}
+
+
+def test_script_from_stdin():
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy"],
+        input="x = 20\nprint(x * 2)\nimport sys; sys.exit(3)\n",
+        capture_output=True,
+        text=True,
+    )
+    assert child.stdout == "40\n"
+    assert child.returncode == 3
+
+
+def test_missing_script(tmp_path):
+    child = subprocess.run(
+        [sys.executable, "-m", "oxipy", str(tmp_path / "missing.oxy")],
+        capture_output=True,
+        text=True,
+    )
+    assert child.returncode == 2
+    assert "can't open file" in child.stderr
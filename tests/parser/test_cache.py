@@ -0,0 +1,79 @@
+"""Tests the `.oxyc` cache and importing `.oxy` modules."""
+
+import sys
+
+import pytest
+
+from oxipy import cache, importer
+
+
+@pytest.fixture
+def script(tmp_path):
+    path = tmp_path / "script.oxy"
+    path.write_text("x = 1\n")
+    return path
+
+
+def run(code):
+    ns = {}
+    exec(code, ns)
+    return ns
+
+
+def test_cache_is_written_and_reused(script, monkeypatch):
+    code = cache.get_code(script, use_cache=True)
+    entry = cache.cache_from_source(script)
+    assert entry.parent.name == "__pycache__"
+    assert entry.name.endswith(".oxyc")
+    assert entry.exists()
+
+    def fail(*args, **kwargs):
+        raise AssertionError("source was recompiled")
+
+    monkeypatch.setattr(cache, "source_to_code", fail)
+    assert cache.get_code(script, use_cache=True) == code
+
+
+def test_changed_source_invalidates(script):
+    assert run(cache.get_code(script, use_cache=True))["x"] == 1
+    script.write_text("x = 2\n")
+    assert run(cache.get_code(script, use_cache=True))["x"] == 2
+
+
+def test_key_covers_build_and_options(monkeypatch):
+    key = cache.cache_key(b"x = 1\n")
+    assert cache.cache_key(b"x = 1\n", optimize=2) != key
+    monkeypatch.setattr(cache, "build_key", lambda: b"other build")
+    assert cache.cache_key(b"x = 1\n") != key
+
+
+def test_corrupt_entry_is_ignored(script):
+    entry = cache.cache_from_source(script)
+    entry.parent.mkdir()
+    entry.write_bytes(cache.MAGIC + cache.cache_key(script.read_bytes()) + b"junk")
+    assert run(cache.get_code(script, use_cache=True))["x"] == 1
+
+
+def test_no_cache(script):
+    cache.get_code(script, use_cache=False)
+    assert not cache.cache_from_source(script).exists()
+
+
+def test_import_oxy_module(tmp_path, monkeypatch):
+    pkg = tmp_path / "pkg"
+    pkg.mkdir()
+    (pkg / "__init__.oxy").write_text("NAME = 'pkg'\n")
+    (pkg / "tool.oxy").write_text("def cmd():\n    return $(echo tool).strip()\n")
+    monkeypatch.syspath_prepend(str(tmp_path))
+    importer.install()
+    try:
+        from pkg import tool
+
+        assert sys.modules["pkg"].NAME == "pkg"
+        assert tool.cmd() == "tool"
+        assert tool.__file__ == str(pkg / "tool.oxy")
+        assert cache.cache_from_source(pkg / "tool.oxy").exists()
+    finally:
+        importer.uninstall()
+        sys.modules.pop("pkg", None)
+        sys.modules.pop("pkg.tool", None)
@@ -0,0 +1,50 @@
+"""Tests parsing many files at once on the thread pool."""
+
+import ast
+
+from oxipy import Parser
+
+
+def test_results_follow_paths(tmp_path):
+    paths = []
+    for idx in range(10):
+        path = tmp_path / f"mod{idx}.py"
+        path.write_text(f"x = {idx}\n")
+        paths.append(path)
+    trees = Parser.parse_many(paths)
+    assert [ast.unparse(tree) for tree in trees] == [f"x = {idx}" for idx in range(10)]
+
+
+def test_matches_parse_file(tmp_path):
+    path = tmp_path / "mod.py"
+    path.write_text("def f(a, *b):\n    return $(ls -l @(a))\n")
+    [tree] = Parser.parse_many([path])
+    assert ast.dump(tree, include_attributes=True) == ast.dump(
+        Parser.parse_file(path), include_attributes=True
+    )
+
+
+def test_errors_are_returned(tmp_path):
+    good = tmp_path / "good.py"
+    good.write_text("x = 1\n")
+    bad = tmp_path / "bad.py"
+    bad.write_text("x = (\n")
+    missing = tmp_path / "missing.py"
+
+    first, second, third = Parser.parse_many(iter([bad, good, missing]))
+    assert isinstance(first, SyntaxError)
+    assert first.filename == str(bad)
+    assert ast.unparse(second) == "x = 1"
+    assert isinstance(third, FileNotFoundError)
+    assert third.filename == str(missing)
+
+
+def test_coding_cookie(tmp_path):
+    path = tmp_path / "latin.py"
+    path.write_bytes("# coding: latin-1\nx = 'é'\n".encode("latin-1"))
+    invalid = tmp_path / "invalid.py"
+    invalid.write_bytes(b"x = '\xff'\n")
+
+    tree, error = Parser.parse_many([path, invalid])
+    assert ast.unparse(tree) == "x = 'é'"
+    assert isinstance(error, UnicodeDecodeError)
@@ -0,0 +1,67 @@
+"""Tests the semantic token classification used for highlighting."""
+
+from inline_snapshot import snapshot
+
+from oxipy import Parser
+
+
+def roles(inp: str):
+    return [(t.role, t.value) for t in Parser(inp).semantic_tokens()]
+
+
+def test_command_args():
+    assert roles("$[ls -la *.py src]") == snapshot(
+        [
+            ("subproc", "$["),
+            ("command", "ls"),
+            ("flag", "-la"),
+            ("glob", "*.py"),
+            ("argument", "src"),
+            ("operator", "]"),
+        ]
+    )
+
+
+def test_redirects():
+    assert roles("$[cat a.txt 2> err.log]") == snapshot(
+        [
+            ("subproc", "$["),
+            ("command", "cat"),
+            ("argument", "a.txt"),
+            ("redirect", "2"),
+            ("redirect", ">"),
+            ("redirect", "err.log"),
+            ("operator", "]"),
+        ]
+    )
+
+
+def test_pipes_and_env():
+    assert roles("x = $(echo $HOME | wc -l)") == snapshot(
+        [
+            ("operator", "="),
+            ("subproc", "$("),
+            ("command", "echo"),
+            ("env-var", "$HOME"),
+            ("operator", "|"),
+            ("command", "wc"),
+            ("flag", "-l"),
+            ("operator", ")"),
+        ]
+    )
+
+
+def test_python():
+    assert roles("if p'~/x' and not re'a.*':  # c\n    pass") == snapshot(
+        [
+            ("python-keyword", "if"),
+            ("path", "p'~/x'"),
+            ("python-keyword", "and"),
+            ("python-keyword", "not"),
+            ("regex", "re'a.*'"),
+            ("operator", ":"),
+            ("comment", "# c"),
+            ("python-keyword", "pass"),
+        ]
+    )
+
@@ -0,0 +1,89 @@
+"""Tests reading sources with coding cookies, BOMs and bad paths."""
+
+import ast
+
+import pytest
+
+from oxipy import Parser
+
+
+def test_default_utf8(tmp_path):
+    path = tmp_path / "mod.py"
+    path.write_text("x = 'héllo'\n", encoding="utf-8")
+    tree = Parser.parse_file(path)
+    assert ast.unparse(tree) == "x = 'héllo'"
+
+
+def test_bom_is_stripped(tmp_path):
+    path = tmp_path / "mod.py"
+    path.write_bytes(b"\xef\xbb\xbfx = 1\n")
+    assert ast.unparse(Parser.parse_file(path)) == "x = 1"
+
+
+def test_str_bom_is_stripped(tmp_path):
+    assert ast.unparse(Parser("\ufeffx = 1\n").parse()) == "x = 1"
+    path = tmp_path / "mod.py"
+    path.write_bytes(b"\xef\xbb\xbfy = 2\n")
+    # reading as UTF-8 text keeps the BOM
+    assert ast.unparse(Parser(path.read_text(encoding="utf-8")).parse()) == "y = 2"
+
+
+@pytest.mark.parametrize(
+    "header",
+    [
+        "# -*- coding: latin-1 -*-\n",
+        "#!/usr/bin/env python\n# vim: set fileencoding=latin-1 :\n",
+    ],
+)
+def test_coding_cookie(tmp_path, header):
+    path = tmp_path / "mod.py"
+    path.write_bytes(header.encode() + "x = 'é'\n".encode("latin-1"))
+    assert ast.unparse(Parser.parse_file(path)) == "x = 'é'"
+
+
+def test_cookie_after_code_is_ignored():
+    with pytest.raises(UnicodeDecodeError):
+        Parser(b"x = 1\n# coding: latin-1\ny = '\xe9'\n")
+
+
+def test_bytes_source():
+    src = "# coding: latin-1\nx = 'é'\n".encode("latin-1")
+    assert ast.unparse(Parser(src).parse()) == "x = 'é'"
+
+
+def test_path_types(tmp_path):
+    path = tmp_path / "mod.py"
+    path.write_text("x = 1\n")
+    for arg in (path, str(path), bytes(path)):
+        assert ast.unparse(Parser.parse_file(arg)) == "x = 1"
+
+
+def test_missing_file(tmp_path):
+    path = tmp_path / "missing.py"
+    with pytest.raises(FileNotFoundError) as exc_info:
+        Parser.parse_file(path)
+    assert exc_info.value.filename == str(path)
+
+
+def test_unknown_encoding():
+    with pytest.raises(SyntaxError, match="unknown encoding: nope") as exc_info:
+        Parser(b"# coding: nope\nx = 1\n", file_name="mod.py")
+    assert exc_info.value.filename == "mod.py"
+
+
+def test_bom_with_other_cookie():
+    with pytest.raises(SyntaxError, match="encoding problem: latin-1 with BOM"):
+        Parser(b"\xef\xbb\xbf# coding: latin-1\nx = 1\n")
+
+
+def test_invalid_utf8():
+    with pytest.raises(UnicodeDecodeError):
+        Parser(b"x = '\xff'\n")
+
+
+def test_syntax_error_has_filename(tmp_path):
+    path = tmp_path / "bad.py"
+    path.write_text("x = (\n")
+    with pytest.raises(SyntaxError) as exc_info:
+        Parser.parse_file(path)
+    assert exc_info.value.filename == str(path)
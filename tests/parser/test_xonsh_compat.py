@@ -0,0 +1,113 @@
+"""Tests the xonsh-compatible parser facade."""
+
+import ast
+
+import pytest
+
+from oxipy.xonsh_compat import Parser, subproc_toks
+
+
+@pytest.fixture
+def parser():
+    return Parser()
+
+
+@pytest.mark.parametrize(
+    "inp, exp",
+    [
+        ("$HOME", "__xonsh__.env['HOME']"),
+        ("$(ls -l)", "__xonsh__.subproc_captured_stdout(['ls', '-l'])"),
+        ("!(ls)", "__xonsh__.subproc_captured_object(['ls'])"),
+        ("$[ls]", "__xonsh__.subproc_uncaptured(['ls'])"),
+        ("![ls]", "__xonsh__.subproc_captured_hiddenobject(['ls'])"),
+        (
+            "$(ls | grep wakka > x.py)",
+            "__xonsh__.subproc_captured_stdout(['ls'], '|', ['grep', 'wakka', '>', 'x.py'])",
+        ),
+        (
+            "$(ls 2>> err.log)",
+            "__xonsh__.subproc_captured_stdout(['ls', '2>>', 'err.log'])",
+        ),
+        (
+            "$(emacs ugggh &)",
+            "__xonsh__.subproc_captured_stdout(['emacs', 'ugggh'], '&')",
+        ),
+        (
+            "$(ls @(x) $HOME)",
+            "__xonsh__.subproc_captured_stdout(['ls', *__xonsh__.list_of_strs_or_callables(x), '$HOME'])",
+        ),
+        ('p"/foo"', "__xonsh__.path_literal('/foo')"),
+        ('g"*.py"', "__xonsh__.pathsearch(__xonsh__.globsearch, '*.py', True, False)"),
+        (
+            '$(ls g"*.py")',
+            "__xonsh__.subproc_captured_stdout(['ls', *__xonsh__.pathsearch(__xonsh__.globsearch, '*.py', False, False)])",
+        ),
+        (
+            '$(ls @foo".*")',
+            "__xonsh__.subproc_captured_stdout(['ls', *__xonsh__.pathsearch(foo, '.*', False, False)])",
+        ),
+        (
+            "![echo a && echo b]",
+            "__xonsh__.subproc_captured_hiddenobject(['echo', 'a']) and "
+            "__xonsh__.subproc_captured_hiddenobject(['echo', 'b'])",
+        ),
+        (
+            "$[false || echo c; ls | wc]",
+            "(__xonsh__.subproc_captured_hiddenobject(['false']) or "
+            "__xonsh__.subproc_captured_hiddenobject(['echo', 'c']), "
+            "__xonsh__.subproc_captured_hiddenobject(['ls'], '|', ['wc']))[-1]",
+        ),
+        (
+            "!(a && b || c)",
+            "__xonsh__.subproc_captured_object(['a']) and "
+            "__xonsh__.subproc_captured_object(['b']) or "
+            "__xonsh__.subproc_captured_object(['c'])",
+        ),
+        (
+            "$(echo ';' '&&' @(x))",
+            "__xonsh__.subproc_captured_stdout(['echo', ';', '&&', "
+            "*__xonsh__.list_of_strs_or_callables(x)])",
+        ),
+        ("$? == 0", "__xonsh__.env['LAST_RETURN_CODE'] == 0"),
+        (
+            "$(echo $?)",
+            "__xonsh__.subproc_captured_stdout(['echo', '$LAST_RETURN_CODE'])",
+        ),
+        ("range?", "__xonsh__.help(range)"),
+        ("range??", "__xonsh__.superhelp(range)"),
+    ],
+)
+def test_lowering(parser, inp, exp):
+    assert ast.unparse(parser.parse(inp)) == exp
+
+
+def test_modes(parser):
+    assert isinstance(parser.parse("x = 1\n"), ast.Module)
+    tree = parser.parse("$(ls)", mode="eval")
+    assert isinstance(tree, ast.Expression)
+    compile(tree, "<eval>", "eval")
+    tree = parser.parse("$[ls -l]\n", mode="single", debug_level=1)
+    assert isinstance(tree, ast.Interactive)
+    compile(tree, "<single>", "single")
+
+
+def test_syntax_error(parser):
+    with pytest.raises(SyntaxError) as exc_info:
+        parser.parse("x = (\n", filename="bad.xsh")
+    assert exc_info.value.filename == "bad.xsh"
+    with pytest.raises(SyntaxError):
+        parser.parse("x = 1\n", mode="eval")
+
+
+def test_captured_command_lists(parser):
+    # xonsh only joins commands with `and` and `or`, which can't concatenate their output
+    with pytest.raises(SyntaxError, match="capture the output"):
+        parser.parse("$(echo a && echo b)")
+
+
+def test_lexer_split(parser):
+    assert parser.lexer.split("echo -n $HOME") == ["echo", "-n", "$HOME"]
+
+
+def test_subproc_toks():
+    assert subproc_toks("ls -l", lexer=None, returnline=False) == "![ls -l]"
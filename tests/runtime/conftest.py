@@ -0,0 +1,15 @@
+import pytest
+
+
+@pytest.fixture
+def run(parse_string):
+    """Executes oxipy source with the real runtime bound to `ox` and returns its namespace."""
+    from oxipy import ox
+
+    def factory(code: str, **names):
+        tree = parse_string(code)
+        namespace = {"ox": ox, **names}
+        exec(compile(tree, "<test>", "exec"), namespace)
+        return namespace
+
+    return factory
@@ -0,0 +1,99 @@
+"""Python callables as pipeline stages."""
+
+import itertools
+import sys
+
+import pytest
+
+from oxipy import ox
+
+
+def test_return_value_is_output(run):
+    ns = run('out = $(echo hello | @(lambda a, s=None: "hey!") foo bar)')
+    assert ns["out"] == "hey!"
+
+
+def test_receives_args_and_streams(run):
+    def upper(args, stdin, stdout):
+        stdout.write(" ".join(args) + ":")
+        for line in stdin:
+            stdout.write(line.upper())
+
+    ns = run("out = $(printf 'a\\nb\\n' | @(upper) x y | cat)", upper=upper)
+    assert ns["out"] == "x y:A\nB\n"
+
+
+def test_stdin_read_and_readline(run):
+    def first(args, stdin):
+        return stdin.readline().strip() + "|" + stdin.read()
+
+    ns = run("out = $(printf 'one\\ntwo\\n' | @(first))", first=first)
+    assert ns["out"] == "one|two\n"
+
+
+def test_stage_feeds_next_command(run):
+    def numbers(args, stdin, stdout):
+        for i in range(int(args[0])):
+            print(i, file=stdout)
+
+    ns = run("out = $(@(numbers) 5 | wc -l)", numbers=numbers)
+    assert ns["out"].strip() == "5"
+
+
+def test_infinite_stage_stops_when_reader_exits(run):
+    def count(args, stdin, stdout):
+        for i in itertools.count():
+            stdout.write(f"{i}\n")
+
+    ns = run("out = $(@(count) | head -n 3)", count=count)
+    assert ns["out"] == "0\n1\n2\n"
+
+
+@pytest.mark.parametrize(
+    "result, output, errors, returncode",
+    [
+        (None, "", "", 0),
+        (3, "", "", 3),
+        (True, "", "", 0),
+        (False, "", "", 1),
+        (b"raw", "raw", "", 0),
+        (("out", "err"), "out", "err", 0),
+        (("out", None, 2), "out", "", 2),
+    ],
+)
+def test_return_values(result, output, errors, returncode):
+    done = ox.cmd(ox.list_of_strs_or_callables(lambda: result)).obj()
+    assert (done.output, done.errors, done.returncode) == (output, errors, returncode)
+
+
+def test_exception_prints_traceback():
+    def fail(args):
+        raise ValueError("boom")
+
+    done = ox.cmd(ox.list_of_strs_or_callables(fail)).obj()
+    assert done.returncode == 1
+    assert "Traceback" in done.errors
+    assert done.errors.endswith("ValueError: boom\n")
+
+
+def test_system_exit_sets_code():
+    done = ox.cmd(ox.list_of_strs_or_callables(lambda args: sys.exit(4))).obj()
+    assert done.returncode == 4
+    assert not done
+
+
+def test_exit_code_drives_boolean_lists(run):
+    ns = run("out = $(@(lambda: 1) || echo fallback)")
+    assert ns["out"] == "fallback\n"
+
+
+def test_callable_only_in_command_position():
+    with pytest.raises(TypeError, match="first word"):
+        ox.cmd("echo", ox.list_of_strs_or_callables(print)).out()
+
+
+def test_list_of_strs_or_callables():
+    assert ox.list_of_strs_or_callables("a b") == ["a b"]
+    assert ox.list_of_strs_or_callables([1, "x"]) == ["1", "x"]
+    assert ox.list_of_strs_or_callables(7) == ["7"]
+    assert ox.list_of_strs_or_callables(print) == [print]
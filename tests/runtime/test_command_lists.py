@@ -0,0 +1,28 @@
+"""`&&`, `||` and `;` between commands, and words that only look like them."""
+
+from oxipy import ox
+
+
+def test_operators_separate_commands(run):
+    ns = run("out = $(echo a && echo b; false || echo c)")
+    assert ns["out"] == "a\nb\nc\n"
+
+
+def test_quoted_operators_are_arguments(run):
+    ns = run("out = $(echo ';' '&&' \"||\" done)")
+    assert ns["out"] == "; && || done\n"
+
+
+def test_interpolated_operators_are_arguments(run):
+    words = [";", "echo", "pwned", "&&", "echo", "again"]
+    ns = run("out = $(echo @(words))", words=words)
+    assert ns["out"] == "; echo pwned && echo again\n"
+    ns = run("out = $(echo @(op) x)", op="||")
+    assert ns["out"] == "|| x\n"
+
+
+def test_operator_sentinels():
+    assert repr(ox.AND) == "ox.AND"
+    assert ox.cmd("echo", "a", ox.THEN, "echo", "b").out() == "a\nb\n"
+    assert ox.cmd("false", ox.OR, "echo", "c").out() == "c\n"
+    assert ox.cmd("echo", ";", "x").out() == "; x\n"
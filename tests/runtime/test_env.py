@@ -0,0 +1,79 @@
+"""Typed variables in `ox.env`."""
+
+import os
+
+import pytest
+
+from oxipy.env import Env, EnvPath
+
+
+@pytest.fixture
+def env(monkeypatch):
+    from oxipy import ox
+
+    env = Env({"PATH": os.pathsep.join(["/usr/bin", "/bin"]), "SHLVL": "2", "NAME": "x"})
+    monkeypatch.setattr(ox, "env", env)
+    return env
+
+
+def test_path_lists_split_and_join(env):
+    assert env["PATH"] == ["/usr/bin", "/bin"]
+    env["PATH"].add("/opt/bin", front=True)
+    env["PATH"].add("/bin")
+    assert env.env_vars()["PATH"] == os.pathsep.join(["/opt/bin", "/usr/bin", "/bin"])
+
+
+def test_path_expands_user(env):
+    env["PYTHONPATH"] = "~/lib"
+    assert env["PYTHONPATH"] == [os.path.expanduser("~/lib")]
+    assert isinstance(env["PYTHONPATH"], EnvPath)
+
+
+def test_ints_and_bools(env):
+    assert env["SHLVL"] == 2
+    env.register("OX_DEBUG", bool, default=False, doc="Print debug output.")
+    assert env["OX_DEBUG"] is False
+    assert "OX_DEBUG" not in env.env_vars()
+    env["OX_DEBUG"] = "yes"
+    assert env["OX_DEBUG"] is True
+    assert env.env_vars()["OX_DEBUG"] == "1"
+    assert env.doc("OX_DEBUG") == "Print debug output."
+    with pytest.raises(ValueError):
+        env["SHLVL"] = "deep"
+
+
+def test_custom_converter(env):
+    env.register("NAME", convert=lambda text: text.split(","), detype=",".join)
+    assert env["NAME"] == ["x"]
+    env["NAME"] = "a,b"
+    assert env["NAME"] == ["a", "b"]
+    assert env.env_vars()["NAME"] == "a,b"
+
+
+def test_script_args(env):
+    env["ARGS"] = ["deploy.oxy", "--name", "a b"]
+    assert env.detype("ARGS") == "deploy.oxy --name 'a b'"
+    assert "ARGS" not in env.env_vars()
+    env["ARGS"] = "x.oxy 'a b'"
+    assert env["ARGS"] == ["x.oxy", "a b"]
+
+
+def test_unexported_vars(env):
+    env.register("SECRET", export=False)
+    env["SECRET"] = "shh"
+    assert env["SECRET"] == "shh"
+    assert "SECRET" not in env.env_vars()
+    assert env.is_exported("NAME")
+
+
+def test_unregistered_values_are_strings(env):
+    env["COUNT"] = 3
+    assert env["COUNT"] == "3"
+    with pytest.raises(KeyError):
+        env["MISSING"]
+
+
+def test_children_see_strings(env, run):
+    run("$SHLVL = $SHLVL + 1\n$GREETING = 'hi'")
+    ns = run("out = $(sh -c 'echo $SHLVL $GREETING')")
+    assert ns["out"] == "3 hi\n"
@@ -0,0 +1,100 @@
+"""`obj?` and `obj??` help."""
+
+import stat
+
+import pytest
+
+from oxipy import ox
+from oxipy.env import Env
+
+
+@pytest.fixture
+def env(monkeypatch, tmp_path):
+    env = Env({"PATH": str(tmp_path), "PWD": str(tmp_path)})
+    monkeypatch.setattr(ox, "env", env)
+    return env
+
+
+def greet(name: str, loud: bool = False) -> str:
+    """Says hello to `name`."""
+    return f"hello {name}"
+
+
+def test_help_shows_signature_doc_and_file(run, capsys):
+    ns = run("x = greet?", greet=greet)
+    assert ns["x"] is greet
+    out = capsys.readouterr().out
+    assert "Signature: (name: str, loud: bool = False) -> str" in out
+    assert "Docstring:\nSays hello to `name`." in out
+    assert f"File: {__file__}:" in out
+    assert "return f" not in out
+
+
+def test_superhelp_shows_source(run, capsys):
+    run("greet??", greet=greet)
+    out = capsys.readouterr().out
+    assert "Source:\ndef greet(" in out
+    assert 'return f"hello {name}"' in out
+
+
+def test_builtins_without_source(capsys):
+    assert ox.superhelp(len) is len
+    out = capsys.readouterr().out
+    assert "Type: builtin_function_or_method" in out
+    assert "Return the number of items" in out
+    assert "File:" not in out
+    assert "Source:" not in out
+
+
+def test_help_returns_object_for_chaining(run, capsys):
+    ns = run("x = range?.index?")
+    assert ns["x"] == range.index
+    out = capsys.readouterr().out
+    assert "Type: type" in out
+    assert "Type: method_descriptor" in out
+
+
+def test_external_command(env, tmp_path, capsys):
+    tool = tmp_path / "tool"
+    tool.write_text('#!/bin/sh\necho "usage: tool [$1]"\n')
+    tool.chmod(tool.stat().st_mode | stat.S_IXUSR)
+    last = ox.last
+    assert ox.help("tool") == "tool"
+    out = capsys.readouterr().out
+    assert "Kind: external command" in out
+    assert f"Path: {tool}" in out
+    assert "Help:\nusage: tool [--help]\n" in out
+    assert "Source:" not in out
+    ox.superhelp("tool")
+    assert "Source:\n#!/bin/sh\n" in capsys.readouterr().out
+    assert ox.last is last
+
+
+def test_external_command_output_is_bounded(env, tmp_path, capsys):
+    def tool(name, body):
+        path = tmp_path / name
+        path.write_text(f"#!/bin/sh\n{body}\n")
+        path.chmod(path.stat().st_mode | stat.S_IXUSR)
+
+    tool("waits", "exec /bin/sleep 10")
+    tool("chatty", "exec /usr/bin/yes usage")
+    ox.help("waits")
+    assert "Help: [stopped after 2s]\n" in capsys.readouterr().out
+    ox.help("chatty")
+    out = capsys.readouterr().out
+    assert out.endswith("usage\n[truncated]\n")
+    assert len(out) < 70_000
+
+
+def test_builtin_command(env, capsys):
+    ox.help("cd")
+    out = capsys.readouterr().out
+    assert "Kind: cmdgroup builtin" in out
+    assert "Path:" not in out
+
+
+def test_unknown_command_is_a_string(env, capsys):
+    ox.help("no-such-command")
+    out = capsys.readouterr().out
+    assert "Type: str" in out
+    assert "String form: 'no-such-command'" in out
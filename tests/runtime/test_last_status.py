@@ -0,0 +1,42 @@
+"""`$?` and `ox.last`."""
+
+import pytest
+
+from oxipy import ox
+
+
+def test_last_status(run):
+    ns = run("$[false]\nfailed = $?\n$[true]\npassed = $?")
+    assert (ns["failed"], ns["passed"]) == (1, 0)
+
+
+def test_exit_raises_system_exit(run):
+    with pytest.raises(SystemExit) as exited:
+        run("$[echo a && exit 3]\nreached = True")
+    assert exited.value.code == 3
+    assert ox.last.returncode == 3
+    # like a subshell, command substitution doesn't end the script
+    run("out = $(exit 4)")
+    assert ox.last.returncode == 4
+
+
+def test_pipestatus(run):
+    run("$[exit 3 | true | cat]")
+    assert ox.last.pipestatus == [3, 0, 0]
+    assert ox.last.returncode == 0
+
+
+def test_last_records_argv_and_timing(run):
+    run("out = $(echo hi | @(lambda: 2))")
+    last = ox.last
+    assert last.args[0] == ["echo", "hi"]
+    assert last.args[1][0].startswith("<function <lambda>")
+    assert last.pipestatus == [0, 2]
+    assert ox.last_status == 2
+    assert last.duration >= 0
+    assert last.started > 0
+
+
+def test_captured_stdout_is_kept(run):
+    run("$(echo hi)")
+    assert ox.last.output == "hi\n"
@@ -0,0 +1,53 @@
+"""`p"..."` path literals."""
+
+import os
+from pathlib import Path
+
+import pytest
+
+from oxipy import ox
+from oxipy.env import Env
+from oxipy.paths import ShellPath
+
+
+@pytest.fixture
+def env(monkeypatch, tmp_path):
+    env = Env({"HOME": "/home/ox", "PROJECT": "crate", "PWD": str(tmp_path)})
+    monkeypatch.setattr(ox, "env", env)
+    return env
+
+
+def test_expands_home_and_variables(env):
+    assert ox.path("~/src/$PROJECT") == Path("/home/ox/src/crate")
+    assert ox.path("/a/${PROJECT}.d/$UNSET") == Path("/a/crate.d/$UNSET")
+    assert ox.path("~other/x") == Path(env["PWD"], "~other/x")
+
+
+def test_relative_to_shell_cwd(env, run, tmp_path):
+    (tmp_path / "sub").mkdir()
+    assert run("p = p'sub'")["p"] == tmp_path / "sub"
+    run("cd sub")
+    assert env["PWD"] == str(tmp_path / "sub")
+    assert run("p = p'x.txt'")["p"] == tmp_path / "sub" / "x.txt"
+
+
+def test_fstring_paths(env, run):
+    ns = run("root = '/opt'\np = pf'{root}/$PROJECT/bin'")
+    assert isinstance(ns["p"], ShellPath)
+    assert ns["p"] == Path("/opt/crate/bin")
+    assert isinstance(ns["p"] / "tool", ShellPath)
+
+
+def test_passed_as_one_argument(env, run):
+    ns = run("out = $(printf '[%s]' @(p'/a dir/$PROJECT'))")
+    assert ns["out"] == "[/a dir/crate]"
+    assert ox.path("/a dir").quoted == "'/a dir'"
+
+
+def test_captured_commands_keep_no_changes(env, run, tmp_path):
+    (tmp_path / "sub").mkdir()
+    run("$(cd sub)\nx = $(export NAME=1)")
+    assert env["PWD"] == str(tmp_path)
+    assert "NAME" not in env
+    run("$[export NAME=1]")
+    assert env["NAME"] == "1"
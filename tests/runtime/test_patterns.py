@@ -0,0 +1,71 @@
+"""Regex and glob literals, and `@name"..."` search functions."""
+
+import pytest
+
+from oxipy import ox
+from oxipy.env import Env
+
+
+@pytest.fixture
+def tree(monkeypatch, tmp_path):
+    for name in ["b.py", "a.py", ".hidden.py", "notes.txt", "src/x.rs", "src/deep/y.rs"]:
+        path = tmp_path / name
+        path.parent.mkdir(parents=True, exist_ok=True)
+        path.write_text("")
+    monkeypatch.setattr(ox, "env", Env({"PWD": str(tmp_path), "DIR": "src"}))
+    return tmp_path
+
+
+@pytest.fixture(autouse=True)
+def searches():
+    """Search functions are registered for the whole process, so each test starts without."""
+    ox.Pattern.unregister_all()
+    yield
+    ox.Pattern.unregister_all()
+
+
+def test_regex_per_path_level(tree):
+    assert ox.Pattern(r"[ab]\.py").regex() == ["a.py", "b.py"]
+    assert ox.Pattern(r"src/.*\.rs").regex() == ["src/x.rs"]
+    assert ox.Pattern(r".*\.py").regex() == [".hidden.py", "a.py", "b.py"]
+    assert ox.Pattern("nothing").regex() == []
+
+
+def test_glob_recursive(tree):
+    assert ox.Pattern("*.py").glob() == ["a.py", "b.py"]
+    assert ox.Pattern("src/**/*.rs").glob() == ["src/deep/y.rs", "src/x.rs"]
+    assert ox.Pattern("$DIR/*.rs").glob() == ["src/x.rs"]
+    assert ox.Pattern(f"{tree}/*.txt").glob() == [str(tree / "notes.txt")]
+
+
+def test_literals_expand_to_arguments(tree, run):
+    ns = run("""out = $(echo g"*.py" `src/.*`)""")
+    assert ns["out"] == "a.py b.py src/deep src/x.rs\n"
+
+
+def test_registered_search_function(tree, run):
+    ox.Pattern.register("upper", lambda pattern: [pattern.upper(), "B", "A"])
+    assert ox.Pattern("x").invoke("upper") == ["A", "B", "X"]
+    ns = run("""out = $(echo @upper"x" @glob"*.txt")""")
+    assert ns["out"] == "A B X notes.txt\n"
+
+
+def test_unknown_search_function(tree):
+    with pytest.raises(KeyError, match="register"):
+        ox.Pattern("x").invoke("missing")
+    with pytest.raises(ValueError):
+        ox.Pattern.register("not a name", print)
+
+
+def test_unregister(tree):
+    def search(pattern):
+        return [pattern]
+
+    ox.Pattern.register("mine", search)
+    assert ox.Pattern.unregister("mine") is search
+    with pytest.raises(KeyError):
+        ox.Pattern("x").invoke("mine")
+    with pytest.raises(KeyError):
+        ox.Pattern.unregister("mine")
+    # the builtin searches stay
+    assert ox.Pattern("*.py").invoke("glob") == ["a.py", "b.py"]
@@ -0,0 +1,94 @@
+"""Python entries of the REPL."""
+
+import sys
+
+import pytest
+
+from oxipy import ox, repl
+
+
+@pytest.fixture
+def namespace():
+    return repl.namespace()
+
+
+def test_namespace_persists(namespace, capsys):
+    assert repl.run("x = 40", namespace) == 0
+    assert repl.run("x + 2", namespace) == 0
+    assert capsys.readouterr().out == "42\n"
+    assert namespace["ox"] is ox
+
+
+def test_command_values_are_not_shown(namespace, capsys):
+    assert repl.run("$[true]", namespace) == 0
+    assert repl.run("echo hi", namespace) == 0
+    assert capsys.readouterr().out == ""
+    repl.run("!(true)", namespace)
+    assert "CompletedCommand" in capsys.readouterr().out
+
+
+def test_status_of_last_command(namespace):
+    assert repl.run("x = 1\n$[false]", namespace) == 1
+    assert repl.run("x = 2", namespace) == 0
+    assert repl.run("$[false] or 1", namespace) == 1
+
+
+def test_errors(namespace, capsys):
+    assert repl.run("1/0", namespace) == 1
+    err = capsys.readouterr().err
+    assert "ZeroDivisionError" in err
+    assert "repl.py" not in err
+    assert repl.run("x = (", namespace, "<test>") == 1
+    assert " in <test>:\n" in capsys.readouterr().err
+
+
+def test_exit(namespace, capsys):
+    with pytest.raises(SystemExit) as exc:
+        repl.run("import sys; sys.exit(3)", namespace)
+    assert repl.exit_code(exc.value) == 3
+    assert repl.exit_code(SystemExit()) == 0
+    assert repl.exit_code(SystemExit("bye")) == 1
+    assert capsys.readouterr().err == "bye\n"
+
+
+def test_source(namespace, tmp_path, capsys):
+    rc = tmp_path / "rc.oxy"
+    rc.write_text("greeting = 'hi'\n$EDITOR = 'vim'\n")
+    argv = sys.argv[:]
+    assert repl.source(rc, namespace) == 0
+    assert namespace["greeting"] == "hi"
+    assert ox.env["EDITOR"] == "vim"
+    assert sys.argv == argv
+    assert "__file__" not in namespace
+
+    broken = tmp_path / "broken.oxy"
+    broken.write_text("x = 1\n1/0\n")
+    assert repl.source(broken, namespace) == 1
+    assert f'File "{broken}", line 2' in capsys.readouterr().err
+    broken.write_text("x = (\n")
+    assert repl.source(broken, namespace) == 1
+    assert f" in {broken}:\n" in capsys.readouterr().err
+
+
+def test_complete(namespace):
+    repl.run("import os\nvalue = 1", namespace)
+    assert repl.complete("val", namespace) == ["value"]
+    assert "os.path" in repl.complete("os.pa", namespace)
+    assert repl.complete("pri", namespace) == ["print("]
+    assert repl.complete("missing.x", namespace) == []
+
+
+def test_prompt_fields(monkeypatch, capsys):
+    def broken():
+        raise RuntimeError("no jobs")
+
+    monkeypatch.setattr(
+        ox, "prompt_fields", {"jobs": lambda: 2, "host": "box", "none": lambda: None, "x": broken}
+    )
+    assert repl.prompt_fields(["jobs", "host", "none", "cwd"]) == {
+        "jobs": "2",
+        "host": "box",
+        "none": "",
+    }
+    assert repl.prompt_fields(["x"]) == {"x": ""}
+    assert "prompt field 'x' failed: RuntimeError('no jobs')" in capsys.readouterr().err